@@ -0,0 +1,185 @@
+// Parsing of BLE advertising data (the "data" field of SF/ST scan lines) into
+// AD structures, see Bluetooth Core Specification Supplement, Part A.
+
+use crate::i18n::tr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AdStructure {
+    Flags(u8),
+    ServiceUuids16 { complete: bool, uuids: Vec<u16> },
+    ServiceUuids32 { complete: bool, uuids: Vec<u32> },
+    ServiceUuids128 { complete: bool, uuids: Vec<[u8; 16]> },
+    LocalName { complete: bool, name: String },
+    TxPower(i8),
+    ServiceData16 { uuid: u16, data: Vec<u8> },
+    ManufacturerData { company_id: u16, data: Vec<u8> },
+    Other { ad_type: u8, data: Vec<u8> },
+}
+
+// Decode a hex string as sent by the dongle ("0201061BFF5B07...") into bytes.
+pub fn parse_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+pub fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+fn u16_le(b: &[u8]) -> u16 {
+    u16::from_le_bytes([b[0], b[1]])
+}
+
+// Split advertising data into its AD structures. Parsing stops at the first
+// structure running past the end of the data or at zero padding.
+pub fn parse_ad_structures(data: &[u8]) -> Vec<AdStructure> {
+    let mut result = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let len = data[pos] as usize;
+        if len == 0 || pos + 1 + len > data.len() {
+            break;
+        }
+        let ad_type = data[pos + 1];
+        let payload = &data[pos + 2..pos + 1 + len];
+        result.push(parse_ad_structure(ad_type, payload));
+        pos += 1 + len;
+    }
+    result
+}
+
+fn parse_ad_structure(ad_type: u8, payload: &[u8]) -> AdStructure {
+    match ad_type {
+        0x01 if payload.len() == 1 => AdStructure::Flags(payload[0]),
+        0x02 | 0x03 => AdStructure::ServiceUuids16 {
+            complete: ad_type == 0x03,
+            uuids: payload.chunks_exact(2).map(u16_le).collect(),
+        },
+        0x04 | 0x05 => AdStructure::ServiceUuids32 {
+            complete: ad_type == 0x05,
+            uuids: payload
+                .chunks_exact(4)
+                .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+                .collect(),
+        },
+        0x06 | 0x07 => AdStructure::ServiceUuids128 {
+            complete: ad_type == 0x07,
+            uuids: payload
+                .chunks_exact(16)
+                .map(|c| {
+                    let mut uuid = [0u8; 16];
+                    uuid.copy_from_slice(c);
+                    uuid.reverse(); // little endian on air
+                    uuid
+                })
+                .collect(),
+        },
+        0x08 | 0x09 => AdStructure::LocalName {
+            complete: ad_type == 0x09,
+            name: String::from_utf8_lossy(payload).to_string(),
+        },
+        0x0A if payload.len() == 1 => AdStructure::TxPower(payload[0] as i8),
+        0x16 if payload.len() >= 2 => AdStructure::ServiceData16 {
+            uuid: u16_le(payload),
+            data: payload[2..].to_vec(),
+        },
+        0xFF if payload.len() >= 2 => AdStructure::ManufacturerData {
+            company_id: u16_le(payload),
+            data: payload[2..].to_vec(),
+        },
+        _ => AdStructure::Other {
+            ad_type,
+            data: payload.to_vec(),
+        },
+    }
+}
+
+pub fn format_uuid128(uuid: &[u8; 16]) -> String {
+    let h = to_hex(uuid);
+    format!("{}-{}-{}-{}-{}", &h[0..8], &h[8..12], &h[12..16], &h[16..20], &h[20..32])
+}
+
+// Company identifiers of some manufacturers commonly seen around an office.
+pub fn company_name(id: u16) -> Option<&'static str> {
+    match id {
+        0x0006 => Some("Microsoft"),
+        0x000D => Some("Texas Instruments"),
+        0x000F => Some("Broadcom"),
+        0x004C => Some("Apple"),
+        0x0059 => Some("Nordic Semiconductor"),
+        0x0075 => Some("Samsung Electronics"),
+        0x0087 => Some("Garmin International"),
+        0x00D2 => Some("Dialog Semiconductor"),
+        0x00E0 => Some("Google"),
+        0x0131 => Some("Cypress Semiconductor"),
+        0x0157 => Some("Anhui Huami Information Technology"),
+        0x0171 => Some("Amazon.com Services"),
+        0x02E5 => Some("Espressif Systems"),
+        0x038F => Some("Xiaomi"),
+        0x0499 => Some("Ruuvi Innovations"),
+        0x075B => Some("Smart Sensor Devices"),
+        _ => None,
+    }
+}
+
+// Names of the advertising PDU types in the "type" field of scan lines.
+pub fn adv_type_name(adv_type: u8) -> &'static str {
+    match adv_type {
+        0 => "ADV_IND",
+        1 => "ADV_DIRECT_IND",
+        2 => "ADV_SCAN_IND",
+        3 => "ADV_NONCONN_IND",
+        4 => "SCAN_RSP",
+        _ => tr!("Unknown"),
+    }
+}
+
+impl AdStructure {
+    // Short human readable description, used in the scanner view.
+    pub fn describe(&self) -> String {
+        match self {
+            AdStructure::Flags(f) => {
+                let mut names = Vec::new();
+                if f & 0x01 != 0 { names.push("LE Limited Discoverable"); }
+                if f & 0x02 != 0 { names.push("LE General Discoverable"); }
+                if f & 0x04 != 0 { names.push("BR/EDR Not Supported"); }
+                if f & 0x08 != 0 { names.push("LE+BR/EDR Controller"); }
+                if f & 0x10 != 0 { names.push("LE+BR/EDR Host"); }
+                tr!("Flags 0x{} ({})", format!("{:02X}", f), names.join(", "))
+            }
+            AdStructure::ServiceUuids16 { uuids, .. } => tr!(
+                "16-bit services: {}",
+                uuids.iter().map(|u| format!("0x{:04X}", u)).collect::<Vec<_>>().join(", ")
+            ),
+            AdStructure::ServiceUuids32 { uuids, .. } => tr!(
+                "32-bit services: {}",
+                uuids.iter().map(|u| format!("0x{:08X}", u)).collect::<Vec<_>>().join(", ")
+            ),
+            AdStructure::ServiceUuids128 { uuids, .. } => tr!(
+                "128-bit services: {}",
+                uuids.iter().map(format_uuid128).collect::<Vec<_>>().join(", ")
+            ),
+            AdStructure::LocalName { complete, name } => {
+                if *complete { tr!("Complete name: {}", name) } else { tr!("Short name: {}", name) }
+            }
+            AdStructure::TxPower(p) => tr!("TX power: {} dBm", p),
+            AdStructure::ServiceData16 { uuid, data } => {
+                tr!("Service data 0x{}: {}", format!("{:04X}", uuid), to_hex(data))
+            }
+            AdStructure::ManufacturerData { company_id, data } => tr!(
+                "Manufacturer data 0x{} ({}): {}",
+                format!("{:04X}", company_id),
+                company_name(*company_id).unwrap_or(tr!("unknown")),
+                to_hex(data)
+            ),
+            AdStructure::Other { ad_type, data } => {
+                tr!("AD type 0x{}: {}", format!("{:02X}", ad_type), to_hex(data))
+            }
+        }
+    }
+}
@@ -0,0 +1,177 @@
+// Local HTTP API, built with the `http-api` feature and started with --http:
+//
+//     GET /api/sensors                            latest reading of every sensor
+//     GET /api/sensors/{id}/readings?from&to      stored history, unix times in seconds
+//     GET /api/live                               WebSocket, one JSON message per new reading
+//     GET /...                                    the web dashboard, when a web root is given
+//
+// The server runs on its own thread and runtime. `ApiState` is a sink of the
+// pipeline the serial task publishes to, the history is read from the files
+// written by the history store.
+
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, State};
+use axum::http::{header, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Deserialize;
+use tokio::sync::broadcast;
+use tracing::error;
+
+use crate::api_types::{ApiReading, ApiSample};
+use crate::decoder::Reading;
+use crate::history;
+use crate::i18n::tr;
+use crate::sinks::Sink;
+
+// Readings a slow WebSocket client may fall behind before it misses some.
+pub const LIVE_BUFFER: usize = 256;
+// History returned when no range is given.
+const DEFAULT_RANGE: i64 = 86_400;
+
+// Shared between the serial task, which publishes, and the server.
+#[derive(Clone)]
+pub struct ApiState {
+    latest: Arc<RwLock<HashMap<String, ApiReading>>>,
+    live: broadcast::Sender<ApiReading>,
+    history: PathBuf,
+}
+
+impl ApiState {
+    pub fn new(history: PathBuf) -> Self {
+        Self { latest: Arc::default(), live: broadcast::channel(LIVE_BUFFER).0, history }
+    }
+
+    // A new reading was decoded.
+    pub fn publish(&self, reading: &Reading) {
+        let reading = ApiReading::from(reading);
+        if let Ok(mut latest) = self.latest.write() {
+            latest.insert(reading.id.clone(), reading.clone());
+        }
+        // No receivers is not an error, nobody is listening.
+        self.live.send(reading).ok();
+    }
+}
+
+// The API gets its readings from the sink pipeline.
+impl Sink for ApiState {
+    fn name(&self) -> String {
+        "HTTP API".to_string()
+    }
+
+    fn write(&mut self, readings: &[Reading]) -> Result<(), String> {
+        readings.iter().for_each(|r| self.publish(r));
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Range {
+    from: Option<i64>,
+    to: Option<i64>,
+}
+
+async fn sensors(State(state): State<ApiState>) -> Json<Vec<ApiReading>> {
+    let mut latest: Vec<ApiReading> = state.latest.read().map(|l| l.values().cloned().collect()).unwrap_or_default();
+    latest.sort_by(|a, b| a.id.cmp(&b.id));
+    Json(latest)
+}
+
+async fn readings(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+    Query(range): Query<Range>,
+) -> Result<Json<Vec<ApiSample>>, (StatusCode, String)> {
+    let to = range.to.unwrap_or(i64::MAX);
+    let from = range.from.unwrap_or_else(|| chrono::Local::now().timestamp() - DEFAULT_RANGE);
+    match history::load_file(&history::sensor_file(&state.history, &id)) {
+        Ok(series) => Ok(Json(series.range(from..=to.max(from)).map(|(_, s)| ApiSample::from(s)).collect())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Err((StatusCode::NOT_FOUND, format!("No history for {}", id))),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+async fn live(ws: WebSocketUpgrade, State(state): State<ApiState>) -> Response {
+    let rx = state.live.subscribe();
+    ws.on_upgrade(move |socket| feed(socket, rx))
+}
+
+async fn feed(mut socket: WebSocket, mut rx: broadcast::Receiver<ApiReading>) {
+    loop {
+        match rx.recv().await {
+            Ok(reading) => {
+                let Ok(text) = serde_json::to_string(&reading) else {
+                    continue;
+                };
+                if socket.send(Message::Text(text.into())).await.is_err() {
+                    break; // the client went away
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+fn content_type(path: &FsPath) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()).unwrap_or("") {
+        "html" => "text/html; charset=utf-8",
+        "js" => "text/javascript",
+        "wasm" => "application/wasm",
+        "css" => "text/css",
+        "json" => "application/json",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "ico" => "image/x-icon",
+        _ => "application/octet-stream",
+    }
+}
+
+// A file of the web build (dx bundle --platform web), index.html for the root.
+async fn static_file(root: PathBuf, uri: Uri) -> Response {
+    let path = uri.path().trim_start_matches('/');
+    if path.split('/').any(|p| p == "..") {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let file = if path.is_empty() { root.join("index.html") } else { root.join(path) };
+    match tokio::fs::read(&file).await {
+        Ok(bytes) => ([(header::CONTENT_TYPE, content_type(&file))], bytes).into_response(),
+        Err(_) => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+pub async fn serve(addr: SocketAddr, state: ApiState, web_root: Option<PathBuf>) -> io::Result<()> {
+    let mut app = Router::new()
+        .route("/api/sensors", get(sensors))
+        .route("/api/sensors/{id}/readings", get(readings))
+        .route("/api/live", get(live));
+    if let Some(root) = web_root {
+        app = app.fallback(move |uri: Uri| static_file(root.clone(), uri));
+    }
+    let app = app.with_state(state);
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, app).await
+}
+
+// Run the server on a thread of its own, next to the UI.
+pub fn start(addr: SocketAddr, state: ApiState) {
+    std::thread::spawn(move || {
+        let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+            Ok(r) => r,
+            Err(e) => {
+                error!(target: "sink", "{}", tr!("Can not start the HTTP API: {}", e));
+                return;
+            }
+        };
+        if let Err(e) = runtime.block_on(serve(addr, state, None)) {
+            error!(target: "sink", "{}", tr!("HTTP API on {} stopped: {}", addr, e));
+        }
+    });
+}
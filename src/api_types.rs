@@ -0,0 +1,72 @@
+// JSON types of the HTTP API, shared by the server and the web dashboard.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::decoder::{Metric, Reading};
+use crate::history::Sample;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiReading {
+    pub id: String,
+    pub address: String,
+    pub model: String,
+    pub decoder: String,
+    pub t: i64,                             // unix time, seconds
+    pub rssi: Option<i16>,
+    pub values: BTreeMap<Metric, f64>,
+    #[serde(default)]
+    pub info: Vec<(String, String)>,
+}
+
+impl From<&Reading> for ApiReading {
+    fn from(r: &Reading) -> Self {
+        Self {
+            id: r.id.clone(),
+            address: r.address.clone(),
+            model: r.model.clone(),
+            decoder: r.decoder.clone(),
+            t: r.at.timestamp(),
+            rssi: r.rssi,
+            values: r.values.iter().copied().collect(),
+            info: r.info.clone(),
+        }
+    }
+}
+
+// Back into a reading for the dashboard components, the values come in the
+// order of `Metric`.
+impl From<ApiReading> for Reading {
+    fn from(r: ApiReading) -> Self {
+        Self {
+            id: r.id,
+            address: r.address,
+            model: r.model,
+            decoder: r.decoder,
+            at: DateTime::from_timestamp(r.t, 0).unwrap_or_default().with_timezone(&Local),
+            rssi: r.rssi,
+            values: r.values.into_iter().collect(),
+            info: r.info,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiSample {
+    pub t: i64,
+    pub values: BTreeMap<Metric, f64>,
+}
+
+impl From<&Sample> for ApiSample {
+    fn from(s: &Sample) -> Self {
+        Self { t: s.t, values: s.values.iter().copied().collect() }
+    }
+}
+
+impl From<ApiSample> for Sample {
+    fn from(s: ApiSample) -> Self {
+        Self { t: s.t, values: s.values.into_iter().collect() }
+    }
+}
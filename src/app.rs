@@ -0,0 +1,2280 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use dioxus::html::div;
+use dioxus::prelude::*;
+use dioxus::desktop::{Config, WindowBuilder, LogicalSize};
+use tokio::time::Duration;
+use tokio::sync::mpsc;
+use serde::{Deserialize, Serialize};
+use serde_json::{Result, Value};
+// use std::path::PathBuf;
+// use std::io::{self, Read, Write};
+// use std::thread::sleep;
+// use std::time::Duration;
+use tracing::{debug, error, info, info_span, warn, Instrument, Level};
+use crate::advertising;
+use crate::bleuio::*;
+use crate::diagnostics::SensorDiagnostics;
+use crate::floor_plan::{self, FloorPlan, PLAN_EXTENSIONS, PLAN_METRICS};
+use crate::console::{self, Console, Direction};
+use crate::dashboard::{self, HistoryChart, LanguageSelect, SensorPanel, UnitsSelect, HISTORY_RANGES};
+use crate::capture::{CaptureDirection, CaptureWriter};
+use crate::config::{AppConfig, KnownSensor, ScanConfig};
+use crate::decoder::{Metric, Reading};
+use crate::gatt::{self, GattClient, GattEvent, GattService};
+use crate::history::{self, HistoryStore, Stats};
+use crate::i18n::{self, tr, Language};
+use crate::layout::{DashboardLayout, GroupBy, PanelView, PlacedSensor, SortBy};
+use crate::logging::{LogBuffer, LogEntry};
+use crate::handshake::{Action, LinkState, Step};
+use crate::options::Options;
+use crate::scan_filter::ScanFilter;
+use crate::scan_schedule::ScanStatus;
+use crate::scanner::{Scanner, ScannerSort};
+use crate::serial::{Frontend, SerialLoop};
+use crate::sinks::SinkPipeline;
+use crate::transport::Transport;
+use crate::units::Units;
+
+const FAVICON: Asset = asset!("/assets/favicon.ico");
+// const HEADER_SVG: Asset = asset!("/assets/header.svg");
+const MAIN_CSS: Asset = asset!("/assets/main.css");
+
+static CSS: Asset = asset!("/assets/main.css");
+const TAILWIND_CSS: Asset = asset!("/assets/tailwind.css");
+
+// The pages selectable from the tab bar at the top of the window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Tab {
+    Dashboard,
+    FloorPlan,
+    Compare,
+    Scanner,
+    Console,
+    Gatt,
+    Dongle,
+}
+
+// Open the desktop window.
+pub fn launch(options: Options, log: LogBuffer) {
+    // 1. Define your window configuration
+    let window = WindowBuilder::new()
+        .with_title(tr!("Sensor Dashboard"))
+        .with_inner_size(LogicalSize::new(1100.0, 600.0)); // Width, Height
+
+    // Decoded readings go to the sinks of the settings and the API server.
+    let sinks = SinkPipeline::from_config(&AppConfig::load().sinks);
+    #[cfg(feature = "http-api")]
+    let sinks = {
+        let mut sinks = sinks;
+        if let Some(addr) = options.http {
+            let state = crate::api::ApiState::new(history::default_dir());
+            crate::api::start(addr, state.clone());
+            sinks.add(Box::new(state), crate::api::LIVE_BUFFER, crate::config::Overflow::DropOldest);
+        }
+        sinks
+    };
+    #[cfg(not(feature = "http-api"))]
+    if options.http.is_some() {
+        warn!("{}", tr!("Ignoring --http, built without the http-api feature"));
+    }
+
+    // 2. Launch with the custom config
+    let launcher = LaunchBuilder::new()
+        .with_cfg(Config::new().with_window(window))
+        .with_context(options)
+        .with_context(sinks)
+        .with_context(log);
+    launcher.launch(App);
+
+    // dioxus::launch(App);
+}
+
+#[component]
+fn App() -> Element {
+    let options = use_context::<Options>();
+    let transport = options.transport.clone();
+    let record = options.record.clone();
+    rsx! {
+        // Länka till den kompilerade filen (dx lägger den i assets/main.css som standard)
+        document::Stylesheet { href: CSS }
+
+        document::Link { rel: "icon", href: FAVICON }
+        document::Link { rel: "stylesheet", href: MAIN_CSS }
+        document::Link { rel: "stylesheet", href: TAILWIND_CSS }
+        Hero { transport, record }
+    }
+}
+
+// Store the latest reading of a sensor, returns true if the sensor was not seen before.
+fn add_reading(mut sens: Signal<HashMap<String, Reading>>, reading: Reading) -> bool {
+    sens.with_mut(|s| {
+        s.insert(reading.id.clone(), reading).is_none()
+    })
+}
+
+// The connection to the dongle as seen from the UI. All traffic is shown in the
+// console and, while recording, written to the capture file.
+struct Link {
+    console: Signal<Console>,
+    capture: Option<CaptureWriter>,
+    recording: Signal<Option<PathBuf>>,
+    status: DongleStatus,
+    scanner: Signal<Scanner>,
+    diag: Signal<HashMap<String, SensorDiagnostics>>,
+    readings: Signal<HashMap<String, Reading>>,
+    history: Signal<HistoryStore>,
+    events: tokio::sync::broadcast::Sender<Value>,
+}
+
+impl Link {
+    fn record_line(&mut self, direction: CaptureDirection, line: &str) {
+        if let Some(capture) = self.capture.as_mut() {
+            if capture.record(direction, line).is_err() {
+                self.stop_recording();
+            }
+        }
+    }
+
+    fn start_recording(&mut self, path: &Path) -> std::io::Result<()> {
+        let capture = CaptureWriter::create(path)?;
+        self.recording.set(Some(capture.path().to_path_buf()));
+        self.capture = Some(capture);
+        Ok(())
+    }
+
+    fn stop_recording(&mut self) {
+        self.capture = None;
+        self.recording.set(None);
+    }
+}
+
+impl Frontend for Link {
+    fn sent(&mut self, line: &str) {
+        self.console.with_mut(|c| c.push_tx(line));
+        self.record_line(CaptureDirection::Sent, line);
+    }
+
+    fn received(&mut self, line: &str, kind: Option<BleuIOResponseType>) {
+        self.console.with_mut(|c| c.push_rx(line, kind));
+        self.record_line(CaptureDirection::Received, line);
+    }
+
+    fn value(&mut self, kind: BleuIOResponseType, v: &Value) {
+        match kind {
+            BleuIOResponseType::ScanDataResponse | BleuIOResponseType::ScanFindDataResponse | BleuIOResponseType::ScanTargetResponse => {
+                self.scanner.with_mut(|s| s.update(v));
+            }
+            // For the GATT client.
+            BleuIOResponseType::EventResponse => {
+                self.events.send(v.clone()).ok();
+            }
+            _ => {}
+        }
+    }
+
+    fn action(&mut self, action: Action) {
+        let mut status = self.status;
+        match action {
+            Action::Send(_) => {}
+            Action::StateChanged(state) => status.state.set(state),
+            Action::StepDone(step) => status.settings.with_mut(|s| match step {
+                Step::EchoOff => s.echo = Some(false),
+                Step::Verbose => s.verbose = Some(true),
+                Step::ShowRssi => s.show_rssi = Some(true),
+                Step::Info => {}
+            }),
+            Action::Compatibility(compat) => status.compatibility.set(Some(compat)),
+        }
+    }
+
+    fn ticked(&mut self, scan: ScanStatus) {
+        let mut current = self.status.scan;
+        if *current.peek() != scan {
+            current.set(scan);
+        }
+    }
+
+    fn reading(&mut self, reading: &Reading, rssi: Option<i16>) {
+        add_diagnostics(self.diag, &reading.id, rssi);
+        if let Err(e) = self.history.write().record(reading) {
+            warn!(target: "history", sensor = %reading.id, "{}", tr!("Can not store history: {}", e));
+        }
+        if add_reading(self.readings, reading.clone()) {
+            let n = self.readings.read().len();
+            info!(target: "decode", sensor = %reading.id, model = %reading.model, "{}", tr!("New sensor, {} found", n));
+        }
+    }
+
+    fn record(&mut self, path: Option<PathBuf>) {
+        let Some(path) = path else {
+            self.stop_recording();
+            return;
+        };
+        match self.start_recording(&path) {
+            Ok(()) => info!(target: "serial", "{}", tr!("Recording to {}", path.display())),
+            Err(e) => warn!(target: "serial", "{}", tr!("Can not record to {}: {}", path.display(), e)),
+        }
+    }
+}
+
+fn add_diagnostics(mut diag: Signal<HashMap<String, SensorDiagnostics>>, id: &str, rssi: Option<i16>) {
+    diag.with_mut(|d| d.entry(id.to_string()).or_default().record(rssi));
+}
+
+// State of the dongle shared between the serial task and the UI.
+#[derive(Clone, Copy)]
+struct DongleStatus {
+    state: Signal<LinkState>,
+    settings: Signal<DongleSettings>,
+    compatibility: Signal<Option<Compatibility>>,
+    scan: Signal<ScanStatus>,
+}
+
+#[component]
+fn HistoryPanel(history: Signal<HistoryStore>, reading: Reading) -> Element {
+    let mut expanded = use_signal(|| false);
+    let mut metric = use_signal(|| reading.values.first().map(|(m, _)| *m));
+    let mut range = use_signal(|| HISTORY_RANGES[0].1);
+
+    let since = chrono::Local::now().timestamp() - range();
+    let chart = metric().map(|m| (m, history.read().segments(&reading.id, m, since, 3 * history::LIVE_INTERVAL)));
+    let metrics: Vec<_> = reading.values.iter().map(|(m, _)| *m).collect();
+
+    // The shown range with all metrics, as CSV in the format of the language in use.
+    let export_id = reading.id.clone();
+    let export_metrics = metrics.clone();
+    let export = move |_| {
+        let id = export_id.clone();
+        let metrics = export_metrics.clone();
+        async move {
+            let since = chrono::Local::now().timestamp() - range();
+            let csv = history::to_csv(history.read().samples(&id, since), &metrics);
+            let name: String = id.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+            let file_name = format!("{}-{}.csv", name, chrono::Local::now().format("%Y%m%d-%H%M%S"));
+            if let Some(file) = rfd::AsyncFileDialog::new().set_file_name(file_name).save_file().await {
+                match std::fs::write(file.path(), csv) {
+                    Ok(()) => info!(target: "history", sensor = %id, "{}", tr!("Exported the history to {}", file.path().display())),
+                    Err(e) => warn!(target: "history", sensor = %id, "{}", tr!("Can not export the history to {}: {}", file.path().display(), e)),
+                }
+            }
+        }
+    };
+
+    rsx! {
+        div {
+            class: "px-4 py-2 bg-green-900 text-white text-sm",
+            div {
+                class: "flex gap-2",
+                button {
+                    class: "underline",
+                    onclick: move |_| expanded.toggle(),
+                    {if expanded() { tr!("Hide history") } else { tr!("History") }}
+                }
+                if expanded() {
+                    button { class: "underline", onclick: export, {tr!("Export CSV")} }
+                }
+            }
+            if expanded() {
+                div {
+                    class: "flex gap-2 mt-2",
+                    for m in metrics {
+                        button {
+                            class: if metric() == Some(m) { "border px-1 rounded-md bg-green-700" } else { "border px-1 rounded-md" },
+                            onclick: move |_| metric.set(Some(m)),
+                            "{m.label()}"
+                        }
+                    }
+                    span { class: "flex-1" }
+                    for (label, seconds) in HISTORY_RANGES {
+                        button {
+                            class: if range() == seconds { "border px-1 rounded-md bg-green-700" } else { "border px-1 rounded-md" },
+                            onclick: move |_| range.set(seconds),
+                            {tr!(label)}
+                        }
+                    }
+                }
+                if let Some((metric, segments)) = chart {
+                    HistoryChart { metric, segments, since }
+                }
+            }
+        }
+    }
+}
+
+// Draws the RSSI history as an SVG polyline, -100 dBm at the bottom and -30 dBm at the top.
+fn rssi_polyline(diagnostics: &SensorDiagnostics, width: f64, height: f64) -> String {
+    let samples: Vec<_> = diagnostics.history().filter_map(|s| Some((s.at, s.rssi?))).collect();
+    let (Some((first, _)), Some((last, _))) = (samples.first(), samples.last()) else {
+        return String::new();
+    };
+    let span = last.saturating_duration_since(*first).as_secs_f64().max(1.0);
+    samples
+        .iter()
+        .map(|(at, rssi)| {
+            let x = at.saturating_duration_since(*first).as_secs_f64() / span * width;
+            let level = ((*rssi as f64 + 100.0) / 70.0).clamp(0.0, 1.0);
+            format!("{:.1},{:.1}", x, height - level * height)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[component]
+fn DiagnosticsPanel(diagnostics: SensorDiagnostics) -> Element {
+    let mut expanded = use_signal(|| false);
+    let dash = "–".to_string();
+    let last = diagnostics.last_rssi().map(|r| format!("{} dBm", r)).unwrap_or(dash.clone());
+    let avg = diagnostics.avg_rssi().map(|r| format!("{:.0} dBm", r)).unwrap_or(dash.clone());
+    let range = match (diagnostics.min_rssi(), diagnostics.max_rssi()) {
+        (Some(min), Some(max)) => format!("{} / {} dBm", min, max),
+        _ => dash.clone(),
+    };
+    let rate = diagnostics.packet_rate().map(|r| format!("{:.1} /min", r)).unwrap_or(dash.clone());
+    let interval = diagnostics.adv_interval().map(|i| format!("{:.1} s", i.as_secs_f64())).unwrap_or(dash.clone());
+    let loss = diagnostics.estimated_loss().map(|l| format!("{:.0} %", l * 100.0)).unwrap_or(dash);
+    let uptime = diagnostics.first_seen().elapsed().as_secs() / 60;
+
+    rsx! {
+        div {
+            class: "px-4 py-2 bg-green-900 rounded-b-lg text-white text-sm",
+            button {
+                class: "underline",
+                onclick: move |_| expanded.toggle(),
+                {if expanded() { tr!("Hide diagnostics") } else { tr!("Diagnostics") }}
+            }
+            if expanded() {
+                div {
+                    style: "display: grid; grid-template-columns: repeat(8, 1fr); gap: 4px 20px;",
+                    div { style: "font-weight: bold;", {tr!("RSSI")} }
+                    div { style: "font-weight: bold;", {tr!("Average")} }
+                    div { style: "font-weight: bold;", {tr!("Min / Max")} }
+                    div { style: "font-weight: bold;", {tr!("Packets")} }
+                    div { style: "font-weight: bold;", {tr!("Rate")} }
+                    div { style: "font-weight: bold;", {tr!("Interval")} }
+                    div { style: "font-weight: bold;", {tr!("Est. loss")} }
+                    div { style: "font-weight: bold;", {tr!("Seen for")} }
+
+                    div { "{last}" }
+                    div { "{avg}" }
+                    div { "{range}" }
+                    div { "{diagnostics.packets()}" }
+                    div { "{rate}" }
+                    div { "{interval}" }
+                    div { "{loss}" }
+                    div { {tr!("{} min", uptime)} }
+                }
+                svg {
+                    class: "mt-2",
+                    width: "100%",
+                    height: "60",
+                    view_box: "0 0 600 60",
+                    preserve_aspect_ratio: "none",
+                    polyline {
+                        points: rssi_polyline(&diagnostics, 600.0, 60.0),
+                        fill: "none",
+                        stroke: "white",
+                        stroke_width: "1.5",
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Text colour for a console line, by direction and response type.
+fn console_color(direction: Direction, kind: Option<BleuIOResponseType>) -> &'static str {
+    match (direction, kind) {
+        (Direction::Tx, _) => "#7dd3fc",
+        (Direction::Rx, None) => "#ffffff",
+        (Direction::Rx, Some(kind)) => match kind {
+            BleuIOResponseType::CommandResponse => "#9ca3af",
+            BleuIOResponseType::AcknowledgementResponse => "#fde047",
+            BleuIOResponseType::ReplyResponse => "#86efac",
+            BleuIOResponseType::EndResponse => "#9ca3af",
+            BleuIOResponseType::ScanDataResponse
+            | BleuIOResponseType::ScanFindDataResponse
+            | BleuIOResponseType::ScanTargetResponse => "#c4b5fd",
+            BleuIOResponseType::ScanEndedResponse => "#f9a8d4",
+            BleuIOResponseType::EventResponse => "#fdba74",
+            BleuIOResponseType::UnknownResponse => "#fca5a5",
+        },
+    }
+}
+
+// Number of console lines rendered, the transcript always contains everything.
+const CONSOLE_VISIBLE_LINES: usize = 500;
+
+#[component]
+fn ConsoleView(console: Signal<Console>, recording: Signal<Option<PathBuf>>) -> Element {
+    let serial = use_coroutine_handle::<BleuIOCommand>();
+    let mut input = use_signal(String::new);
+    let mut history = use_signal(Vec::<String>::new);
+    let mut history_pos = use_signal(|| None::<usize>);
+    let mut show_log = use_signal(|| false);
+    let mut status = use_signal(String::new);
+
+    let mut submit = move || {
+        let cmd = input().trim().to_string();
+        if cmd.is_empty() {
+            return;
+        }
+        serial.send(BleuIOCommand::Raw(cmd.clone()));
+        history.with_mut(|h| {
+            h.retain(|c| c != &cmd);
+            h.push(cmd);
+        });
+        history_pos.set(None);
+        input.set(String::new());
+    };
+
+    let save_transcript = move |_| async move {
+        let transcript = console.read().transcript();
+        let file_name = format!("bleuio-{}.txt", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+        if let Some(file) = rfd::AsyncFileDialog::new().set_file_name(file_name).save_file().await {
+            match std::fs::write(file.path(), transcript) {
+                Ok(()) => status.set(tr!("Saved {}", file.path().display())),
+                Err(e) => status.set(tr!("Error: {}", e)),
+            }
+        }
+    };
+
+    let toggle_recording = move |_| async move {
+        if recording.read().is_some() {
+            serial.send(BleuIOCommand::StopRecording);
+            return;
+        }
+        let file_name = format!("bleuio-{}.capture", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+        if let Some(file) = rfd::AsyncFileDialog::new().set_file_name(file_name).save_file().await {
+            serial.send(BleuIOCommand::StartRecording(file.path().to_path_buf()));
+        }
+    };
+
+    rsx! {
+        div {
+            class: "flex flex-col gap-2 p-4",
+            div {
+                style: "background: rgb(31, 28, 28); height: 400px; overflow-y: scroll; font-family: monospace; font-size: 12px;",
+                for line in console.read().tail(CONSOLE_VISIBLE_LINES) {
+                    {
+                        let arrow = if line.direction == Direction::Tx { "→" } else { "←" };
+                        rsx! {
+                            div {
+                                style: "color: {console_color(line.direction, line.kind)}; white-space: pre;",
+                                "{line.timestamp()} {arrow} {line.text}"
+                            }
+                        }
+                    }
+                }
+            }
+            div {
+                class: "flex gap-2",
+                input {
+                    class: "border p-1 rounded-md grow",
+                    style: "background: rgb(31, 28, 28); font-family: monospace;",
+                    placeholder: tr!("AT command"),
+                    list: "at-commands",
+                    value: "{input}",
+                    oninput: move |e| {
+                        input.set(e.value());
+                        history_pos.set(None);
+                    },
+                    onkeydown: move |e| match e.key() {
+                        Key::Enter => submit(),
+                        Key::Tab => {
+                            e.prevent_default();
+                            if let Some(completed) = console::complete(&input()) {
+                                input.set(completed);
+                            }
+                        }
+                        Key::ArrowUp => {
+                            let len = history.read().len();
+                            if len > 0 {
+                                let pos = history_pos().map_or(len - 1, |p: usize| p.saturating_sub(1));
+                                history_pos.set(Some(pos));
+                                input.set(history.read()[pos].clone());
+                            }
+                        }
+                        Key::ArrowDown => {
+                            if let Some(pos) = history_pos() {
+                                if pos + 1 < history.read().len() {
+                                    history_pos.set(Some(pos + 1));
+                                    input.set(history.read()[pos + 1].clone());
+                                } else {
+                                    history_pos.set(None);
+                                    input.set(String::new());
+                                }
+                            }
+                        }
+                        _ => {}
+                    },
+                }
+                datalist {
+                    id: "at-commands",
+                    for cmd in KNOWN_COMMANDS {
+                        option { value: "{cmd}" }
+                    }
+                }
+                button {
+                    class: "border p-1 rounded-md bg-gray-500",
+                    onclick: move |_| submit(),
+                    {tr!("Send")}
+                }
+            }
+            div {
+                class: "flex gap-2 items-center",
+                button {
+                    class: "border p-1 rounded-md bg-gray-500",
+                    onclick: move |_| console.with_mut(|c| c.clear()),
+                    {tr!("Clear console")}
+                }
+                button {
+                    class: "border p-1 rounded-md bg-gray-500",
+                    onclick: save_transcript,
+                    {tr!("Save transcript")}
+                }
+                button {
+                    class: "border p-1 rounded-md bg-gray-500",
+                    onclick: toggle_recording,
+                    {if recording.read().is_some() { tr!("Stop recording") } else { tr!("Record capture") }}
+                }
+                button {
+                    class: "border p-1 rounded-md bg-gray-500",
+                    onclick: move |_| show_log.toggle(),
+                    {if show_log() { tr!("Hide log") } else { tr!("Show log") }}
+                }
+                span { "{status}" }
+                if let Some(path) = recording() {
+                    span { style: "color: #fca5a5;", {tr!("● Recording to {}", path.display())} }
+                }
+            }
+            if show_log() {
+                LogView {}
+            }
+        }
+    }
+}
+
+// Number of log events rendered.
+const LOG_VISIBLE_LINES: usize = 500;
+
+fn log_color(level: Level) -> &'static str {
+    match level {
+        Level::ERROR => "#fca5a5",
+        Level::WARN => "#fcd34d",
+        Level::INFO => "#e5e7eb",
+        Level::DEBUG => "#93c5fd",
+        Level::TRACE => "#9ca3af",
+    }
+}
+
+// The latest log events, filtered by level and text.
+#[component]
+fn LogView() -> Element {
+    let buffer = use_context::<LogBuffer>();
+    let mut entries = use_signal(|| buffer.entries());
+    let mut level = use_signal(|| Level::INFO);
+    let mut search = use_signal(String::new);
+
+    let buffer_for_task = buffer.clone();
+    use_future(move || {
+        let buffer = buffer_for_task.clone();
+        async move {
+            loop {
+                buffer.changed().await;
+                // Events often come in bursts, update a few times a second at most.
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                entries.set(buffer.entries());
+            }
+        }
+    });
+
+    let shown: Vec<LogEntry> = {
+        let entries = entries.read();
+        let search = search();
+        let matching: Vec<&LogEntry> = entries.iter().filter(|e| e.level <= level() && e.contains(&search)).collect();
+        let skip = matching.len().saturating_sub(LOG_VISIBLE_LINES);
+        matching.into_iter().skip(skip).cloned().collect()
+    };
+
+    rsx! {
+        div {
+            class: "flex flex-col gap-2",
+            div {
+                class: "flex gap-2 items-center",
+                select {
+                    class: "border p-1 rounded-md",
+                    style: "background: rgb(31, 28, 28);",
+                    value: "{level}",
+                    onchange: move |e| level.set(e.value().parse().unwrap_or(Level::INFO)),
+                    for l in [Level::ERROR, Level::WARN, Level::INFO, Level::DEBUG, Level::TRACE] {
+                        option { value: "{l}", "{l}" }
+                    }
+                }
+                input {
+                    class: "border p-1 rounded-md grow",
+                    style: "background: rgb(31, 28, 28);",
+                    placeholder: tr!("Search"),
+                    value: "{search}",
+                    oninput: move |e| search.set(e.value()),
+                }
+                button {
+                    class: "border p-1 rounded-md bg-gray-500",
+                    onclick: move |_| buffer.clear(),
+                    {tr!("Clear log")}
+                }
+            }
+            div {
+                style: "background: rgb(31, 28, 28); height: 300px; overflow-y: scroll; margin-bottom: 10px; font-family: monospace; font-size: 12px;",
+                for entry in shown {
+                    div {
+                        style: "color: {log_color(entry.level)}; white-space: pre-wrap;",
+                        "{entry.timestamp()} {entry.level:>5} {entry.target} {entry.spans} {entry.message}"
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn ScannerView(scanner: Signal<Scanner>) -> Element {
+    let serial = use_coroutine_handle::<BleuIOCommand>();
+    let mut filter = use_signal(String::new);
+    let mut sort = use_signal(|| ScannerSort::Rssi);
+    let mut data_filter = use_signal(String::new);
+    let mut selected = use_signal(|| None::<String>);
+
+    let devices = scanner.read().list(&filter(), sort());
+    let total = scanner.read().len();
+
+    rsx! {
+        div {
+            class: "flex flex-col gap-2 p-4",
+            div {
+                class: "flex gap-2 items-center",
+                button {
+                    class: "border p-1 rounded-md bg-gray-500",
+                    onclick: move |_| {
+                        serial.send(BleuIOCommand::StopScan);
+                        serial.send(BleuIOCommand::AtGapScan);
+                    },
+                    {tr!("Scan all devices")}
+                }
+                input {
+                    class: "border p-1 rounded-md",
+                    style: "background: rgb(31, 28, 28); font-family: monospace;",
+                    placeholder: tr!("hex, e.g. FF4C00"),
+                    value: "{data_filter}",
+                    oninput: move |e| data_filter.set(e.value().to_uppercase()),
+                }
+                button {
+                    class: "border p-1 rounded-md bg-gray-500",
+                    disabled: advertising::parse_hex(&data_filter()).is_none_or(|d| d.is_empty()),
+                    onclick: move |_| {
+                        serial.send(BleuIOCommand::StopScan);
+                        serial.send(BleuIOCommand::FindScanData(data_filter()));
+                    },
+                    {tr!("Scan advertising data")}
+                }
+                button {
+                    class: "border p-1 rounded-md bg-gray-500",
+                    onclick: move |_| {
+                        serial.send(BleuIOCommand::StopScan);
+                        serial.send(BleuIOCommand::AtFindscandata);
+                    },
+                    {tr!("Back to HibouAir scan")}
+                }
+                button {
+                    class: "border p-1 rounded-md bg-gray-500",
+                    onclick: move |_| scanner.with_mut(|s| s.clear()),
+                    {tr!("Clear")}
+                }
+            }
+            div {
+                class: "flex gap-2 items-center",
+                input {
+                    class: "border p-1 rounded-md grow",
+                    style: "background: rgb(31, 28, 28);",
+                    placeholder: tr!("Filter on address, name or manufacturer"),
+                    value: "{filter}",
+                    oninput: move |e| filter.set(e.value()),
+                }
+                span { {tr!("{} of {} devices", devices.len(), total)} }
+            }
+            table {
+                class: "text-sm",
+                style: "width: 100%; font-family: monospace;",
+                thead {
+                    tr {
+                        style: "text-align: left; cursor: pointer;",
+                        th { onclick: move |_| sort.set(ScannerSort::Address), {tr!("Address")} }
+                        th { onclick: move |_| sort.set(ScannerSort::Name), {tr!("Name")} }
+                        th { onclick: move |_| sort.set(ScannerSort::Rssi), {tr!("RSSI")} }
+                        th { {tr!("Type")} }
+                        th { {tr!("Manufacturer")} }
+                        th { {tr!("TX power")} }
+                        th { {tr!("Packets")} }
+                        th { onclick: move |_| sort.set(ScannerSort::LastSeen), {tr!("Last seen")} }
+                    }
+                }
+                tbody {
+                    for device in devices {
+                        {
+                            let addr = device.addr.clone();
+                            let is_selected = selected.read().as_deref() == Some(addr.as_str());
+                            let addr_type = match device.addr_type {
+                                Some(0) => tr!(" (public)"),
+                                Some(1) => tr!(" (random)"),
+                                _ => "",
+                            };
+                            let name = device.name.clone().unwrap_or_default();
+                            let rssi = device.rssi.map(|r| format!("{} dBm", r)).unwrap_or_default();
+                            let adv_type = device.adv_type.map(advertising::adv_type_name).unwrap_or_default();
+                            let company = match device.company_id() {
+                                Some(id) => format!("{} (0x{:04X})", device.company().unwrap_or(tr!("Unknown")), id),
+                                None => String::new(),
+                            };
+                            let tx_power = device.tx_power().map(|p| format!("{} dBm", p)).unwrap_or_default();
+                            let seen = device.last_seen.elapsed().as_secs();
+                            rsx! {
+                                tr {
+                                    key: "{addr}",
+                                    style: "cursor: pointer;",
+                                    class: if is_selected { "bg-gray-700" } else { "" },
+                                    onclick: move |_| {
+                                        let addr = addr.clone();
+                                        selected.with_mut(|s| *s = if s.as_deref() == Some(addr.as_str()) { None } else { Some(addr) });
+                                    },
+                                    td { "{device.addr}{addr_type}" }
+                                    td { "{name}" }
+                                    td { "{rssi}" }
+                                    td { "{adv_type}" }
+                                    td { "{company}" }
+                                    td { "{tx_power}" }
+                                    td { "{device.packets}" }
+                                    td { {tr!("{} s ago", seen)} }
+                                }
+                                if is_selected {
+                                    tr {
+                                        td {
+                                            colspan: "8",
+                                            class: "p-2",
+                                            if device.ad.is_empty() {
+                                                div { {tr!("No advertising data received, try scanning for advertising data.")} }
+                                            }
+                                            for ad in device.ad.iter() {
+                                                div { "{ad.describe()}" }
+                                            }
+                                            if !device.data.is_empty() {
+                                                div { style: "color: #9ca3af;", {tr!("Raw: {}", advertising::to_hex(&device.data))} }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Hex and, if printable, ASCII form of a characteristic value.
+fn format_value(value: &[u8]) -> String {
+    let hex = advertising::to_hex(value);
+    if !value.is_empty() && value.iter().all(|b| b.is_ascii_graphic() || *b == b' ') {
+        format!("{} \"{}\"", hex, String::from_utf8_lossy(value))
+    } else {
+        hex
+    }
+}
+
+#[component]
+fn GattView(scanner: Signal<Scanner>) -> Element {
+    let client = use_context::<GattClient>();
+    let mut addr = use_signal(String::new);
+    let mut connected = use_signal(|| false);
+    let mut busy = use_signal(|| false);
+    let mut status = use_signal(String::new);
+    let mut services = use_signal(Vec::<GattService>::new);
+    let mut values = use_signal(HashMap::<u16, String>::new);
+    let mut write_input = use_signal(HashMap::<u16, String>::new);
+    let mut notifications = use_signal(Vec::<String>::new);
+
+    // Follow connection events and notifications for as long as the page is shown.
+    let events_client = client.clone();
+    use_future(move || {
+        let mut events = events_client.events();
+        async move {
+            loop {
+                let v = match events.recv().await {
+                    Ok(v) => v,
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(_) => break,
+                };
+                match gatt::parse_event(&v) {
+                    Some(GattEvent::Connected(a)) => {
+                        connected.set(true);
+                        status.set(tr!("Connected {}", a));
+                    }
+                    Some(GattEvent::Disconnected) => {
+                        connected.set(false);
+                        status.set(tr!("Disconnected").to_string());
+                    }
+                    Some(GattEvent::Notification(n)) => {
+                        let line = format!("{} 0x{:04X}: {}", chrono::Local::now().format("%H:%M:%S"), n.handle, format_value(&n.value));
+                        notifications.with_mut(|list| {
+                            list.insert(0, line);
+                            list.truncate(200);
+                        });
+                        values.with_mut(|m| { m.insert(n.handle, format_value(&n.value)); });
+                    }
+                    None => {}
+                }
+            }
+        }
+    });
+
+    // Devices from the scanner that accept connections, strongest first.
+    let candidates: Vec<(String, String)> = scanner
+        .read()
+        .list("", ScannerSort::Rssi)
+        .into_iter()
+        .filter(|d| matches!(d.adv_type, Some(0) | Some(1) | None))
+        .map(|d| {
+            let value = format!("{}{}", d.addr_type.map(|t| format!("[{}]", t)).unwrap_or_default(), d.addr);
+            let label = format!("{} {}", d.addr, d.name.clone().unwrap_or_default());
+            (value, label)
+        })
+        .collect();
+
+    let connect_client = client.clone();
+    let disconnect_client = client.clone();
+    let discover_client = client.clone();
+
+    rsx! {
+        div {
+            class: "flex flex-col gap-2 p-4",
+            div {
+                class: "flex gap-2",
+                input {
+                    class: "border p-1 rounded-md bg-gray-800 w-72",
+                    placeholder: "[0]C8:47:8C:00:00:01",
+                    list: "gatt-devices",
+                    value: "{addr}",
+                    oninput: move |e| addr.set(e.value()),
+                }
+                datalist {
+                    id: "gatt-devices",
+                    for (value, label) in candidates {
+                        option { value: "{value}", "{label}" }
+                    }
+                }
+                button {
+                    class: "border p-1 rounded-md",
+                    disabled: busy() || connected() || addr().trim().is_empty(),
+                    onclick: move |_| {
+                        let client = connect_client.clone();
+                        let target = addr().trim().to_string();
+                        busy.set(true);
+                        status.set(tr!("Connecting to {}...", target));
+                        spawn(async move {
+                            match client.connect(&target).await {
+                                Ok(()) => {
+                                    connected.set(true);
+                                    status.set(tr!("Connected {}", target));
+                                }
+                                Err(e) => status.set(tr!("Connect failed: {}", e)),
+                            }
+                            busy.set(false);
+                        });
+                    },
+                    {tr!("Connect")}
+                }
+                button {
+                    class: "border p-1 rounded-md",
+                    disabled: busy() || !connected(),
+                    onclick: move |_| {
+                        let client = disconnect_client.clone();
+                        spawn(async move {
+                            if let Err(e) = client.disconnect().await {
+                                status.set(tr!("Disconnect failed: {}", e));
+                            }
+                            connected.set(false);
+                            services.set(Vec::new());
+                            values.set(HashMap::new());
+                        });
+                    },
+                    {tr!("Disconnect")}
+                }
+                button {
+                    class: "border p-1 rounded-md",
+                    disabled: busy() || !connected(),
+                    onclick: move |_| {
+                        let client = discover_client.clone();
+                        busy.set(true);
+                        spawn(async move {
+                            match client.discover().await {
+                                Ok(found) => {
+                                    status.set(tr!("{} services", found.len()));
+                                    services.set(found);
+                                }
+                                Err(e) => status.set(tr!("Discovery failed: {}", e)),
+                            }
+                            busy.set(false);
+                        });
+                    },
+                    {tr!("Discover services")}
+                }
+                span { class: "p-1", "{status}" }
+            }
+
+            for service in services.read().iter() {
+                div {
+                    key: "{service.uuid}",
+                    class: "border rounded-md p-2",
+                    div { class: "font-bold", {tr!("Service {}", service.uuid)} }
+                    table {
+                        class: "text-left",
+                        for c in service.characteristics.iter() {
+                            {
+                                let handle = c.handle;
+                                let value = values.read().get(&handle).cloned().unwrap_or_default();
+                                let input = write_input.read().get(&handle).cloned().unwrap_or_default();
+                                let read_client = client.clone();
+                                let write_client = client.clone();
+                                let notify_client = client.clone();
+                                rsx! {
+                                    tr {
+                                        key: "{handle}",
+                                        td { class: "pr-4", "{c.uuid}" }
+                                        td { class: "pr-4", "0x{handle:04X}" }
+                                        td { class: "pr-4", "{c.properties}" }
+                                        td {
+                                            class: "pr-4",
+                                            if c.can("READ") {
+                                                button {
+                                                    class: "border px-1 rounded-md",
+                                                    onclick: move |_| {
+                                                        let client = read_client.clone();
+                                                        spawn(async move {
+                                                            match client.read(handle).await {
+                                                                Ok(v) => { values.with_mut(|m| { m.insert(handle, format_value(&v)); }); }
+                                                                Err(e) => status.set(tr!("Read 0x{} failed: {}", format!("{:04X}", handle), e)),
+                                                            }
+                                                        });
+                                                    },
+                                                    {tr!("Read")}
+                                                }
+                                            }
+                                            if c.can("NOTIFY") || c.can("INDICATE") {
+                                                button {
+                                                    class: "border px-1 rounded-md",
+                                                    onclick: move |_| {
+                                                        let client = notify_client.clone();
+                                                        spawn(async move {
+                                                            match client.subscribe(handle).await {
+                                                                Ok(()) => status.set(tr!("Subscribed to 0x{}", format!("{:04X}", handle))),
+                                                                Err(e) => status.set(tr!("Subscribe 0x{} failed: {}", format!("{:04X}", handle), e)),
+                                                            }
+                                                        });
+                                                    },
+                                                    {tr!("Subscribe")}
+                                                }
+                                            }
+                                        }
+                                        td { class: "pr-4 font-mono", "{value}" }
+                                        td {
+                                            if c.can("WRITE") {
+                                                input {
+                                                    class: "border px-1 rounded-md bg-gray-800 w-40 font-mono",
+                                                    placeholder: "hex",
+                                                    value: "{input}",
+                                                    oninput: move |e| { write_input.with_mut(|m| { m.insert(handle, e.value()); }); },
+                                                }
+                                                button {
+                                                    class: "border px-1 rounded-md",
+                                                    onclick: move |_| {
+                                                        let client = write_client.clone();
+                                                        let hex = write_input.read().get(&handle).cloned().unwrap_or_default().replace(' ', "");
+                                                        let Some(bytes) = advertising::parse_hex(&hex) else {
+                                                            status.set(tr!("Not a hex value: {}", hex));
+                                                            return;
+                                                        };
+                                                        spawn(async move {
+                                                            match client.write(handle, &bytes).await {
+                                                                Ok(()) => status.set(tr!("Wrote {} bytes to 0x{}", bytes.len(), format!("{:04X}", handle))),
+                                                                Err(e) => status.set(tr!("Write 0x{} failed: {}", format!("{:04X}", handle), e)),
+                                                            }
+                                                        });
+                                                    },
+                                                    {tr!("Write")}
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            if !notifications.read().is_empty() {
+                div {
+                    class: "border rounded-md p-2 font-mono",
+                    div { class: "font-bold", {tr!("Notifications")} }
+                    for (i, line) in notifications.read().iter().enumerate() {
+                        div { key: "{i}", "{line}" }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn on_off(v: Option<bool>) -> String {
+    match v {
+        Some(true) => tr!("on").to_string(),
+        Some(false) => tr!("off").to_string(),
+        None => tr!("unknown").to_string(),
+    }
+}
+
+#[component]
+fn DongleView(settings: Signal<DongleSettings>, compatibility: Signal<Option<Compatibility>>, mut config: Signal<AppConfig>) -> Element {
+    let dongle = use_context::<BleuIOClient>();
+    let mut info = use_signal(|| None::<DongleInfo>);
+    let mut status = use_signal(String::new);
+    let mut busy = use_signal(|| false);
+    let mut show_all = use_signal(|| false);
+    let mut confirm_reset = use_signal(|| false);
+    let mut interval_input = use_signal(|| settings.peek().scan_interval.map(|v| v.to_string()).unwrap_or_default());
+    let mut window_input = use_signal(|| settings.peek().scan_window.map(|v| v.to_string()).unwrap_or_default());
+    let mut power_input = use_signal(|| settings.peek().tx_power.map(|v| v.to_string()).unwrap_or_default());
+    let mut duration_input = use_signal(|| config.peek().scan.duration.map(|v| v.to_string()).unwrap_or_default());
+    let mut pause_input = use_signal(|| config.peek().scan.pause.to_string());
+
+    let refresh_client = dongle.clone();
+    let mut refresh = move || {
+        let client = refresh_client.clone();
+        busy.set(true);
+        spawn(async move {
+            match client.info().await {
+                Ok(i) => {
+                    info.set(Some(i));
+                    status.set(String::new());
+                }
+                Err(e) => status.set(tr!("Could not read dongle information: {}", e)),
+            }
+            busy.set(false);
+        });
+    };
+    use_hook(refresh.clone());
+
+    let scan_client = dongle.clone();
+    let apply_scan = move |_| {
+        let parse = |s: String| s.trim().parse::<u16>().ok().filter(|v| (SCAN_TIMING_RANGE.0..=SCAN_TIMING_RANGE.1).contains(v));
+        let (Some(interval), Some(window)) = (parse(interval_input()), parse(window_input())) else {
+            status.set(tr!("Scan interval and window must be between {} and {} ms", SCAN_TIMING_RANGE.0, SCAN_TIMING_RANGE.1));
+            return;
+        };
+        if window > interval {
+            status.set(tr!("The scan window can not be longer than the interval").to_string());
+            return;
+        }
+        let client = scan_client.clone();
+        spawn(async move {
+            match client.command(&AtCommand::ScanParams { interval, window }).await {
+                Ok(_) => {
+                    settings.with_mut(|s| {
+                        s.scan_interval = Some(interval);
+                        s.scan_window = Some(window);
+                    });
+                    status.set(tr!("Scan parameters set").to_string());
+                }
+                Err(e) => status.set(tr!("Could not set scan parameters: {}", e)),
+            }
+        });
+    };
+
+    let power_client = dongle.clone();
+    let apply_power = move |_| {
+        let Ok(dbm) = power_input().trim().parse::<i8>() else {
+            status.set(tr!("TX power must be a whole number of dBm").to_string());
+            return;
+        };
+        let client = power_client.clone();
+        spawn(async move {
+            match client.command(&AtCommand::TxPower(dbm)).await {
+                Ok(_) => {
+                    settings.with_mut(|s| s.tx_power = Some(dbm));
+                    status.set(tr!("TX power set").to_string());
+                }
+                Err(e) => status.set(tr!("Could not set TX power: {}", e)),
+            }
+        });
+    };
+
+    let schedule_client = dongle.clone();
+    let apply_schedule = move |_| {
+        let duration = duration_input();
+        let duration = if duration.trim().is_empty() { Ok(None) } else { duration.trim().parse::<u32>().map(Some) };
+        let (Ok(duration), Ok(pause)) = (duration, pause_input().trim().parse::<u32>()) else {
+            status.set(tr!("Scan duration and pause must be whole seconds").to_string());
+            return;
+        };
+        let scan = ScanConfig { duration, pause, ..config.peek().scan.clone() };
+        if let Err(e) = scan.validate() {
+            status.set(e);
+            return;
+        }
+        config.with_mut(|c| c.scan = scan.clone());
+        if let Err(e) = config.peek().save() {
+            status.set(tr!("Could not save settings to {}: {}", AppConfig::path().display(), e));
+            return;
+        }
+        match schedule_client.send(BleuIOCommand::SetScanConfig(scan)) {
+            Ok(()) => status.set(tr!("Scan schedule set").to_string()),
+            Err(e) => status.set(tr!("Could not set scan schedule: {}", e)),
+        }
+    };
+
+    let reset_client = dongle.clone();
+    let reset = move |_| {
+        confirm_reset.set(false);
+        // The dongle restarts before it can answer, so don't wait for a reply.
+        match reset_client.send(BleuIOCommand::Raw(AtCommand::Reset.line())) {
+            Ok(()) => status.set(tr!("Dongle is restarting, the connection is lost until the app is restarted").to_string()),
+            Err(e) => status.set(tr!("Could not reset the dongle: {}", e)),
+        }
+    };
+
+    let unknown = tr!("unknown").to_string();
+    let current = info();
+    let summary = [
+        (tr!("Firmware"), current.as_ref().and_then(|i| i.firmware()).map(str::to_string)),
+        (tr!("Role"), current.as_ref().and_then(|i| i.role()).map(str::to_string)),
+        (tr!("MAC address"), current.as_ref().and_then(|i| i.mac()).map(str::to_string)),
+        (tr!("Connection"), current.as_ref().and_then(|i| i.connected()).map(str::to_string)),
+    ]
+    .map(|(k, v)| (k, v.unwrap_or_else(|| unknown.clone())));
+    let compat = compatibility();
+    let model = compat.as_ref().and_then(|c| c.model).map(|m| m.name().to_string()).unwrap_or_else(|| unknown.clone());
+    let version = compat.as_ref().and_then(|c| c.firmware).map(|f| f.to_string()).unwrap_or_else(|| unknown.clone());
+    let minimum = compat.as_ref().map(|c| c.minimum().to_string()).unwrap_or_else(|| unknown.clone());
+    let scan_params_supported = compat.as_ref().is_none_or(|c| c.scan_params());
+    let tx_power_supported = compat.as_ref().is_none_or(|c| c.tx_power());
+    let s = settings();
+    let setting_rows = [
+        (tr!("Echo"), on_off(s.echo)),
+        (tr!("Verbose"), on_off(s.verbose)),
+        (tr!("RSSI display"), on_off(s.show_rssi)),
+        (tr!("Scan interval"), s.scan_interval.map(|v| format!("{} ms", v)).unwrap_or_else(|| tr!("default").to_string())),
+        (tr!("Scan window"), s.scan_window.map(|v| format!("{} ms", v)).unwrap_or_else(|| tr!("default").to_string())),
+        (tr!("TX power"), s.tx_power.map(|v| format!("{} dBm", v)).unwrap_or_else(|| tr!("default").to_string())),
+    ];
+    let all_fields = current.map(|i| i.fields).unwrap_or_default();
+
+    rsx! {
+        div {
+            class: "flex flex-col gap-4 p-4",
+            div {
+                class: "flex gap-2",
+                button { class: "border p-1 rounded-md", disabled: busy(), onclick: move |_| refresh(), {tr!("Refresh")} }
+                span { class: "p-1", "{status}" }
+            }
+            div {
+                style: "display: grid; grid-template-columns: 160px 1fr; gap: 4px 20px;",
+                div { style: "font-weight: bold;", {tr!("Dongle")} }
+                div { {tr!("{}, firmware {} (minimum {})", model, version, minimum)} }
+                for (key, value) in summary {
+                    div { style: "font-weight: bold;", "{key}" }
+                    div { "{value}" }
+                }
+                for (key, value) in setting_rows {
+                    div { style: "font-weight: bold;", "{key}" }
+                    div { "{value}" }
+                }
+            }
+            if !all_fields.is_empty() {
+                div {
+                    button {
+                        class: "underline",
+                        onclick: move |_| show_all.toggle(),
+                        {if show_all() { tr!("Hide details") } else { tr!("All reported values") }}
+                    }
+                    if show_all() {
+                        for (key, value) in all_fields {
+                            div { class: "text-sm", "{key}: {value}" }
+                        }
+                    }
+                }
+            }
+            div {
+                class: "flex gap-2 items-center",
+                span { class: "w-40", {tr!("Scan interval / window (ms)")} }
+                input { class: "border p-1 rounded-md bg-gray-800 w-20", value: "{interval_input}", oninput: move |e| interval_input.set(e.value()) }
+                input { class: "border p-1 rounded-md bg-gray-800 w-20", value: "{window_input}", oninput: move |e| window_input.set(e.value()) }
+                button { class: "border p-1 rounded-md", disabled: !scan_params_supported, onclick: apply_scan, {tr!("Set")} }
+                if !scan_params_supported {
+                    span { class: "text-sm", {tr!("Not supported by this firmware")} }
+                }
+            }
+            div {
+                class: "flex gap-2 items-center",
+                span { class: "w-40", {tr!("Scan duration / pause (s)")} }
+                input { class: "border p-1 rounded-md bg-gray-800 w-20", placeholder: tr!("no limit"), value: "{duration_input}", oninput: move |e| duration_input.set(e.value()) }
+                input { class: "border p-1 rounded-md bg-gray-800 w-20", value: "{pause_input}", oninput: move |e| pause_input.set(e.value()) }
+                button { class: "border p-1 rounded-md", onclick: apply_schedule, {tr!("Set")} }
+                span { class: "text-sm", {tr!("Scans for the given time, then waits before scanning again. Leave the duration empty to scan until the dongle stops.")} }
+            }
+            div {
+                class: "flex gap-2 items-center",
+                span { class: "w-40", {tr!("TX power (dBm)")} }
+                input { class: "border p-1 rounded-md bg-gray-800 w-20", value: "{power_input}", oninput: move |e| power_input.set(e.value()) }
+                button { class: "border p-1 rounded-md", disabled: !tx_power_supported, onclick: apply_power, {tr!("Set")} }
+                if !tx_power_supported {
+                    span { class: "text-sm", {tr!("Not supported by this firmware")} }
+                }
+            }
+            div {
+                class: "flex gap-2 items-center",
+                if confirm_reset() {
+                    span { {tr!("Restart the dongle?")} }
+                    button { class: "border p-1 rounded-md bg-red-700", onclick: reset, {tr!("Yes, reset")} }
+                    button { class: "border p-1 rounded-md", onclick: move |_| confirm_reset.set(false), {tr!("Cancel")} }
+                } else {
+                    button { class: "border p-1 rounded-md", onclick: move |_| confirm_reset.set(true), {tr!("Reset dongle")} }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn ScanFilterView(mut config: Signal<AppConfig>) -> Element {
+    let serial = use_coroutine_handle::<BleuIOCommand>();
+    let mut name_input = use_signal(String::new);
+    let mut manufacturer_input = use_signal(String::new);
+    let mut service_input = use_signal(String::new);
+    let mut prefix_input = use_signal(String::new);
+    let mut addresses_input = use_signal(String::new);
+    let mut rssi_input = use_signal(String::new);
+    let mut status = use_signal(String::new);
+
+    // Save the filters and hand the selected one to the scan scheduler.
+    let mut save = move |f: &dyn Fn(&mut AppConfig)| {
+        config.with_mut(|c| f(c));
+        let current = config.peek().clone();
+        if let Err(e) = current.save() {
+            status.set(tr!("Could not save settings to {}: {}", AppConfig::path().display(), e));
+        }
+        serial.send(BleuIOCommand::SetScanConfig(current.scan.clone()));
+        serial.send(BleuIOCommand::SetScanFilter(current.filter()));
+    };
+
+    let mut edit = move |filter: &ScanFilter| {
+        name_input.set(filter.name.clone());
+        manufacturer_input.set(filter.manufacturer_id.map(|id| format!("{:04X}", id)).unwrap_or_default());
+        service_input.set(filter.service_uuid.map(|id| format!("{:04X}", id)).unwrap_or_default());
+        prefix_input.set(filter.name_prefix.clone().unwrap_or_default());
+        addresses_input.set(filter.addresses.join(", "));
+        rssi_input.set(filter.min_rssi.map(|v| v.to_string()).unwrap_or_default());
+    };
+
+    let store = move |_| {
+        let parsed = ScanFilter::from_form(
+            &name_input(),
+            &manufacturer_input(),
+            &service_input(),
+            &prefix_input(),
+            &addresses_input(),
+            &rssi_input(),
+        );
+        match parsed {
+            Ok(filter) => {
+                save(&|c| match c.filters.iter_mut().find(|f| f.name == filter.name) {
+                    Some(existing) => *existing = filter.clone(),
+                    None => c.filters.push(filter.clone()),
+                });
+                status.set(tr!("Filter {} saved", filter.name));
+            }
+            Err(e) => status.set(e),
+        }
+    };
+
+    let c = config();
+    let selected = c.scan.filter.clone();
+    let filters = c.filters.clone();
+
+    rsx! {
+        div {
+            class: "flex flex-col gap-2 p-4",
+            div { style: "font-weight: bold;", {tr!("Scan filters")} }
+            for filter in filters {
+                {
+                    let active = filter.name == selected;
+                    let name = filter.name.clone();
+                    let remove_name = filter.name.clone();
+                    let description = filter.describe();
+                    rsx! {
+                        div {
+                            key: "{filter.name}",
+                            class: "flex gap-2 items-center",
+                            input {
+                                r#type: "radio",
+                                name: "scan-filter",
+                                checked: active,
+                                onchange: move |_| save(&|c| c.scan.filter = name.clone()),
+                            }
+                            span { class: "w-40", "{filter.name}" }
+                            span { class: "flex-1 text-sm", "{description}" }
+                            button { class: "border px-1 rounded-md text-sm", onclick: move |_| edit(&filter), {tr!("Edit")} }
+                            button {
+                                class: "border px-1 rounded-md text-sm",
+                                disabled: active,
+                                onclick: move |_| save(&|c| c.filters.retain(|f| f.name != remove_name)),
+                                {tr!("Remove")}
+                            }
+                        }
+                    }
+                }
+            }
+            div {
+                class: "flex gap-2 items-center flex-wrap",
+                input { class: "border p-1 rounded-md bg-gray-800 w-32", placeholder: tr!("Name"), value: "{name_input}", oninput: move |e| name_input.set(e.value()) }
+                input { class: "border p-1 rounded-md bg-gray-800 w-32", placeholder: tr!("Manufacturer id"), value: "{manufacturer_input}", oninput: move |e| manufacturer_input.set(e.value()) }
+                input { class: "border p-1 rounded-md bg-gray-800 w-32", placeholder: tr!("Service UUID"), value: "{service_input}", oninput: move |e| service_input.set(e.value()) }
+                input { class: "border p-1 rounded-md bg-gray-800 w-32", placeholder: tr!("Name prefix"), value: "{prefix_input}", oninput: move |e| prefix_input.set(e.value()) }
+                input { class: "border p-1 rounded-md bg-gray-800 w-64", placeholder: tr!("Addresses"), value: "{addresses_input}", oninput: move |e| addresses_input.set(e.value()) }
+                input { class: "border p-1 rounded-md bg-gray-800 w-24", placeholder: tr!("Min RSSI"), value: "{rssi_input}", oninput: move |e| rssi_input.set(e.value()) }
+                button { class: "border p-1 rounded-md", onclick: store, {tr!("Save filter")} }
+            }
+            span { class: "p-1", "{status}" }
+        }
+    }
+}
+
+#[component]
+fn SensorRegistryView(config: Signal<AppConfig>, readings: Signal<HashMap<String, Reading>>) -> Element {
+    let serial = use_coroutine_handle::<BleuIOCommand>();
+    let mut address_input = use_signal(String::new);
+    let mut name_input = use_signal(String::new);
+    let mut status = use_signal(String::new);
+
+    // Save the list and hand it to the scan scheduler.
+    let mut save = move |f: &dyn Fn(&mut AppConfig)| {
+        config.with_mut(|c| f(c));
+        let current = config.peek().clone();
+        if let Err(e) = current.save() {
+            status.set(tr!("Could not save settings to {}: {}", AppConfig::path().display(), e));
+        }
+        serial.send(BleuIOCommand::SetScanConfig(current.scan.clone()));
+        serial.send(BleuIOCommand::SetScanTargets(current.targets()));
+    };
+
+    let add = move |_| {
+        let name = Some(name_input());
+        match KnownSensor::new(&address_input(), name) {
+            Ok(sensor) if config.peek().sensor(&sensor.address).is_some() => {
+                status.set(tr!("{} is already in the list", sensor.address));
+            }
+            Ok(sensor) => {
+                save(&|c| c.sensors.push(sensor.clone()));
+                address_input.set(String::new());
+                name_input.set(String::new());
+                status.set(String::new());
+            }
+            Err(e) => status.set(e),
+        }
+    };
+
+    let add_found = move |_| {
+        let found: Vec<KnownSensor> = readings
+            .read()
+            .values()
+            .filter(|r| config.peek().sensor(&r.address).is_none())
+            .filter_map(|r| KnownSensor::new(&r.address, None).ok())
+            .collect();
+        status.set(tr!("{} sensors added", found.len()));
+        save(&|c| c.sensors.extend(found.iter().cloned()));
+    };
+
+    let c = config();
+    let targets_only = c.scan.targets_only;
+    let sensors = c.sensors.clone();
+    let empty = sensors.is_empty();
+
+    rsx! {
+        div {
+            class: "flex flex-col gap-2 p-4",
+            div { style: "font-weight: bold;", {tr!("Known sensors")} }
+            label {
+                class: "flex gap-2 items-center",
+                input {
+                    r#type: "checkbox",
+                    checked: targets_only,
+                    disabled: empty && !targets_only,
+                    onchange: move |e| save(&|c| c.scan.targets_only = e.checked()),
+                }
+                {tr!("Follow only these sensors (AT+SCANTARGET)")}
+            }
+            for (i, sensor) in sensors.into_iter().enumerate() {
+                div {
+                    key: "{sensor.address}",
+                    class: "flex gap-2 items-center",
+                    span { class: "w-56 font-mono", "{sensor.address}" }
+                    span { class: "w-40", {sensor.name.clone().unwrap_or_default()} }
+                    {
+                        let room = sensor.room.clone().unwrap_or_default();
+                        let floor = sensor.floor.clone().unwrap_or_default();
+                        rsx! {
+                            input {
+                                class: "border p-1 rounded-md bg-gray-800 w-32",
+                                placeholder: tr!("Room"),
+                                value: "{room}",
+                                onchange: {
+                                    let floor = floor.clone();
+                                    move |e: FormEvent| save(&|c| c.sensors[i].set_place(&e.value(), &floor))
+                                },
+                            }
+                            input {
+                                class: "border p-1 rounded-md bg-gray-800 w-24",
+                                placeholder: tr!("Floor"),
+                                value: "{floor}",
+                                onchange: move |e: FormEvent| save(&|c| c.sensors[i].set_place(&room, &e.value())),
+                            }
+                        }
+                    }
+                    button {
+                        class: "border px-1 rounded-md text-sm",
+                        onclick: move |_| save(&|c| {
+                            c.sensors.remove(i);
+                            if c.sensors.is_empty() {
+                                c.scan.targets_only = false;
+                            }
+                        }),
+                        {tr!("Remove")}
+                    }
+                }
+            }
+            div {
+                class: "flex gap-2 items-center",
+                input { class: "border p-1 rounded-md bg-gray-800 w-56", placeholder: "[1]D3:2A:7C:1B:4F:57", value: "{address_input}", oninput: move |e| address_input.set(e.value()) }
+                input { class: "border p-1 rounded-md bg-gray-800 w-40", placeholder: tr!("Name"), value: "{name_input}", oninput: move |e| name_input.set(e.value()) }
+                button { class: "border p-1 rounded-md", onclick: add, {tr!("Add")} }
+                button { class: "border p-1 rounded-md", onclick: add_found, {tr!("Add all found sensors")} }
+            }
+            span { class: "p-1", "{status}" }
+        }
+    }
+}
+
+// Line colours of the sensors in a comparison, repeated when there are more.
+const COMPARE_COLORS: [&str; 8] = ["#86efac", "#93c5fd", "#fca5a5", "#fde047", "#c4b5fd", "#f9a8d4", "#67e8f9", "#fdba74"];
+
+// A sensor in a comparison.
+struct CompareSeries {
+    name: String,
+    color: &'static str,
+    segments: Vec<Vec<(i64, f64)>>,
+    stats: Option<Stats>,
+}
+
+// One metric of several sensors from the history on a shared time axis, with
+// statistics per sensor for the range.
+#[component]
+fn CompareView(history: Signal<HistoryStore>, config: Signal<AppConfig>, readings: Signal<HashMap<String, Reading>>) -> Element {
+    let mut metric = use_signal(|| Metric::Co2);
+    let mut range = use_signal(|| HISTORY_RANGES[0].1);
+    let mut selected = use_signal(Vec::<String>::new);
+
+    let since = chrono::Local::now().timestamp() - range();
+    let m = metric();
+    let c = config();
+    // Every sensor with a history, named as on the dashboard. Sensors not seen
+    // since the start are found in the registry by their stored address.
+    let store = history.read();
+    let mut sensors: Vec<(String, String, Option<String>)> = store
+        .ids()
+        .map(|id| match readings.read().get(id) {
+            Some(reading) => {
+                let placed = PlacedSensor::new(reading, &c);
+                (id.clone(), placed.name, placed.room)
+            }
+            None => {
+                let known = store.address(id).and_then(|a| c.sensor(a));
+                let name = known.and_then(|s| s.name.clone()).unwrap_or_else(|| id.clone());
+                (id.clone(), name, known.and_then(|s| s.room.clone()))
+            }
+        })
+        .collect();
+    sensors.sort_by_key(|(id, name, _)| (name.to_lowercase(), id.clone()));
+    let mut rooms: Vec<String> = sensors.iter().filter_map(|(_, _, room)| room.clone()).collect();
+    rooms.sort();
+    rooms.dedup();
+    let metrics: BTreeSet<Metric> = readings.read().values().flat_map(|r| r.values.iter().map(|(m, _)| *m)).chain([m]).collect();
+
+    // The selected sensors in the order of the list, each with its colour.
+    let series: Vec<CompareSeries> = sensors
+        .iter()
+        .filter(|(id, _, _)| selected.read().contains(id))
+        .zip(COMPARE_COLORS.iter().cycle())
+        .map(|((id, name, _), &color)| {
+            let store = history.read();
+            CompareSeries {
+                name: name.clone(),
+                color,
+                segments: store.segments(id, m, since, 3 * history::LIVE_INTERVAL),
+                stats: Stats::of(store.samples(id, since), m),
+            }
+        })
+        .collect();
+    let value_range = dashboard::value_range(series.iter().flat_map(|s| &s.segments));
+    let lines: Vec<(String, &str)> = value_range
+        .map(|value_range| {
+            series
+                .iter()
+                .flat_map(|s| dashboard::polylines(&s.segments, since, value_range, 600.0, 200.0).into_iter().map(|l| (l, s.color)))
+                .collect()
+        })
+        .unwrap_or_default();
+    let (low, high) = value_range.map(|(low, high)| (m.format(low), m.format(high))).unwrap_or_default();
+    let start = chrono::DateTime::from_timestamp(since, 0).map(|t| i18n::datetime(&t.with_timezone(&chrono::Local))).unwrap_or_default();
+    let end = i18n::datetime(&chrono::Local::now());
+
+    rsx! {
+        div {
+            class: "flex flex-col gap-2 p-4",
+            div {
+                class: "flex gap-2 items-center text-sm",
+                for pm in metrics {
+                    button {
+                        class: if m == pm { "border px-1 rounded-md bg-gray-500" } else { "border px-1 rounded-md" },
+                        onclick: move |_| metric.set(pm),
+                        "{pm.label()}"
+                    }
+                }
+                span { class: "flex-1" }
+                for (label, seconds) in HISTORY_RANGES {
+                    button {
+                        class: if range() == seconds { "border px-1 rounded-md bg-gray-500" } else { "border px-1 rounded-md" },
+                        onclick: move |_| range.set(seconds),
+                        {tr!(label)}
+                    }
+                }
+            }
+            div {
+                class: "flex flex-wrap gap-2 items-center text-sm",
+                for (id, name, room) in sensors.iter().cloned() {
+                    label {
+                        key: "{id}",
+                        class: "flex gap-1 items-center",
+                        input {
+                            r#type: "checkbox",
+                            checked: selected.read().contains(&id),
+                            onchange: move |e| selected.with_mut(|s| {
+                                s.retain(|i| *i != id);
+                                if e.checked() {
+                                    s.push(id.clone());
+                                }
+                            }),
+                        }
+                        if let Some(room) = room {
+                            "{name} ({room})"
+                        } else {
+                            "{name}"
+                        }
+                    }
+                }
+            }
+            if !rooms.is_empty() {
+                div {
+                    class: "flex gap-2 items-center text-sm",
+                    {tr!("Select a room:")}
+                    for room in rooms {
+                        button {
+                            class: "border px-1 rounded-md",
+                            onclick: {
+                                let ids: Vec<String> = sensors.iter().filter(|(_, _, r)| r.as_ref() == Some(&room)).map(|(id, _, _)| id.clone()).collect();
+                                move |_| selected.set(ids.clone())
+                            },
+                            "{room}"
+                        }
+                    }
+                    button { class: "border px-1 rounded-md", onclick: move |_| selected.set(Vec::new()), {tr!("None")} }
+                }
+            }
+            if series.is_empty() {
+                div { {tr!("Select the sensors to compare.")} }
+            } else if lines.is_empty() {
+                div { {tr!("No stored {} values in this range.", m.label())} }
+            } else {
+                div { class: "flex justify-between text-sm", span { "{high}" } span { "{low}" } }
+                svg {
+                    width: "100%",
+                    height: "200",
+                    view_box: "0 0 600 200",
+                    preserve_aspect_ratio: "none",
+                    style: "background: rgb(31, 28, 28);",
+                    for (points, color) in lines {
+                        polyline { points: "{points}", fill: "none", stroke: "{color}", stroke_width: "1.5" }
+                    }
+                }
+                div { class: "flex justify-between text-sm", span { "{start}" } span { "{end}" } }
+            }
+            if !series.is_empty() {
+                table {
+                    class: "text-sm",
+                    thead {
+                        tr {
+                            th { class: "text-left pr-4", {tr!("Sensor")} }
+                            th { class: "text-right pr-4", {tr!("Samples")} }
+                            th { class: "text-right pr-4", {tr!("Min")} }
+                            th { class: "text-right pr-4", {tr!("Mean")} }
+                            th { class: "text-right pr-4", {tr!("Median")} }
+                            th { class: "text-right pr-4", "P90" }
+                            th { class: "text-right pr-4", {tr!("Max")} }
+                        }
+                    }
+                    tbody {
+                        for s in series.iter() {
+                            tr {
+                                td { class: "pr-4", span { style: "color: {s.color};", "■ " } "{s.name}" }
+                                if let Some(stats) = s.stats {
+                                    td { class: "text-right pr-4", "{stats.count}" }
+                                    for value in [stats.min, stats.mean, stats.median, stats.p90, stats.max] {
+                                        td { class: "text-right pr-4", {m.format(value)} }
+                                    }
+                                } else {
+                                    td { class: "text-right pr-4", "0" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Cells across and down the heatmap of a floor plan.
+const HEATMAP_CELLS: usize = 24;
+
+// The known sensors of a floor on its plan, coloured by a metric. Markers are
+// dragged to where the sensors are; the positions are saved on release.
+#[component]
+fn FloorPlanView(config: Signal<AppConfig>, readings: Signal<HashMap<String, Reading>>) -> Element {
+    let mut floor = use_signal(|| config.peek().floors().into_iter().next());
+    let mut metric = use_signal(|| PLAN_METRICS[0]);
+    let mut heatmap = use_signal(|| false);
+    let mut status = use_signal(String::new);
+    // The address of the sensor being dragged and where it is now.
+    let mut dragging = use_signal(|| None::<(String, (f64, f64))>);
+    let mut area = use_signal(|| None::<Rc<MountedData>>);
+    let mut area_size = use_signal(|| (1.0, 1.0));
+
+    let plan = use_memo(move || floor().and_then(|f| config.read().floor_plan(&f).cloned()));
+    let image = use_resource(move || async move { plan().map(|p| p.data_url()) });
+
+    let mut save = move |f: &dyn Fn(&mut AppConfig)| {
+        config.with_mut(|c| f(c));
+        if let Err(e) = config.peek().save() {
+            status.set(tr!("Could not save settings to {}: {}", AppConfig::path().display(), e));
+        }
+    };
+
+    let import = move |_| async move {
+        let Some(f) = floor() else { return };
+        let Some(file) = rfd::AsyncFileDialog::new().add_filter(tr!("Images"), &PLAN_EXTENSIONS).pick_file().await else { return };
+        match FloorPlan::import(&f, file.path()) {
+            Ok(plan) => {
+                save(&|c| {
+                    c.floor_plans.retain(|p| p.floor != plan.floor);
+                    c.floor_plans.push(plan.clone());
+                });
+                info!(target: "ui", "{}", tr!("Imported the floor plan of {}", f));
+            }
+            Err(e) => status.set(tr!("Can not import {}: {}", file.path().display(), e)),
+        }
+    };
+
+    let mut start_drag = move |address: String, at: (f64, f64)| {
+        dragging.set(Some((address, at)));
+        // The plan may have been resized since it was last measured.
+        if let Some(area) = area() {
+            spawn(async move {
+                if let Ok(rect) = area.get_client_rect().await {
+                    area_size.set((rect.size.width.max(1.0), rect.size.height.max(1.0)));
+                }
+            });
+        }
+    };
+    let mut end_drag = move || {
+        if let Some((address, at)) = dragging.take() {
+            save(&|c| {
+                if let Some(sensor) = c.sensors.iter_mut().find(|s| s.address == address) {
+                    sensor.position = Some(at);
+                }
+            });
+        }
+    };
+
+    let c = config();
+    let floors = c.floors();
+    let current = floor();
+    let m = metric();
+    // Sensors of the floor with their latest value of the metric.
+    let sensors: Vec<(KnownSensor, Option<f64>)> = c
+        .sensors
+        .iter()
+        .filter(|s| current.is_some() && s.floor == current)
+        .map(|s| {
+            let value = readings
+                .read()
+                .values()
+                .find(|r| c.sensor(&r.address).is_some_and(|k| k.address == s.address))
+                .and_then(|r| r.values.iter().find(|(rm, _)| *rm == m).map(|(_, v)| *v));
+            let mut s = s.clone();
+            if let Some((address, at)) = dragging() {
+                if address == s.address {
+                    s.position = Some(at);
+                }
+            }
+            (s, value)
+        })
+        .collect();
+    let points: Vec<(f64, f64, f64)> = sensors
+        .iter()
+        .filter_map(|(s, v)| Some((s.position?.0, s.position?.1, (*v)?)))
+        .collect();
+    let cells: Vec<(usize, usize, String)> = if heatmap() {
+        (0..HEATMAP_CELLS * HEATMAP_CELLS)
+            .filter_map(|i| {
+                let (x, y) = (i % HEATMAP_CELLS, i / HEATMAP_CELLS);
+                let at = |n: usize| (n as f64 + 0.5) / HEATMAP_CELLS as f64;
+                floor_plan::interpolate(&points, at(x), at(y)).map(|v| (x, y, floor_plan::color(m, v)))
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    rsx! {
+        div {
+            class: "flex flex-col gap-2 p-4",
+            if floors.is_empty() {
+                div { {tr!("Give the known sensors a floor in the Dongle tab to make a plan of it.")} }
+            }
+            div {
+                class: "flex gap-2 items-center",
+                for f in floors {
+                    button {
+                        class: if current.as_ref() == Some(&f) { "border p-1 rounded-md bg-gray-500" } else { "border p-1 rounded-md" },
+                        onclick: {
+                            let f = f.clone();
+                            move |_| floor.set(Some(f.clone()))
+                        },
+                        "{f}"
+                    }
+                }
+                span { class: "flex-1" }
+                for pm in PLAN_METRICS {
+                    button {
+                        class: if m == pm { "border p-1 rounded-md bg-gray-500" } else { "border p-1 rounded-md" },
+                        onclick: move |_| metric.set(pm),
+                        "{pm.label()}"
+                    }
+                }
+                label {
+                    class: "flex gap-1 items-center",
+                    input { r#type: "checkbox", checked: heatmap(), onchange: move |e| heatmap.set(e.checked()) }
+                    {tr!("Heatmap")}
+                }
+                button { class: "border p-1 rounded-md", disabled: current.is_none(), onclick: import, {tr!("Import plan...")} }
+            }
+            match &*image.read() {
+                Some(Some(Ok(url))) => rsx! {
+                    div {
+                        class: "relative select-none",
+                        onmounted: move |e| {
+                            let data = e.data();
+                            area.set(Some(data.clone()));
+                            spawn(async move {
+                                if let Ok(rect) = data.get_client_rect().await {
+                                    area_size.set((rect.size.width.max(1.0), rect.size.height.max(1.0)));
+                                }
+                            });
+                        },
+                        // Markers and the heatmap let the mouse through while dragging,
+                        // so the coordinates are those of the image.
+                        onmousemove: move |e| {
+                            if let Some((address, _)) = dragging() {
+                                let point = e.element_coordinates();
+                                let (w, h) = area_size();
+                                dragging.set(Some((address, ((point.x / w).clamp(0.0, 1.0), (point.y / h).clamp(0.0, 1.0)))));
+                            }
+                        },
+                        onmouseup: move |_| end_drag(),
+                        onmouseleave: move |_| end_drag(),
+                        img { src: "{url}", draggable: "false", style: "width: 100%; display: block;" }
+                        svg {
+                            class: "absolute inset-0",
+                            width: "100%",
+                            height: "100%",
+                            view_box: "0 0 {HEATMAP_CELLS} {HEATMAP_CELLS}",
+                            preserve_aspect_ratio: "none",
+                            style: "pointer-events: none;",
+                            for (x, y, color) in cells {
+                                rect { x: "{x}", y: "{y}", width: "1", height: "1", fill: "{color}", fill_opacity: "0.45" }
+                            }
+                        }
+                        for (sensor, value) in sensors.iter().filter(|(s, _)| s.position.is_some()).cloned() {
+                            {
+                                let (x, y) = sensor.position.unwrap_or_default();
+                                let color = value.map(|v| floor_plan::color(m, v)).unwrap_or("gray".to_string());
+                                let text = value.map(|v| m.format(v)).unwrap_or("–".to_string());
+                                let name = sensor.name.clone().unwrap_or(sensor.address.clone());
+                                let pointer = if dragging().is_some() { "none" } else { "auto" };
+                                rsx! {
+                                    div {
+                                        key: "{sensor.address}",
+                                        class: "absolute px-1 rounded-md text-white text-xs shadow-md cursor-move",
+                                        style: "left: {x * 100.0}%; top: {y * 100.0}%; transform: translate(-50%, -50%); background: {color}; pointer-events: {pointer};",
+                                        title: "{sensor.address}",
+                                        onmousedown: move |_| start_drag(sensor.address.clone(), (x, y)),
+                                        div { class: "font-bold", "{name}" }
+                                        div { "{text}" }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                Some(Some(Err(e))) => rsx! { div { {tr!("Can not read the floor plan: {}", e)} } },
+                Some(None) if current.is_some() => rsx! { div { {tr!("No plan for this floor yet.")} } },
+                _ => rsx! {},
+            }
+            for (sensor, _) in sensors.iter().cloned() {
+                div {
+                    key: "{sensor.address}",
+                    class: "flex gap-2 items-center text-sm",
+                    span { class: "w-56 font-mono", "{sensor.address}" }
+                    span { class: "w-40", {sensor.name.clone().unwrap_or_default()} }
+                    if sensor.position.is_some() {
+                        button {
+                            class: "border px-1 rounded-md",
+                            onclick: move |_| save(&|c| {
+                                if let Some(s) = c.sensors.iter_mut().find(|s| s.address == sensor.address) {
+                                    s.position = None;
+                                }
+                            }),
+                            {tr!("Remove from plan")}
+                        }
+                    } else {
+                        button {
+                            class: "border px-1 rounded-md",
+                            disabled: plan().is_none(),
+                            onclick: move |_| save(&|c| {
+                                if let Some(s) = c.sensors.iter_mut().find(|s| s.address == sensor.address) {
+                                    s.position = Some((0.5, 0.5));
+                                }
+                            }),
+                            {tr!("Place")}
+                        }
+                    }
+                }
+            }
+            span { class: "p-1", "{status}" }
+        }
+    }
+}
+
+// Metrics shown on a tile of the compact view.
+const TILE_METRICS: usize = 4;
+
+// Sort, group and view of the dashboard.
+#[component]
+fn DashboardControls(layout: DashboardLayout, on_change: EventHandler<DashboardLayout>) -> Element {
+    rsx! {
+        div {
+            class: "flex gap-2 items-center text-sm",
+            {tr!("Sort by")}
+            select {
+                class: "border p-1 rounded-md",
+                style: "background: rgb(31, 28, 28);",
+                onchange: move |e| {
+                    if let Some(&sort) = e.value().parse::<usize>().ok().and_then(|i| SortBy::ALL.get(i)) {
+                        on_change.call(DashboardLayout { sort, ..layout });
+                    }
+                },
+                for (i, sort) in SortBy::ALL.into_iter().enumerate() {
+                    option { value: "{i}", selected: sort == layout.sort, {sort.label()} }
+                }
+            }
+            {tr!("Group by")}
+            select {
+                class: "border p-1 rounded-md",
+                style: "background: rgb(31, 28, 28);",
+                onchange: move |e| {
+                    if let Some(&group) = e.value().parse::<usize>().ok().and_then(|i| GroupBy::ALL.get(i)) {
+                        on_change.call(DashboardLayout { group, ..layout });
+                    }
+                },
+                for (i, group) in GroupBy::ALL.into_iter().enumerate() {
+                    option { value: "{i}", selected: group == layout.group, {group.label()} }
+                }
+            }
+            span { class: "flex-1" }
+            for view in PanelView::ALL {
+                button {
+                    class: if layout.view == view { "border p-1 rounded-md bg-gray-500" } else { "border p-1 rounded-md" },
+                    onclick: move |_| on_change.call(DashboardLayout { view, ..layout }),
+                    {view.label()}
+                }
+            }
+        }
+    }
+}
+
+// A sensor as a full panel with its settings, history and diagnostics. The
+// name opens the detail view when `on_open` is given.
+#[component]
+fn SensorEntry(
+    sensor: PlacedSensor,
+    history: Signal<HistoryStore>,
+    diagnostics: Option<SensorDiagnostics>,
+    on_open: Option<EventHandler<String>>,
+) -> Element {
+    let reading = sensor.reading.clone();
+    let place = sensor.place();
+    let id = reading.id.clone();
+
+    rsx! {
+        div {
+            class: "flex gap-2 items-baseline px-1",
+            if let Some(on_open) = on_open {
+                button {
+                    class: "font-bold underline",
+                    onclick: move |_| on_open.call(id.clone()),
+                    "{sensor.name}"
+                }
+            } else {
+                span { class: "font-bold", "{sensor.name}" }
+            }
+            span { class: "text-sm", "{place}" }
+        }
+        SensorPanel { reading: reading.clone() }
+        HistoryPanel { history, reading }
+        if let Some(diagnostics) = diagnostics {
+            DiagnosticsPanel { diagnostics }
+        }
+    }
+}
+
+// A sensor in the compact view, opened in the detail view on click.
+#[component]
+fn SensorTile(sensor: PlacedSensor, on_open: EventHandler<String>) -> Element {
+    let id = sensor.reading.id.clone();
+    let place = sensor.place();
+    let seen = i18n::time(&sensor.reading.at);
+    let values: Vec<(String, String)> = sensor
+        .reading
+        .values
+        .iter()
+        .take(TILE_METRICS)
+        .map(|(metric, value)| (metric.label().to_string(), metric.format(*value)))
+        .collect();
+
+    rsx! {
+        div {
+            class: "p-2 w-56 bg-green-700 rounded-lg shadow-md text-white text-sm cursor-pointer",
+            onclick: move |_| on_open.call(id.clone()),
+            div {
+                class: "flex justify-between gap-2",
+                span { class: "font-bold truncate", "{sensor.name}" }
+                span { "{seen}" }
+            }
+            if !place.is_empty() {
+                div { class: "text-xs", "{place}" }
+            }
+            div {
+                class: "grid grid-cols-2 gap-x-2 mt-1",
+                for (label, value) in values {
+                    span { "{label}" }
+                    span { class: "text-right", "{value}" }
+                }
+            }
+        }
+    }
+}
+
+// One sensor on its own, with what is known about it.
+#[component]
+fn SensorDetail(
+    sensor: PlacedSensor,
+    history: Signal<HistoryStore>,
+    diagnostics: Option<SensorDiagnostics>,
+    on_close: EventHandler<()>,
+) -> Element {
+    let dash = "–".to_string();
+    let details = [
+        (tr!("Address"), sensor.reading.address.clone()),
+        (tr!("Model"), sensor.reading.model.clone()),
+        (tr!("Decoder"), sensor.reading.decoder.clone()),
+        (tr!("Room"), sensor.room.clone().unwrap_or(dash.clone())),
+        (tr!("Floor"), sensor.floor.clone().unwrap_or(dash)),
+    ];
+
+    rsx! {
+        div {
+            class: "flex flex-col gap-2 p-4",
+            div {
+                button { class: "border p-1 rounded-md", onclick: move |_| on_close.call(()), {tr!("Back")} }
+            }
+            div {
+                class: "grid gap-x-4 text-sm",
+                style: "grid-template-columns: max-content 1fr;",
+                for (label, value) in details {
+                    span { class: "font-bold", "{label}" }
+                    span { "{value}" }
+                }
+            }
+            SensorEntry { sensor, history, diagnostics }
+        }
+    }
+}
+
+#[component]
+pub fn Hero(transport: Transport, record: Option<PathBuf>) -> Element {
+    let readings = use_signal(HashMap::<String, Reading>::new);
+    let diag = use_signal(HashMap::<String, SensorDiagnostics>::new);
+    let console = use_signal(Console::default);
+    let recording = use_signal(|| None::<PathBuf>);
+    let scanner = use_signal(Scanner::default);
+    let dongle_settings = use_signal(DongleSettings::default);
+    let compatibility = use_signal(|| None::<Compatibility>);
+    let link_state = use_signal(|| LinkState::Opening);
+    let scan_status = use_signal(|| ScanStatus::Idle);
+    let status = DongleStatus { state: link_state, settings: dongle_settings, compatibility, scan: scan_status };
+    let mut config = use_signal(AppConfig::load);
+    let history = use_signal(|| {
+        let dir = history::default_dir();
+        HistoryStore::open(&dir).unwrap_or_else(|e| {
+            warn!(target: "history", "{}", tr!("Can not read history from {}: {}", dir.display(), e));
+            HistoryStore::default()
+        })
+    });
+    let events = use_hook(|| tokio::sync::broadcast::channel::<Value>(256).0);
+    let events_for_task = events.clone();
+    let sinks = use_context::<SinkPipeline>();
+    
+    let serial_task = use_coroutine(move |commands: UnboundedReceiver<BleuIOCommand>| {
+        let transport_for_async = transport.clone();
+        let record_for_async = record.clone();
+        let events_for_async = events_for_task.clone();
+        let sinks_for_async = sinks.clone();
+
+        let span = info_span!("serial", port = %transport_for_async.description());
+
+        async move {
+            debug!(target: "serial", "{}", tr!("Opening {}", transport_for_async.description()));
+            let (reader, writer) = match transport_for_async.open() {
+                Ok(t) => t,
+                Err(e) => {
+                    error!(target: "serial", "{}", tr!("Can not open {}: {}", transport_for_async.description(), e));
+                    return;
+                }
+            };
+            let mut link = Link {
+                console,
+                capture: None,
+                recording,
+                status,
+                scanner,
+                diag,
+                readings,
+                history,
+                events: events_for_async,
+            };
+            if let Some(path) = record_for_async {
+                if let Err(e) = link.start_recording(&path) {
+                    warn!(target: "serial", "{}", tr!("Can not record to {}: {}", path.display(), e));
+                }
+            }
+
+            if transport_for_async.is_replay() {
+                info!(target: "serial", "{}", tr!("Replaying {}", transport_for_async.description()));
+            } else {
+                info!(target: "serial", "{}", tr!("Port open"));
+            }
+
+            let model = match &transport_for_async {
+                Transport::Serial { port_name } => dongle_model(port_name),
+                Transport::Replay { .. } => None,
+            };
+            let mut serial = SerialLoop::new(writer, model, &config.peek(), sinks_for_async, link);
+            if let Err(e) = serial.run(reader, commands, transport_for_async.is_replay()).await {
+                error!(target: "serial", "{}", tr!("Read error: {}", e));
+            }
+        }
+        .instrument(span)
+    });
+    let dongle = use_context_provider(|| BleuIOClient::new(serial_task.tx()));
+    use_context_provider(|| GattClient::new(dongle.clone(), events.clone()));
+
+    let mut tab = use_signal(|| Tab::Dashboard);
+    let mut detail = use_signal(|| None::<String>);
+
+    // The dashboard in the order and groups of the settings. The detail view
+    // closes when its sensor is gone.
+    let layout = config.read().dashboard;
+    let groups = layout.arrange(readings.read().values(), &config.read());
+    let detail_sensor = detail().and_then(|id| groups.iter().flat_map(|g| &g.sensors).find(|s| s.reading.id == id).cloned());
+    let save_layout = move |layout: DashboardLayout| {
+        config.write().dashboard = layout;
+        if let Err(e) = config.read().save() {
+            warn!(target: "ui", "{}", tr!("Could not save settings to {}: {}", AppConfig::path().display(), e));
+        }
+    };
+
+    rsx! {
+        div {
+            // img { src: HEADER_SVG, id: "header" }
+            // style: "font-family: monospace; padding: 20px;",
+            // h1 { "HibouAIR Monitor" }
+            div {
+                class: "flex gap-2 px-4",
+                for (t, label) in [(Tab::Dashboard, "Dashboard"), (Tab::FloorPlan, "Floor plan"), (Tab::Compare, "Compare"), (Tab::Scanner, "Scanner"), (Tab::Console, "Console"), (Tab::Gatt, "GATT"), (Tab::Dongle, "Dongle")] {
+                    button {
+                        class: if tab() == t { "border p-1 rounded-md bg-gray-500" } else { "border p-1 rounded-md" },
+                        onclick: move |_| tab.set(t),
+                        {tr!(label)}
+                    }
+                }
+                span { class: "flex-1" }
+                span { class: "p-1", "{link_state().description()}" }
+                span { class: "p-1 text-sm", "{scan_status().description()}" }
+                UnitsSelect {
+                    on_change: move |units: Units| {
+                        config.write().units = units;
+                        if let Err(e) = config.read().save() {
+                            warn!(target: "ui", "{}", tr!("Could not save settings to {}: {}", AppConfig::path().display(), e));
+                        }
+                    }
+                }
+                LanguageSelect {
+                    on_change: move |language: Language| {
+                        config.write().language = Some(language);
+                        if let Err(e) = config.read().save() {
+                            warn!(target: "ui", "{}", tr!("Could not save settings to {}: {}", AppConfig::path().display(), e));
+                        }
+                    }
+                }
+                if scan_status() == ScanStatus::Suspended {
+                    button {
+                        class: "border p-1 rounded-md",
+                        onclick: move |_| serial_task.send(BleuIOCommand::AtFindscandata),
+                        {tr!("Resume")}
+                    }
+                }
+                if matches!(link_state(), LinkState::Error(_)) {
+                    button {
+                        class: "border p-1 rounded-md",
+                        onclick: move |_| serial_task.send(BleuIOCommand::Reinitialize),
+                        {tr!("Retry")}
+                    }
+                }
+            }
+
+            if let Some(warning) = compatibility().and_then(|c| c.warning()) {
+                div { class: "mx-4 my-2 p-2 rounded-md bg-red-800 text-white", "{warning}" }
+            }
+
+            if tab() == Tab::FloorPlan {
+                FloorPlanView { config, readings }
+            }
+
+            if tab() == Tab::Compare {
+                CompareView { history, config, readings }
+            }
+
+            if tab() == Tab::Scanner {
+                ScannerView { scanner }
+            }
+
+            if tab() == Tab::Console {
+                ConsoleView { console, recording }
+            }
+
+            if tab() == Tab::Gatt {
+                GattView { scanner }
+            }
+
+            if tab() == Tab::Dongle {
+                DongleView { settings: dongle_settings, compatibility, config }
+                ScanFilterView { config }
+                SensorRegistryView { config, readings }
+            }
+
+            if tab() == Tab::Dashboard {
+                if let Some(sensor) = detail_sensor {
+                    SensorDetail {
+                        diagnostics: diag.read().get(&sensor.reading.id).cloned(),
+                        sensor,
+                        history,
+                        on_close: move |_| detail.set(None),
+                    }
+                } else {
+                    div {
+                        class: "flex flex-col gap-8 p-4",
+                        DashboardControls { layout, on_change: save_layout }
+                        for group in groups {
+                            div {
+                                key: "{group.label.clone().unwrap_or_default()}",
+                                class: "flex flex-col gap-4",
+                                if let Some(label) = &group.label {
+                                    div { class: "text-lg font-bold", "{label}" }
+                                }
+                                if layout.view == PanelView::Tiles {
+                                    div {
+                                        class: "flex flex-wrap gap-2",
+                                        for sensor in group.sensors {
+                                            SensorTile {
+                                                key: "{sensor.reading.id}",
+                                                sensor,
+                                                on_open: move |id| detail.set(Some(id)),
+                                            }
+                                        }
+                                    }
+                                } else {
+                                    for sensor in group.sensors {
+                                        div {
+                                            key: "{sensor.reading.id}",
+                                            SensorEntry {
+                                                diagnostics: diag.read().get(&sensor.reading.id).cloned(),
+                                                sensor,
+                                                history,
+                                                                        on_open: move |id| detail.set(Some(id)),
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // div { class: "grid grid-cols-1 gap-4 lg:grid-cols-[120px_1fr] lg:gap-8",
+        //     div { class: "h-32 rounded bg-gray-300" }
+        //     div { class: "h-32 rounded bg-gray-300" }
+        // }
+    }
+}
@@ -1,11 +1,159 @@
 use serialport5::*;
 use serde_json::{Result, Value};
+use std::collections::VecDeque;
 use std::convert::TryFrom;
+use std::fmt;
+use std::path::PathBuf;
+use futures_channel::mpsc::UnboundedSender;
+use futures_channel::oneshot;
+use tokio::time::{timeout, Duration};
+
+use crate::config::ScanConfig;
+use crate::i18n::tr;
+use crate::scan_filter::ScanFilter;
 
 const BLUEIO_VID: u16 = 0x2dcf;
-const BLUEIO_PID: u16 = 0x6002;
 
-#[derive(PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DongleModel {
+    BleuIO,
+    BleuIOPro,
+}
+
+impl DongleModel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DongleModel::BleuIO => "BleuIO",
+            DongleModel::BleuIOPro => "BleuIO Pro",
+        }
+    }
+}
+
+// USB product ids of the supported dongles. The Pro id is not confirmed
+// against the vendor's documentation yet. A Pro under another id is not found
+// automatically, and is taken for a BleuIO when its port is given.
+const BLEUIO_PIDS: &[(u16, DongleModel)] = &[
+    (0x6002, DongleModel::BleuIO),
+    (0x6003, DongleModel::BleuIOPro),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FirmwareVersion {
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+}
+
+impl FirmwareVersion {
+    pub const fn new(major: u16, minor: u16, patch: u16) -> Self {
+        Self { major, minor, patch }
+    }
+
+    // Find a version like "2.7.9" in `s`, e.g. "v2.7.9.51" or "BleuIO Firmware 2.7.9".
+    // A fourth build number is ignored, a missing patch level counts as 0.
+    pub fn parse(s: &str) -> Option<Self> {
+        s.split(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .filter(|part| part.contains('.'))
+            .find_map(|part| {
+                let mut numbers = part.split('.').map(|n| n.parse::<u16>());
+                let major = numbers.next()?.ok()?;
+                let minor = numbers.next()?.ok()?;
+                let patch = numbers.next().and_then(|n| n.ok()).unwrap_or(0);
+                Some(Self::new(major, minor, patch))
+            })
+    }
+}
+
+impl fmt::Display for FirmwareVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+// First firmware versions of a model with the features the app depends on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FirmwareLevels {
+    pub minimum: FirmwareVersion,   // oldest tested, older ones lack the verbose protocol
+    pub show_rssi: FirmwareVersion,
+    pub scan_params: FirmwareVersion,
+    pub tx_power: FirmwareVersion,
+}
+
+const BLEUIO_FIRMWARE: FirmwareLevels = FirmwareLevels {
+    minimum: FirmwareVersion::new(2, 2, 0),
+    show_rssi: FirmwareVersion::new(2, 2, 1),
+    scan_params: FirmwareVersion::new(2, 4, 0),
+    tx_power: FirmwareVersion::new(2, 7, 0),
+};
+
+// The Pro numbers its firmware from 1.0.0, assumed to have had all of these
+// from its first release until a Pro on older firmware shows otherwise.
+const BLEUIO_PRO_FIRMWARE: FirmwareLevels = FirmwareLevels {
+    minimum: FirmwareVersion::new(1, 0, 0),
+    show_rssi: FirmwareVersion::new(1, 0, 0),
+    scan_params: FirmwareVersion::new(1, 0, 0),
+    tx_power: FirmwareVersion::new(1, 0, 0),
+};
+
+impl DongleModel {
+    pub fn firmware_levels(&self) -> FirmwareLevels {
+        match self {
+            DongleModel::BleuIO => BLEUIO_FIRMWARE,
+            DongleModel::BleuIOPro => BLEUIO_PRO_FIRMWARE,
+        }
+    }
+}
+
+// What the connected dongle can do, derived from its model and firmware version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Compatibility {
+    pub model: Option<DongleModel>,
+    pub firmware: Option<FirmwareVersion>,
+}
+
+impl Compatibility {
+    // An unknown model is taken to be the original BleuIO.
+    fn levels(&self) -> FirmwareLevels {
+        self.model.unwrap_or(DongleModel::BleuIO).firmware_levels()
+    }
+
+    fn at_least(&self, version: FirmwareVersion) -> bool {
+        // Unknown firmware is assumed to be recent, commands fail visibly if not.
+        self.firmware.is_none_or(|f| f >= version)
+    }
+
+    pub fn minimum(&self) -> FirmwareVersion {
+        self.levels().minimum
+    }
+
+    pub fn show_rssi(&self) -> bool {
+        self.at_least(self.levels().show_rssi)
+    }
+
+    pub fn scan_params(&self) -> bool {
+        self.at_least(self.levels().scan_params)
+    }
+
+    pub fn tx_power(&self) -> bool {
+        self.at_least(self.levels().tx_power)
+    }
+
+    // Shown to the user when the dongle is not fully supported.
+    pub fn warning(&self) -> Option<String> {
+        match self.firmware {
+            Some(f) if f < self.minimum() => Some(tr!(
+                "{} firmware {} is too old, version {} or later is needed. Please update the dongle firmware.",
+                self.model.map(|m| m.name()).unwrap_or("BleuIO"),
+                f,
+                self.minimum()
+            )),
+            None => Some(tr!("Could not read the firmware version of the dongle, some functions may not work.").to_string()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum BleuIOResponseType {
     UnknownResponse,            // Unknown command found in JSON string
     CommandResponse,            // {"C":Command Index,"cmd":"command"}
@@ -19,7 +167,7 @@ pub enum BleuIOResponseType {
     EventResponse, 	            // {event code:"Connection Index in hex if any otherwise 0xFFFF",Event response data}
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum BleuIOErrorCode {
 	Success,                        // 0x00
 	GenericFailure,                 // 0x01
@@ -72,29 +220,326 @@ impl TryFrom<i64> for BleuIOErrorCode {
     }
 }
 
+impl BleuIOErrorCode {
+    // Text shown to the user when a command fails.
+    pub fn describe(&self) -> &'static str {
+        tr!(match self {
+            BleuIOErrorCode::Success => "Success",
+            BleuIOErrorCode::GenericFailure => "Generic failure",
+            BleuIOErrorCode::AlreadyDone => "Already done",
+            BleuIOErrorCode::OperationAlreadyInProgress => "Operation already in progress",
+            BleuIOErrorCode::InvalidParameter => "Invalid parameter",
+            BleuIOErrorCode::NotAllowed => "Not allowed",
+            BleuIOErrorCode::NotConnected => "Not connected",
+            BleuIOErrorCode::NotSupported => "Not supported",
+            BleuIOErrorCode::NotAccepted => "Not accepted",
+            BleuIOErrorCode::Busy => "Busy",
+            BleuIOErrorCode::RequestTimedOut => "Request timed out",
+            BleuIOErrorCode::NotSupportedByPeer => "Not supported by the sensor",
+            BleuIOErrorCode::CanceledByUser => "Canceled",
+            BleuIOErrorCode::EncryptionKeyMissing => "Encryption key missing, pair with the sensor first",
+            BleuIOErrorCode::InsufficientResources => "Insufficient resources",
+            BleuIOErrorCode::NotFound => "Not found",
+            BleuIOErrorCode::NoCreditsAvailableOnL2CAPCoC => "No credits available on L2CAP CoC",
+            BleuIOErrorCode::MTUExceededOnL2CAPCoC => "MTU exceeded on L2CAP CoC",
+            BleuIOErrorCode::InsufficientBandwidth => "Insufficient bandwidth",
+            BleuIOErrorCode::UnknownError => "Unknown error",
+        })
+    }
+}
+
+// How long to wait for the E line of a request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandError {
+    Timeout,
+    Dongle(Option<BleuIOErrorCode>, String),    // error reported in the A line
+    Closed,                                     // the serial task is gone
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::Timeout => f.write_str(tr!("timeout waiting for the dongle")),
+            CommandError::Dongle(Some(code), msg) => write!(f, "{} ({})", code.describe(), msg),
+            CommandError::Dongle(None, msg) => f.write_str(&tr!("dongle error: {}", msg)),
+            CommandError::Closed => f.write_str(tr!("connection to the dongle closed")),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+// Sends commands to the serial task. Cheap to clone.
+#[derive(Clone)]
+pub struct BleuIOClient {
+    commands: UnboundedSender<BleuIOCommand>,
+}
+
+impl BleuIOClient {
+    pub fn new(commands: UnboundedSender<BleuIOCommand>) -> Self {
+        Self { commands }
+    }
+
+    // Queue a command without waiting for its result.
+    pub fn send(&self, cmd: BleuIOCommand) -> std::result::Result<(), CommandError> {
+        self.commands.unbounded_send(cmd).map_err(|_| CommandError::Closed)
+    }
+
+    // Send a command line and wait for all its lines.
+    pub async fn request(&self, line: &str) -> std::result::Result<CommandReply, CommandError> {
+        let (tx, rx) = oneshot::channel();
+        self.send(BleuIOCommand::Request { line: line.to_string(), reply: tx })?;
+        let reply = timeout(REQUEST_TIMEOUT, rx)
+            .await
+            .map_err(|_| CommandError::Timeout)?
+            .map_err(|_| CommandError::Closed)?;
+        if reply.is_ok() {
+            Ok(reply)
+        } else {
+            Err(CommandError::Dongle(reply.error_code(), reply.err_msg.clone()))
+        }
+    }
+
+    pub async fn command(&self, cmd: &AtCommand) -> std::result::Result<CommandReply, CommandError> {
+        self.request(&cmd.line()).await
+    }
+
+    // Run ATI, AT+GETMAC and AT+GAPSTATUS. Queries the dongle does not support
+    // are left out rather than failing the whole lookup.
+    pub async fn info(&self) -> std::result::Result<DongleInfo, CommandError> {
+        let mut info = DongleInfo::default();
+        for cmd in [AtCommand::Info, AtCommand::GetMac, AtCommand::GapStatus] {
+            match self.command(&cmd).await {
+                Ok(reply) => info.add_replies(&reply.replies),
+                Err(CommandError::Dongle(..)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(info)
+    }
+}
+
+// Fixed command lines sent by the serial task.
+pub const AT: &[u8; 4] = b"AT\r\n";
+pub const ATE0: &[u8; 6] = b"ATE0\r\n";
+pub const ATV1: &[u8; 6] = b"ATV1\r\n";
+pub const ATI: &[u8; 5] = b"ATI\r\n";
+pub const AT_SHOWRSSI: &[u8; 15] = b"AT+SHOWRSSI=1\r\n";
+pub const AT_GAPSCAN: &[u8; 12] = b"AT+GAPSCAN\r\n";
+pub const STOP_SCAN: &[u8; 1] = b"\x03";  // Ctrl-C ends any ongoing scan
+
+// Commands for the serial task.
+#[derive(Debug)]
+pub enum BleuIOCommand {
+    At,
+    AtI,
+    AtCentral,
+    AtShowRssi,
+    AtFindscandata,
+    AtGapScan,                  // scan for all devices
+    FindScanData(String),       // scan for advertising data containing the given hex string
+    StopScan,
+    PauseScan,                  // stop the sensor scan while a GATT connection is open
+    ResumeScan,                 // the GATT connection is closed
+    Raw(String),        // arbitrary command line typed in the console, without CR/LF
+    StartRecording(PathBuf),
+    StopRecording,
+    Reinitialize,               // run the init handshake again
+    SetScanConfig(ScanConfig),  // duration and pause of the sensor scan
+    SetScanTargets(Vec<String>),    // sensors followed with AT+SCANTARGET
+    SetScanFilter(ScanFilter),
+    // Send a command line and get all lines belonging to it back, see RequestTracker.
+    Request { line: String, reply: futures_channel::oneshot::Sender<CommandReply> },
+}
+
+// AT commands understood by the dongle, used for autocompletion in the console.
+pub const KNOWN_COMMANDS: &[&str] = &[
+    "AT",
+    "ATE0",
+    "ATE1",
+    "ATI",
+    "ATR",
+    "ATV0",
+    "ATV1",
+    "AT+ADVDATA",
+    "AT+ADVSTART",
+    "AT+ADVSTOP",
+    "AT+CANCELCONNECT",
+    "AT+CENTRAL",
+    "AT+DUAL",
+    "AT+FINDSCANDATA",
+    "AT+GAPCONNECT",
+    "AT+GAPDISCONNECT",
+    "AT+GAPSCAN",
+    "AT+GAPSTATUS",
+    "AT+GATTCREAD",
+    "AT+GATTCWRITE",
+    "AT+GATTCWRITEB",
+    "AT+GETCONN",
+    "AT+GETMAC",
+    "AT+GETSERVICES",
+    "AT+PERIPHERAL",
+    "AT+SCANPARAM",
+    "AT+SCANTARGET",
+    "AT+SETNOTI",
+    "AT+SHOWRSSI",
+    "AT+TXPOWER",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GapRole {
+    Central,
+    Peripheral,
+    Dual,
+}
+
+// Dongle commands with typed parameters, sent as requests through BleuIOClient.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AtCommand {
+    Info,                                   // ATI, firmware, role and state
+    GetMac,
+    GapStatus,                              // role and connection state
+    GetConnections,
+    SetRole(GapRole),
+    ScanParams { interval: u16, window: u16 },  // ms, window <= interval
+    TxPower(i8),                            // dBm
+    Reset,                                  // restarts the dongle, the serial port goes away
+}
+
+impl AtCommand {
+    pub fn line(&self) -> String {
+        match self {
+            AtCommand::Info => "ATI".to_string(),
+            AtCommand::GetMac => "AT+GETMAC".to_string(),
+            AtCommand::GapStatus => "AT+GAPSTATUS".to_string(),
+            AtCommand::GetConnections => "AT+GETCONN".to_string(),
+            AtCommand::SetRole(GapRole::Central) => "AT+CENTRAL".to_string(),
+            AtCommand::SetRole(GapRole::Peripheral) => "AT+PERIPHERAL".to_string(),
+            AtCommand::SetRole(GapRole::Dual) => "AT+DUAL".to_string(),
+            AtCommand::ScanParams { interval, window } => format!("AT+SCANPARAM={}={}", interval, window),
+            AtCommand::TxPower(dbm) => format!("AT+TXPOWER={}", dbm),
+            AtCommand::Reset => "ATR".to_string(),
+        }
+    }
+}
+
+// Scan interval and window accepted by the Bluetooth controller, 2.5 ms to 10.24 s.
+pub const SCAN_TIMING_RANGE: (u16, u16) = (3, 10240);
+
+// Settings of the dongle as last set by the app. None until set, the dongle
+// has no command to read most of them back.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DongleSettings {
+    pub echo: Option<bool>,
+    pub verbose: Option<bool>,
+    pub show_rssi: Option<bool>,
+    pub scan_interval: Option<u16>,     // ms
+    pub scan_window: Option<u16>,       // ms
+    pub tx_power: Option<i8>,           // dBm
+}
+
+// What the dongle tells about itself in the reply lines of ATI, AT+GETMAC and
+// AT+GAPSTATUS, flattened into key/value pairs.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DongleInfo {
+    pub fields: Vec<(String, String)>,
+}
+
+impl DongleInfo {
+    pub fn add_replies(&mut self, replies: &[Value]) {
+        for reply in replies {
+            let Some(o) = reply.as_object() else {
+                continue;
+            };
+            for (key, value) in o.iter().filter(|(k, _)| k.as_str() != "R") {
+                let value = value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string());
+                match self.fields.iter_mut().find(|(k, _)| k == key) {
+                    Some(field) => field.1 = value,
+                    None => self.fields.push((key.clone(), value)),
+                }
+            }
+        }
+    }
+
+    // First field with one of the names, ignoring case.
+    pub fn get(&self, names: &[&str]) -> Option<&str> {
+        names.iter().find_map(|n| {
+            self.fields.iter().find(|(k, _)| k.eq_ignore_ascii_case(n)).map(|(_, v)| v.as_str())
+        })
+    }
+
+    pub fn firmware(&self) -> Option<&str> {
+        self.get(&["fwVer", "fw", "firmware", "version"])
+    }
+
+    pub fn role(&self) -> Option<&str> {
+        self.get(&["gapRole", "role"])
+    }
+
+    pub fn mac(&self) -> Option<&str> {
+        self.get(&["addr", "mac", "own_mac_addr"])
+    }
+
+    pub fn connected(&self) -> Option<&str> {
+        self.get(&["connected", "status"])
+    }
+
+    // The firmware version field, or failing that any value that looks like a version.
+    pub fn firmware_version(&self) -> Option<FirmwareVersion> {
+        self.firmware()
+            .and_then(FirmwareVersion::parse)
+            .or_else(|| self.fields.iter().find_map(|(_, v)| FirmwareVersion::parse(v)))
+    }
+}
+
 // Checks if given UsbPortInfo is associated with an attached BleuIO device.
-fn is_bleuio(info: &UsbPortInfo) -> bool {
-    (info.vid == BLUEIO_VID) && (info.pid == BLUEIO_PID)
+fn bleuio_model(info: &UsbPortInfo) -> Option<DongleModel> {
+    if info.vid != BLUEIO_VID {
+        return None;
+    }
+    BLEUIO_PIDS.iter().find(|(pid, _)| *pid == info.pid).map(|(_, model)| *model)
 }
 
-// Scan list of available USB devices and return device path of first detected BleuIO device.
-pub fn find_bleuio() -> String {
-        serialport5::available_ports()
+// All attached BleuIO devices with their device paths.
+fn bleuio_ports() -> Vec<(String, DongleModel)> {
+    serialport5::available_ports()
         .unwrap_or_default()
         .into_iter()
-        .find_map(|p| {
+        .filter_map(|p| {
             if let SerialPortType::UsbPort(info) = p.port_type {
-                if is_bleuio(&info) {
+                if let Some(model) = bleuio_model(&info) {
                     #[cfg(target_os = "macos")]
-                    return Some(p.port_name.replace("/dev/tty.", "/dev/cu."));
-                    
+                    return Some((p.port_name.replace("/dev/tty.", "/dev/cu."), model));
+
                     #[cfg(not(target_os = "macos"))]
-                    return Some(p.port_name);
+                    return Some((p.port_name, model));
                 }
             }
             None
         })
-        .unwrap_or_default()
+        .collect()
+}
+
+// Scan list of available USB devices and return device path of first detected BleuIO device.
+pub fn find_bleuio() -> String {
+    bleuio_ports().into_iter().next().map(|(port, _)| port).unwrap_or_default()
+}
+
+// Which kind of dongle is attached at `port_name`, None if it is not a known one.
+pub fn dongle_model(port_name: &str) -> Option<DongleModel> {
+    bleuio_ports().into_iter().find(|(port, _)| port == port_name).map(|(_, model)| model)
+}
+
+// The error code of an A line. The documentation describes it as hex, some
+// firmware versions send a number and others a string such as "0x00".
+pub fn parse_error_code(v: &Value) -> i64 {
+    match &v["err"] {
+        Value::Number(n) => n.as_i64().unwrap_or(-1),
+        Value::String(s) => i64::from_str_radix(s.trim_start_matches("0x"), 16).unwrap_or(-1),
+        _ => -1,
+    }
 }
 
 /* Test result strings:
@@ -125,6 +570,8 @@ pub fn get_bleuio_result_type(v: &Value) -> BleuIOResponseType {
         result_type = BleuIOResponseType::ScanTargetResponse;
     } else if v.get("SE").is_some() {
         result_type = BleuIOResponseType::ScanEndedResponse;
+    } else if v.as_object().is_some_and(|o| o.keys().any(|k| k.parse::<u32>().is_ok())) {
+        result_type = BleuIOResponseType::EventResponse;
     } else {
         result_type = BleuIOResponseType::UnknownResponse;
     }
@@ -136,3 +583,149 @@ pub fn parse_bleuio_result(json: &str) -> Result<Value> {
     let v: Value = serde_json::from_str(json)?; 
     Ok(v)
 }
+
+// The result of a command: error code and all reply lines between its C and E lines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandReply {
+    pub cmd: String,
+    pub err: i64,
+    pub err_msg: String,
+    pub replies: Vec<Value>,
+}
+
+impl CommandReply {
+    pub fn error_code(&self) -> Option<BleuIOErrorCode> {
+        BleuIOErrorCode::try_from(self.err).ok()
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.err == 0
+    }
+}
+
+struct PendingRequest {
+    index: Option<i64>,     // command index assigned by the dongle in the C line
+    reply: CommandReply,
+    tx: futures_channel::oneshot::Sender<CommandReply>,
+}
+
+// Matches the numbered C/A/R/E lines from the dongle with the requests waiting for them.
+// A request is identified by the command text echoed in its C line, after that by
+// the command index.
+#[derive(Default)]
+pub struct RequestTracker {
+    pending: VecDeque<PendingRequest>,
+}
+
+impl RequestTracker {
+    pub fn push(&mut self, line: &str, tx: futures_channel::oneshot::Sender<CommandReply>) {
+        self.pending.push_back(PendingRequest {
+            index: None,
+            reply: CommandReply { cmd: line.trim().to_string(), err: 0, err_msg: String::new(), replies: Vec::new() },
+            tx,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    fn find(&mut self, index: Option<i64>) -> Option<&mut PendingRequest> {
+        let index = index?;
+        self.pending.iter_mut().find(|p| p.index == Some(index))
+    }
+
+    // Feed a parsed JSON line from the dongle.
+    pub fn handle(&mut self, t: BleuIOResponseType, v: &Value) {
+        // Forget requests nobody waits for any more (timed out).
+        self.pending.retain(|p| !p.tx.is_canceled());
+
+        match t {
+            BleuIOResponseType::CommandResponse => {
+                let cmd = v["cmd"].as_str().unwrap_or("").trim();
+                if let Some(p) = self.pending.iter_mut().find(|p| p.index.is_none() && p.reply.cmd.eq_ignore_ascii_case(cmd)) {
+                    p.index = v["C"].as_i64();
+                }
+            }
+            BleuIOResponseType::AcknowledgementResponse => {
+                if let Some(p) = self.find(v["A"].as_i64()) {
+                    p.reply.err = parse_error_code(v);
+                    p.reply.err_msg = v["errMsg"].as_str().unwrap_or("").to_string();
+                }
+            }
+            BleuIOResponseType::ReplyResponse => {
+                if let Some(p) = self.find(v["R"].as_i64()) {
+                    p.reply.replies.push(v.clone());
+                }
+            }
+            BleuIOResponseType::EndResponse => {
+                let index = v["E"].as_i64();
+                if let Some(pos) = self.pending.iter().position(|p| index.is_some() && p.index == index) {
+                    if let Some(p) = self.pending.remove(pos) {
+                        p.tx.send(p.reply).ok();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compat(model: DongleModel, major: u16, minor: u16, patch: u16) -> Compatibility {
+        Compatibility { model: Some(model), firmware: Some(FirmwareVersion::new(major, minor, patch)) }
+    }
+
+    // Checks `supported` just below, at and just above `level` on `model`.
+    fn around(model: DongleModel, level: FirmwareVersion, supported: fn(&Compatibility) -> bool) {
+        let FirmwareVersion { major, minor, patch } = level;
+        let below = match (minor, patch) {
+            (0, 0) => compat(model, major - 1, 99, 0),
+            (_, 0) => compat(model, major, minor - 1, 99),
+            _ => compat(model, major, minor, patch - 1),
+        };
+        assert!(!supported(&below), "{:?} {:?}", model, below.firmware);
+        assert!(supported(&compat(model, major, minor, patch)), "{:?} {}", model, level);
+        assert!(supported(&compat(model, major, minor, patch + 1)), "{:?} {}", model, level);
+    }
+
+    #[test]
+    fn thresholds_per_model() {
+        for model in [DongleModel::BleuIO, DongleModel::BleuIOPro] {
+            let levels = model.firmware_levels();
+            around(model, levels.minimum, |c| c.warning().is_none());
+            around(model, levels.show_rssi, Compatibility::show_rssi);
+            around(model, levels.scan_params, Compatibility::scan_params);
+            around(model, levels.tx_power, Compatibility::tx_power);
+        }
+    }
+
+    #[test]
+    fn pro_firmware_is_numbered_on_its_own() {
+        let pro = compat(DongleModel::BleuIOPro, 1, 0, 3);
+        assert!(pro.warning().is_none());
+        assert!(pro.show_rssi() && pro.scan_params() && pro.tx_power());
+
+        let bleuio = compat(DongleModel::BleuIO, 1, 0, 3);
+        assert!(bleuio.warning().unwrap().contains("2.2.0"));
+        assert!(!bleuio.show_rssi() && !bleuio.scan_params() && !bleuio.tx_power());
+
+        let old = compat(DongleModel::BleuIO, 2, 3, 0);
+        assert!(old.warning().is_none());
+        assert!(old.show_rssi() && !old.scan_params() && !old.tx_power());
+    }
+
+    #[test]
+    fn unknown_model_or_firmware() {
+        let unknown = Compatibility { model: None, firmware: Some(FirmwareVersion::new(1, 0, 3)) };
+        assert_eq!(unknown.minimum(), DongleModel::BleuIO.firmware_levels().minimum);
+        assert!(unknown.warning().unwrap().starts_with("BleuIO firmware 1.0.3"));
+
+        let unread = Compatibility { model: Some(DongleModel::BleuIOPro), firmware: None };
+        assert!(unread.warning().is_some());
+        assert!(unread.show_rssi() && unread.scan_params() && unread.tx_power());
+    }
+}
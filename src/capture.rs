@@ -0,0 +1,128 @@
+use std::fs::File;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use tokio::io::{AsyncWriteExt, BufReader, DuplexStream};
+use tokio::time::{sleep, Duration};
+
+/* Capture file format, one line per serial line:
+    # dxbleuio capture 2026-01-17T10:15:00+01:00
+    0	>	ATE0
+    12	<	ECHO OFF
+    2051	<	{"SF":38,"rssi":-61,"addr":"F5:50:35:CF:B1:ED","type":0,"data":"0201061BFF5B07..."}
+   The first column is milliseconds since the capture started, the second the
+   direction ('>' sent to the dongle, '<' received from it).
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaptureDirection {
+    Sent,
+    Received,
+}
+
+impl CaptureDirection {
+    fn marker(&self) -> &'static str {
+        match self {
+            CaptureDirection::Sent => ">",
+            CaptureDirection::Received => "<",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureLine {
+    pub offset: Duration,
+    pub direction: CaptureDirection,
+    pub line: String,
+}
+
+// Parse one line of a capture file, None for comments and malformed lines.
+pub fn parse_capture_line(s: &str) -> Option<CaptureLine> {
+    if s.starts_with('#') {
+        return None;
+    }
+    let mut parts = s.splitn(3, '\t');
+    let offset = parts.next()?.parse::<u64>().ok()?;
+    let direction = match parts.next()? {
+        ">" => CaptureDirection::Sent,
+        "<" => CaptureDirection::Received,
+        _ => return None,
+    };
+    let line = parts.next()?.trim_end_matches(['\r', '\n']).to_string();
+    Some(CaptureLine {
+        offset: Duration::from_millis(offset),
+        direction,
+        line,
+    })
+}
+
+// Records serial traffic to a capture file.
+pub struct CaptureWriter {
+    path: PathBuf,
+    file: File,
+    started: Instant,
+}
+
+impl CaptureWriter {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut file = File::create(path)?;
+        writeln!(file, "# dxbleuio capture {}", chrono::Local::now().to_rfc3339())?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            started: Instant::now(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(&mut self, direction: CaptureDirection, line: &str) -> io::Result<()> {
+        writeln!(
+            self.file,
+            "{}\t{}\t{}",
+            self.started.elapsed().as_millis(),
+            direction.marker(),
+            line.trim_end_matches(['\r', '\n'])
+        )
+    }
+}
+
+pub fn read_capture(path: &Path) -> io::Result<Vec<CaptureLine>> {
+    let file = io::BufReader::new(File::open(path)?);
+    let mut lines = Vec::new();
+    for l in file.lines() {
+        if let Some(line) = parse_capture_line(&l?) {
+            lines.push(line);
+        }
+    }
+    Ok(lines)
+}
+
+// Play back the received lines of a capture file as if they came from the dongle.
+// `speed` 1.0 keeps the original timing, 10.0 plays ten times faster and 0.0 (or
+// less) sends everything as fast as the reader takes it. The returned reader hits
+// EOF when the capture is exhausted.
+pub fn replay(path: &Path, speed: f64) -> io::Result<BufReader<DuplexStream>> {
+    let lines = read_capture(path)?;
+    let (reader, mut writer) = tokio::io::duplex(64 * 1024);
+
+    tokio::spawn(async move {
+        let mut previous = Duration::ZERO;
+        for l in lines.into_iter().filter(|l| l.direction == CaptureDirection::Received) {
+            if speed > 0.0 {
+                let gap = l.offset.saturating_sub(previous);
+                sleep(gap.div_f64(speed)).await;
+            }
+            previous = l.offset;
+            let data = format!("{}\r\n", l.line);
+            if writer.write_all(data.as_bytes()).await.is_err() {
+                break; // reader dropped
+            }
+        }
+    });
+
+    Ok(BufReader::new(reader))
+}
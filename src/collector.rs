@@ -0,0 +1,74 @@
+// Headless collector, built with the `server` feature and started with
+// --headless. It owns the dongle like the desktop app does (handshake, scan
+// schedule, decoding, history) but has no window. Readings are published to
+// the HTTP API and the web dashboard is served from --web-root, so anyone on
+// the network can follow the sensors in a browser.
+
+use std::io;
+
+use futures_util::stream;
+use tracing::{error, info, info_span, warn, Instrument};
+
+use crate::api::{self, ApiState};
+use crate::bleuio::dongle_model;
+use crate::config::{AppConfig, Overflow};
+use crate::decoder::Reading;
+use crate::history::{self, HistoryStore};
+use crate::i18n::tr;
+use crate::options::Options;
+use crate::serial::{Frontend, SerialLoop};
+use crate::sinks::SinkPipeline;
+use crate::transport::Transport;
+
+pub fn run(options: Options) {
+    let Some(addr) = options.http else {
+        error!("{}", tr!("--headless needs --http <addr:port> to serve the dashboard on"));
+        return;
+    };
+    let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+        Ok(r) => r,
+        Err(e) => {
+            error!("{}", tr!("Can not start the collector: {}", e));
+            return;
+        }
+    };
+    runtime.block_on(async move {
+        let config = AppConfig::load();
+        let state = ApiState::new(history::default_dir());
+        let server = tokio::spawn(api::serve(addr, state.clone(), options.web_root.clone()));
+        info!(target: "sink", "{}", tr!("Serving the dashboard on http://{}", addr));
+        let mut sinks = SinkPipeline::from_config(&config.sinks);
+        sinks.add(Box::new(state), api::LIVE_BUFFER, Overflow::DropOldest);
+        let span = info_span!("serial", port = %options.transport.description());
+        if let Err(e) = collect(&options, &config, sinks).instrument(span).await {
+            error!(target: "serial", "{}: {}", options.transport.description(), e);
+        }
+        server.abort();
+    });
+}
+
+// The collector only keeps the history, the sinks get the readings.
+struct Collector {
+    history: HistoryStore,
+}
+
+impl Frontend for Collector {
+    fn reading(&mut self, reading: &Reading, _rssi: Option<i16>) {
+        if let Err(e) = self.history.record(reading) {
+            warn!(target: "history", sensor = %reading.id, "{}", tr!("Can not store history: {}", e));
+        }
+    }
+}
+
+// Read from the dongle until the port is closed.
+async fn collect(options: &Options, config: &AppConfig, sinks: SinkPipeline) -> io::Result<()> {
+    let (reader, writer) = options.transport.open()?;
+    let history = HistoryStore::open(&history::default_dir())?;
+    let model = match &options.transport {
+        Transport::Serial { port_name } => dongle_model(port_name),
+        Transport::Replay { .. } => None,
+    };
+    let mut serial = SerialLoop::new(writer, model, config, sinks, Collector { history });
+    // Nothing sends commands, the scan schedule runs by itself.
+    serial.run(reader, stream::pending(), options.transport.is_replay()).await
+}
@@ -0,0 +1,310 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::decoder::Metric;
+use crate::floor_plan::FloorPlan;
+use crate::i18n::{tr, Language};
+use crate::layout::DashboardLayout;
+use crate::paths;
+use crate::scan_filter::{ScanFilter, DEFAULT_FILTER};
+use crate::scanner::split_addr;
+use crate::units::Units;
+
+const CONFIG_FILE: &str = "config.json";
+
+// Limits for the scan settings, in seconds.
+pub const SCAN_DURATION_RANGE: (u32, u32) = (1, 3600);
+pub const SCAN_PAUSE_RANGE: (u32, u32) = (0, 3600);
+
+// How the sensor scan is run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanConfig {
+    pub duration: Option<u32>,      // s, None = until stopped
+    pub pause: u32,                 // s between the end of a scan and the next one
+    pub targets_only: bool,         // follow only the registered sensors with AT+SCANTARGET
+    pub filter: String,             // name of the filter in use
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        Self { duration: None, pause: 1, targets_only: false, filter: DEFAULT_FILTER.to_string() }
+    }
+}
+
+impl ScanConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(d) = self.duration {
+            if !(SCAN_DURATION_RANGE.0..=SCAN_DURATION_RANGE.1).contains(&d) {
+                return Err(tr!("Scan duration must be between {} and {} s", SCAN_DURATION_RANGE.0, SCAN_DURATION_RANGE.1));
+            }
+        }
+        if !(SCAN_PAUSE_RANGE.0..=SCAN_PAUSE_RANGE.1).contains(&self.pause) {
+            return Err(tr!("Pause must be between {} and {} s", SCAN_PAUSE_RANGE.0, SCAN_PAUSE_RANGE.1));
+        }
+        Ok(())
+    }
+}
+
+// A sensor whose address is known, as in "[1]D3:2A:7C:1B:4F:57". Without the
+// address type a public address is assumed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KnownSensor {
+    pub address: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub room: Option<String>,       // for grouping on the dashboard
+    #[serde(default)]
+    pub floor: Option<String>,
+    #[serde(default)]
+    pub position: Option<(f64, f64)>,   // on the floor plan, fractions of its width and height
+}
+
+impl KnownSensor {
+    // Check and normalize an address typed by the user.
+    pub fn new(address: &str, name: Option<String>) -> Result<Self, String> {
+        let (addr_type, addr) = split_addr(address.trim());
+        let parts: Vec<&str> = addr.split(':').collect();
+        if parts.len() != 6 || parts.iter().any(|p| p.len() != 2 || u8::from_str_radix(p, 16).is_err()) {
+            return Err(tr!("{} is not a Bluetooth address (XX:XX:XX:XX:XX:XX)", address.trim()));
+        }
+        let address = match addr_type {
+            Some(t) => format!("[{}]{}", t, addr.to_uppercase()),
+            None => addr.to_uppercase(),
+        };
+        Ok(Self { address, name: name.filter(|n| !n.trim().is_empty()), room: None, floor: None, position: None })
+    }
+
+    // Room and floor as typed by the user, blank for none.
+    pub fn set_place(&mut self, room: &str, floor: &str) {
+        let text = |s: &str| Some(s.trim().to_string()).filter(|s| !s.is_empty());
+        self.room = text(room);
+        self.floor = text(floor);
+    }
+
+    // The address without the type.
+    pub fn mac(&self) -> String {
+        split_addr(&self.address).1
+    }
+
+    // The address as AT+SCANTARGET wants it, with the address type.
+    pub fn target(&self) -> String {
+        match split_addr(&self.address) {
+            (Some(t), addr) => format!("[{}]{}", t, addr),
+            (None, addr) => format!("[0]{}", addr),
+        }
+    }
+}
+
+// Where an influxdb sink writes with the InfluxDB line protocol (needs the
+// influxdb feature). Works with InfluxDB v2 and anything that accepts its
+// /api/v2/write endpoint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InfluxConfig {
+    pub url: String,                        // e.g. http://localhost:8086
+    pub org: String,
+    pub bucket: String,
+    pub token: Option<String>,
+    pub measurement: String,
+    pub sensor_tag: String,                 // tag key for the sensor id
+    pub model_tag: Option<String>,          // tag key for the model, None = no tag
+    pub tags: BTreeMap<String, String>,     // added to every point, e.g. location=office
+    pub fields: BTreeMap<Metric, String>,   // field names other than the default ones
+    pub batch_size: usize,                  // points per write
+    pub flush_interval: u64,                // s, longest time a point waits
+}
+
+impl Default for InfluxConfig {
+    fn default() -> Self {
+        Self {
+            url: "http://localhost:8086".to_string(),
+            org: String::new(),
+            bucket: "hibouair".to_string(),
+            token: None,
+            measurement: "air_quality".to_string(),
+            sensor_tag: "sensor".to_string(),
+            model_tag: Some("model".to_string()),
+            tags: BTreeMap::new(),
+            fields: BTreeMap::new(),
+            batch_size: 100,
+            flush_interval: 10,
+        }
+    }
+}
+
+impl InfluxConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.url.starts_with("http://") && !self.url.starts_with("https://") {
+            return Err(tr!("{} is not an http or https URL", self.url));
+        }
+        if self.bucket.is_empty() || self.measurement.is_empty() || self.sensor_tag.is_empty() {
+            return Err(tr!("Bucket, measurement and sensor tag can not be empty").to_string());
+        }
+        if self.batch_size == 0 || self.flush_interval == 0 {
+            return Err(tr!("Batch size and flush interval must be at least 1").to_string());
+        }
+        Ok(())
+    }
+}
+
+// What a reading sink does, see sinks.rs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SinkKind {
+    File { path: PathBuf },         // JSON lines appended to a file
+    Influxdb(Box<InfluxConfig>),
+}
+
+// What happens to new readings when a sink has fallen a whole buffer behind.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Overflow {
+    #[default]
+    DropOldest,
+    DropNewest,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_sink_buffer() -> usize {
+    1000
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SinkConfig {
+    #[serde(flatten)]
+    pub kind: SinkKind,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "default_sink_buffer")]
+    pub buffer: usize,                  // readings waiting for the sink
+    #[serde(default)]
+    pub overflow: Overflow,
+}
+
+// When log files are started anew.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
+// Logging, see logging.rs. RUST_LOG overrides the filter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    pub filter: String,             // e.g. "info" or "info,serial=trace,decode=debug"
+    pub file: bool,                 // also write to files in the log directory
+    pub rotation: LogRotation,
+    pub max_files: usize,           // older log files are removed
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self { filter: "info".to_string(), file: false, rotation: LogRotation::Daily, max_files: 7 }
+    }
+}
+
+// Settings kept between runs. Fields missing from the file get their defaults,
+// so older files keep working when settings are added.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    pub scan: ScanConfig,
+    pub sensors: Vec<KnownSensor>,      // the sensor registry
+    pub filters: Vec<ScanFilter>,
+    pub sinks: Vec<SinkConfig>,         // where readings are sent besides the history
+    pub log: LogConfig,
+    pub language: Option<Language>,     // None = from the locale of the system
+    pub units: Units,                   // for showing and exporting readings
+    pub dashboard: DashboardLayout,
+    pub floor_plans: Vec<FloorPlan>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            scan: ScanConfig::default(),
+            sensors: Vec::new(),
+            filters: vec![ScanFilter::default()],
+            sinks: Vec::new(),
+            log: LogConfig::default(),
+            language: None,
+            units: Units::default(),
+            dashboard: DashboardLayout::default(),
+            floor_plans: Vec::new(),
+        }
+    }
+}
+
+impl AppConfig {
+    pub fn path() -> PathBuf {
+        paths::config_dir().join(CONFIG_FILE)
+    }
+
+    // The saved settings, or the defaults if there are none or they can not be read.
+    pub fn load() -> Self {
+        match fs::read_to_string(Self::path()) {
+            Ok(s) => serde_json::from_str(&s).unwrap_or_else(|e| {
+                eprintln!("{}", tr!("Ignoring invalid settings in {}: {}", Self::path().display(), e));
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn language(&self) -> Language {
+        self.language.unwrap_or_else(Language::from_env)
+    }
+
+    pub fn sensor(&self, address: &str) -> Option<&KnownSensor> {
+        let mac = split_addr(address).1;
+        self.sensors.iter().find(|s| s.mac().eq_ignore_ascii_case(&mac))
+    }
+
+    // The filter selected in the scan settings, the HibouAir filter if it is gone.
+    pub fn filter(&self) -> ScanFilter {
+        self.filters.iter().find(|f| f.name == self.scan.filter).cloned().unwrap_or_default()
+    }
+
+    // Floors with a plan or a sensor on them, in order.
+    pub fn floors(&self) -> Vec<String> {
+        let mut floors: Vec<String> = self
+            .floor_plans
+            .iter()
+            .map(|p| p.floor.clone())
+            .chain(self.sensors.iter().filter_map(|s| s.floor.clone()))
+            .collect();
+        floors.sort();
+        floors.dedup();
+        floors
+    }
+
+    pub fn floor_plan(&self, floor: &str) -> Option<&FloorPlan> {
+        self.floor_plans.iter().find(|p| p.floor == floor)
+    }
+
+    // Addresses for AT+SCANTARGET.
+    pub fn targets(&self) -> Vec<String> {
+        self.sensors.iter().map(KnownSensor::target).collect()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Local};
+
+use crate::bleuio::{BleuIOResponseType, KNOWN_COMMANDS};
+
+// Maximum number of lines kept in the console before the oldest are dropped.
+const MAX_LINES: usize = 5000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Tx,     // sent to the dongle
+    Rx,     // received from the dongle
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsoleLine {
+    pub at: DateTime<Local>,
+    pub direction: Direction,
+    pub text: String,
+    pub kind: Option<BleuIOResponseType>,   // None for TX lines and non-JSON replies
+}
+
+impl ConsoleLine {
+    pub fn timestamp(&self) -> String {
+        self.at.format("%H:%M:%S%.3f").to_string()
+    }
+
+    pub fn to_transcript_line(&self) -> String {
+        let dir = match self.direction {
+            Direction::Tx => "TX",
+            Direction::Rx => "RX",
+        };
+        format!("{} {} {}", self.at.format("%Y-%m-%d %H:%M:%S%.3f"), dir, self.text)
+    }
+}
+
+// Raw serial traffic to and from the dongle.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Console {
+    lines: VecDeque<ConsoleLine>,
+}
+
+impl Console {
+    fn push(&mut self, direction: Direction, text: &str, kind: Option<BleuIOResponseType>) {
+        if self.lines.len() == MAX_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(ConsoleLine {
+            at: Local::now(),
+            direction,
+            text: text.to_string(),
+            kind,
+        });
+    }
+
+    pub fn push_tx(&mut self, text: &str) {
+        self.push(Direction::Tx, text, None);
+    }
+
+    pub fn push_rx(&mut self, text: &str, kind: Option<BleuIOResponseType>) {
+        self.push(Direction::Rx, text, kind);
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+
+    // The last `n` lines, oldest first.
+    pub fn tail(&self, n: usize) -> impl Iterator<Item = &ConsoleLine> {
+        self.lines.iter().skip(self.lines.len().saturating_sub(n))
+    }
+
+    // The whole session as text, one timestamped line per TX/RX line.
+    pub fn transcript(&self) -> String {
+        self.lines
+            .iter()
+            .map(|l| l.to_transcript_line() + "\n")
+            .collect()
+    }
+}
+
+// Known commands starting with `prefix` (case insensitive).
+pub fn completions(prefix: &str) -> Vec<&'static str> {
+    let prefix = prefix.to_uppercase();
+    KNOWN_COMMANDS
+        .iter()
+        .copied()
+        .filter(|c| c.starts_with(&prefix))
+        .collect()
+}
+
+// Complete `input` as far as all matching known commands agree.
+pub fn complete(input: &str) -> Option<String> {
+    let matches = completions(input);
+    let first = matches.first()?;
+    let common = matches.iter().fold(first.len(), |len, c| {
+        first
+            .bytes()
+            .zip(c.bytes())
+            .take(len)
+            .take_while(|(a, b)| a == b)
+            .count()
+    });
+    if common > input.len() {
+        Some(first[..common].to_string())
+    } else {
+        None
+    }
+}
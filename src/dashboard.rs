@@ -0,0 +1,195 @@
+// Parts of the dashboard shared by the desktop app and the web build.
+
+use dioxus::prelude::*;
+
+use crate::decoder::{Metric, Reading};
+use crate::i18n::{self, tr, Language};
+use crate::units::{self, LightUnit, PressureUnit, TemperatureUnit, Units};
+
+// Number of columns in a sensor panel, metrics wrap onto more rows if needed.
+const PANEL_COLUMNS: usize = 8;
+
+#[component]
+pub fn SensorPanel(reading: Reading) -> Element {
+    let rssi = reading.rssi.map(|r| format!("RSSI: {} dBm", r)).unwrap_or_default();
+    let seen = i18n::datetime(&reading.at);
+    // Pad every row of metrics to the full width so labels and values line up.
+    let rows: Vec<Vec<Option<(String, String)>>> = reading
+        .values
+        .chunks(PANEL_COLUMNS)
+        .map(|chunk| {
+            let mut row: Vec<_> = chunk
+                .iter()
+                .map(|(metric, value)| Some((metric.label().to_string(), metric.format(*value))))
+                .collect();
+            row.resize(PANEL_COLUMNS, None);
+            row
+        })
+        .collect();
+
+    rsx! {
+        div {
+            class: "p-4 bg-green-700 rounded-lg shadow-md text-white flex justify-between items-center",
+            style: "display: grid; grid-template-columns: repeat(8, 1fr); gap: 4px 20px;",
+
+            // Headers #1
+            div { style: "font-weight: bold;", "{reading.model}" }
+            div { class: "col-span-2", "ID: {reading.id}" }
+            div { "{rssi}" }
+            div { class: "col-span-4", style: "text-align: right;", "{seen}" }
+            hr { class: "col-span-8 border-white/20 my-2" }
+
+            for row in rows {
+                // Headers #2
+                for cell in row.iter() {
+                    div { style: "font-weight: bold;", {cell.as_ref().map(|c| c.0.clone()).unwrap_or_default()} }
+                }
+                // Data Row
+                for cell in row.iter() {
+                    div { {cell.as_ref().map(|c| c.1.clone()).unwrap_or_default()} }
+                }
+            }
+
+            for (key, value) in reading.info.iter() {
+                div { class: "col-span-2 text-sm", "{tr!(key)}: {value}" }
+            }
+        }
+    }
+}
+
+// Switches the language of the whole app, `on_change` is for keeping the choice.
+#[component]
+pub fn LanguageSelect(on_change: EventHandler<Language>) -> Element {
+    let current = i18n::language();
+    rsx! {
+        select {
+            class: "border p-1 rounded-md",
+            style: "background: rgb(31, 28, 28);",
+            onchange: move |e| {
+                if let Some(language) = Language::from_code(&e.value()) {
+                    i18n::set_language(language);
+                    on_change.call(language);
+                }
+            },
+            for l in Language::ALL {
+                option { value: "{l.code()}", selected: l == current, "{l.name()}" }
+            }
+        }
+    }
+}
+
+// Picks the units readings are shown in, `on_change` is for keeping the choice.
+#[component]
+pub fn UnitsSelect(on_change: EventHandler<Units>) -> Element {
+    let current = units::units();
+    let update = move |units: Units| {
+        units::set_units(units);
+        on_change.call(units);
+    };
+    rsx! {
+        select {
+            class: "border p-1 rounded-md",
+            style: "background: rgb(31, 28, 28);",
+            title: tr!("Temperature"),
+            onchange: move |e| {
+                if let Some(&temperature) = TemperatureUnit::ALL.iter().find(|u| u.symbol() == e.value()) {
+                    update(Units { temperature, ..current });
+                }
+            },
+            for u in TemperatureUnit::ALL {
+                option { value: u.symbol(), selected: u == current.temperature, "{u.symbol()}" }
+            }
+        }
+        select {
+            class: "border p-1 rounded-md",
+            style: "background: rgb(31, 28, 28);",
+            title: tr!("Pressure"),
+            onchange: move |e| {
+                if let Some(&pressure) = PressureUnit::ALL.iter().find(|u| u.symbol() == e.value()) {
+                    update(Units { pressure, ..current });
+                }
+            },
+            for u in PressureUnit::ALL {
+                option { value: u.symbol(), selected: u == current.pressure, "{u.symbol()}" }
+            }
+        }
+        select {
+            class: "border p-1 rounded-md",
+            style: "background: rgb(31, 28, 28);",
+            title: tr!("Light"),
+            onchange: move |e| {
+                if let Some(&light) = LightUnit::ALL.iter().find(|u| u.symbol() == e.value()) {
+                    update(Units { light, ..current });
+                }
+            },
+            for u in LightUnit::ALL {
+                option { value: u.symbol(), selected: u == current.light, "{u.symbol()}" }
+            }
+        }
+    }
+}
+
+// Chart ranges selectable in the history panel, in seconds. Labels in
+// English, translated where they are shown.
+pub const HISTORY_RANGES: [(&str, i64); 3] = [("24 h", 86_400), ("7 days", 7 * 86_400), ("30 days", 30 * 86_400)];
+
+// The lowest and highest value of segments, None if they are empty.
+pub fn value_range<'a>(segments: impl IntoIterator<Item = &'a Vec<(i64, f64)>>) -> Option<(f64, f64)> {
+    let (min, max) = segments
+        .into_iter()
+        .flatten()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), (_, v)| (min.min(*v), max.max(*v)));
+    min.is_finite().then_some((min, max))
+}
+
+// Draws segments of (unix time, value) as SVG polylines, `since` at the left edge
+// and now at the right, `min` at the bottom and `max` at the top.
+pub fn polylines(segments: &[Vec<(i64, f64)>], since: i64, (min, max): (f64, f64), width: f64, height: f64) -> Vec<String> {
+    let span = (chrono::Local::now().timestamp() - since).max(1) as f64;
+    let range = (max - min).max(f64::EPSILON);
+    segments
+        .iter()
+        .map(|segment| {
+            segment
+                .iter()
+                .map(|(t, v)| {
+                    let x = (t - since) as f64 / span * width;
+                    let y = height - (v - min) / range * (height - 4.0) - 2.0;
+                    format!("{:.1},{:.1}", x, y)
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect()
+}
+
+// Chart of one metric over time, with the extremes above it.
+#[component]
+pub fn HistoryChart(metric: Metric, segments: Vec<Vec<(i64, f64)>>, since: i64) -> Element {
+    let range = value_range(&segments);
+    let lines = range.map(|range| polylines(&segments, since, range, 600.0, 100.0)).unwrap_or_default();
+    let (min, max) = range.unwrap_or_default();
+    let (min, max) = (tr!("max {}", metric.format(max)), tr!("min {}", metric.format(min)));
+    let empty = tr!("No stored {} values in this range.", metric.label());
+    rsx! {
+        if lines.is_empty() {
+            div { class: "mt-2", "{empty}" }
+        } else {
+            div { class: "flex justify-between mt-2", span { "{max}" } span { "{min}" } }
+            svg {
+                width: "100%",
+                height: "100",
+                view_box: "0 0 600 100",
+                preserve_aspect_ratio: "none",
+                for points in lines {
+                    polyline {
+                        points: "{points}",
+                        fill: "none",
+                        stroke: "#86efac",
+                        stroke_width: "1.5",
+                    }
+                }
+            }
+        }
+    }
+}
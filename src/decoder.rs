@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::advertising::{parse_ad_structures, parse_hex, AdStructure};
+use crate::i18n::tr;
+use crate::units;
+
+// A measured quantity. The unit is fixed per metric, decoders convert to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Metric {
+    Co2,            // ppm
+    Pm10,           // µg/m³
+    Pm2_5,          // µg/m³
+    Pm1_0,          // µg/m³
+    Voc,            // ppm
+    Iaq,            // indoor air quality index
+    Tvoc,           // µg/m³
+    Humidity,       // %rh
+    Temperature,    // °C
+    Pressure,       // hPa
+    Light,          // lux
+    Battery,        // %
+    Voltage,        // V
+    Distance,       // m, estimated from RSSI and calibrated TX power
+}
+
+impl Metric {
+    pub fn label(&self) -> &'static str {
+        tr!(match self {
+            Metric::Co2 => "CO2",
+            Metric::Pm10 => "PM10",
+            Metric::Pm2_5 => "PM2.5",
+            Metric::Pm1_0 => "PM1.0",
+            Metric::Voc => "VOC",
+            Metric::Iaq => "IAQ",
+            Metric::Tvoc => "TVOC",
+            Metric::Humidity => "Humidity",
+            Metric::Temperature => "Temp",
+            Metric::Pressure => "Pressure",
+            Metric::Light => "Light",
+            Metric::Battery => "Battery",
+            Metric::Voltage => "Voltage",
+            Metric::Distance => "Distance",
+        })
+    }
+
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Metric::Co2 => "ppm",
+            Metric::Pm10 | Metric::Pm2_5 | Metric::Pm1_0 | Metric::Tvoc => "µg/m³",
+            Metric::Voc => "ppm",
+            Metric::Iaq => "IAQ",
+            Metric::Humidity => "%rh",
+            Metric::Temperature => "°C",
+            Metric::Pressure => "hPa",
+            Metric::Light => "lux",
+            Metric::Battery => "%",
+            Metric::Voltage => "V",
+            Metric::Distance => "m",
+        }
+    }
+
+    pub fn decimals(&self) -> usize {
+        match self {
+            Metric::Co2 | Metric::Tvoc | Metric::Humidity | Metric::Pressure
+            | Metric::Light | Metric::Battery => 0,
+            Metric::Voltage => 2,
+            _ => 1,
+        }
+    }
+
+    // The value in the units picked by the user, see units.rs.
+    pub fn format(&self, value: f64) -> String {
+        units::units().format(*self, value)
+    }
+}
+
+// One decoded advertisement from a sensor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reading {
+    pub id: String,                     // stable id of the sensor, unique across decoders
+    pub address: String,                // MAC address the advertisement came from
+    pub model: String,                  // e.g. "HibouAir CO2", "RuuviTag"
+    pub decoder: String,                // name of the decoder that produced the reading
+    pub at: DateTime<Local>,            // when the advertisement was received
+    pub rssi: Option<i16>,
+    pub values: Vec<(Metric, f64)>,     // in display order
+    pub info: Vec<(String, String)>,    // non numeric details, e.g. iBeacon UUID
+}
+
+impl Reading {
+    pub fn new(id: String, model: &str, decoder: &'static str, adv: &Advertisement) -> Self {
+        Self {
+            id,
+            address: adv.addr.to_string(),
+            model: model.to_string(),
+            decoder: decoder.to_string(),
+            at: Local::now(),
+            rssi: adv.rssi,
+            values: Vec::new(),
+            info: Vec::new(),
+        }
+    }
+
+    pub fn with(mut self, metric: Metric, value: f64) -> Self {
+        self.values.push((metric, value));
+        self
+    }
+
+    pub fn with_info(mut self, key: &str, value: String) -> Self {
+        self.info.push((key.to_string(), value));
+        self
+    }
+
+    pub fn get(&self, metric: Metric) -> Option<f64> {
+        self.values.iter().find(|(m, _)| *m == metric).map(|(_, v)| *v)
+    }
+}
+
+// An advertisement as received from the dongle, with its AD structures parsed.
+pub struct Advertisement<'a> {
+    pub addr: &'a str,
+    pub rssi: Option<i16>,
+    pub data: &'a [u8],
+    pub ad: &'a [AdStructure],
+}
+
+impl Advertisement<'_> {
+    pub fn manufacturer_data(&self, company: u16) -> Option<&[u8]> {
+        self.ad.iter().find_map(|ad| match ad {
+            AdStructure::ManufacturerData { company_id, data } if *company_id == company => Some(data.as_slice()),
+            _ => None,
+        })
+    }
+
+    pub fn service_data(&self, service: u16) -> Option<&[u8]> {
+        self.ad.iter().find_map(|ad| match ad {
+            AdStructure::ServiceData16 { uuid, data } if *uuid == service => Some(data.as_slice()),
+            _ => None,
+        })
+    }
+}
+
+// What an advertisement is dispatched on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DecoderKey {
+    Manufacturer(u16),      // company id of manufacturer specific data
+    ServiceData(u16),       // 16-bit UUID of service data
+}
+
+pub trait AdvertisementDecoder: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // The manufacturer ids and service data UUIDs this decoder understands.
+    fn keys(&self) -> Vec<DecoderKey>;
+
+    // Decode the advertisement, None if it is not in a format this decoder knows.
+    fn decode(&self, adv: &Advertisement) -> Option<Reading>;
+}
+
+#[derive(Default)]
+pub struct DecoderRegistry {
+    decoders: Vec<Box<dyn AdvertisementDecoder>>,
+    by_key: HashMap<DecoderKey, Vec<usize>>,
+}
+
+impl DecoderRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // A registry with the HibouAir decoder and all built in decoders for open formats.
+    pub fn with_builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register(Box::new(crate::hibouair::HibouAirDecoder));
+        for decoder in crate::decoders::builtin() {
+            registry.register(decoder);
+        }
+        registry
+    }
+
+    pub fn register(&mut self, decoder: Box<dyn AdvertisementDecoder>) {
+        let index = self.decoders.len();
+        for key in decoder.keys() {
+            self.by_key.entry(key).or_default().push(index);
+        }
+        self.decoders.push(decoder);
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.decoders.iter().map(|d| d.name()).collect()
+    }
+
+    // Decode advertising data given as a hex string from a scan line.
+    pub fn decode_hex(&self, addr: &str, rssi: Option<i16>, hex: &str) -> Option<Reading> {
+        let data = parse_hex(hex)?;
+        let ad = parse_ad_structures(&data);
+        self.decode(&Advertisement { addr, rssi, data: &data, ad: &ad })
+    }
+
+    // Try the decoders registered for each manufacturer id and service data UUID
+    // in the advertisement, the first successful one wins.
+    pub fn decode(&self, adv: &Advertisement) -> Option<Reading> {
+        adv.ad
+            .iter()
+            .filter_map(|ad| match ad {
+                AdStructure::ManufacturerData { company_id, .. } => Some(DecoderKey::Manufacturer(*company_id)),
+                AdStructure::ServiceData16 { uuid, .. } => Some(DecoderKey::ServiceData(*uuid)),
+                _ => None,
+            })
+            .filter_map(|key| self.by_key.get(&key))
+            .flatten()
+            .find_map(|&i| self.decoders[i].decode(adv))
+    }
+}
@@ -0,0 +1,599 @@
+// Built in decoders for open sensor beacon formats.
+
+use crate::advertising::{format_uuid128, to_hex};
+use crate::decoder::{Advertisement, AdvertisementDecoder, DecoderKey, Metric, Reading};
+
+const RUUVI_COMPANY_ID: u16 = 0x0499;
+const APPLE_COMPANY_ID: u16 = 0x004C;
+const ENVIRONMENTAL_SENSING_UUID: u16 = 0x181A;     // used by ATC1441 and PVVX firmware
+const XIAOMI_MIBEACON_UUID: u16 = 0xFE95;
+const BTHOME_UUID: u16 = 0xFCD2;
+const EDDYSTONE_UUID: u16 = 0xFEAA;
+
+pub fn builtin() -> Vec<Box<dyn AdvertisementDecoder>> {
+    vec![
+        Box::new(RuuviDecoder),
+        Box::new(AtcPvvxDecoder),
+        Box::new(MiBeaconDecoder),
+        Box::new(BtHomeDecoder),
+        Box::new(IBeaconDecoder),
+        Box::new(EddystoneDecoder),
+    ]
+}
+
+fn be_u16(b: &[u8], i: usize) -> u16 {
+    u16::from_be_bytes([b[i], b[i + 1]])
+}
+
+fn be_i16(b: &[u8], i: usize) -> i16 {
+    i16::from_be_bytes([b[i], b[i + 1]])
+}
+
+fn le_u16(b: &[u8], i: usize) -> u16 {
+    u16::from_le_bytes([b[i], b[i + 1]])
+}
+
+fn le_i16(b: &[u8], i: usize) -> i16 {
+    i16::from_le_bytes([b[i], b[i + 1]])
+}
+
+fn le_u24(b: &[u8], i: usize) -> u32 {
+    u32::from_le_bytes([b[i], b[i + 1], b[i + 2], 0])
+}
+
+fn mac_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(":")
+}
+
+// Distance in metres from RSSI and the calibrated RSSI at 1 m, free space model.
+fn estimate_distance(rssi: i16, measured_power: i8) -> f64 {
+    10f64.powf((measured_power as f64 - rssi as f64) / 20.0)
+}
+
+// RuuviTag data format 5 (RAWv2).
+pub struct RuuviDecoder;
+
+impl AdvertisementDecoder for RuuviDecoder {
+    fn name(&self) -> &'static str {
+        "Ruuvi RAWv2"
+    }
+
+    fn keys(&self) -> Vec<DecoderKey> {
+        vec![DecoderKey::Manufacturer(RUUVI_COMPANY_ID)]
+    }
+
+    fn decode(&self, adv: &Advertisement) -> Option<Reading> {
+        let d = adv.manufacturer_data(RUUVI_COMPANY_ID)?;
+        if d.len() < 24 || d[0] != 0x05 {
+            return None;
+        }
+        let mut reading = Reading::new(mac_string(&d[18..24]), "RuuviTag", self.name(), adv);
+        // All fields use their maximum (minimum for signed) value for "not available".
+        let temp = be_i16(d, 1);
+        if temp != i16::MIN {
+            reading = reading.with(Metric::Temperature, temp as f64 * 0.005);
+        }
+        let hum = be_u16(d, 3);
+        if hum != u16::MAX {
+            reading = reading.with(Metric::Humidity, hum as f64 * 0.0025);
+        }
+        let pressure = be_u16(d, 5);
+        if pressure != u16::MAX {
+            reading = reading.with(Metric::Pressure, (pressure as f64 + 50000.0) / 100.0);
+        }
+        let power = be_u16(d, 13);
+        if power >> 5 != 0x7FF {
+            reading = reading.with(Metric::Voltage, ((power >> 5) as f64 + 1600.0) / 1000.0);
+        }
+        let (x, y, z) = (be_i16(d, 7), be_i16(d, 9), be_i16(d, 11));
+        Some(
+            reading
+                .with_info("Acceleration", format!("{} / {} / {} mG", x, y, z))
+                .with_info("TX power", format!("{} dBm", (power & 0x1F) as i32 * 2 - 40))
+                .with_info("Movements", d[15].to_string())
+                .with_info("Sequence", be_u16(d, 16).to_string()),
+        )
+    }
+}
+
+// Xiaomi LYWSD03MMC and similar thermometers running the ATC1441 or PVVX custom
+// firmware, advertising as Environmental Sensing service data.
+pub struct AtcPvvxDecoder;
+
+impl AdvertisementDecoder for AtcPvvxDecoder {
+    fn name(&self) -> &'static str {
+        "ATC/PVVX"
+    }
+
+    fn keys(&self) -> Vec<DecoderKey> {
+        vec![DecoderKey::ServiceData(ENVIRONMENTAL_SENSING_UUID)]
+    }
+
+    fn decode(&self, adv: &Advertisement) -> Option<Reading> {
+        let d = adv.service_data(ENVIRONMENTAL_SENSING_UUID)?;
+        match d.len() {
+            // ATC1441: MAC (big endian), temp 0.1 °C, humidity %, battery %, battery mV, counter
+            13 => Some(
+                Reading::new(mac_string(&d[0..6]), "ATC thermometer", self.name(), adv)
+                    .with(Metric::Temperature, be_i16(d, 6) as f64 / 10.0)
+                    .with(Metric::Humidity, d[8] as f64)
+                    .with(Metric::Battery, d[9] as f64)
+                    .with(Metric::Voltage, be_u16(d, 10) as f64 / 1000.0),
+            ),
+            // PVVX custom: MAC (little endian), temp 0.01 °C, humidity 0.01 %, battery mV, battery %, counter, flags
+            15 => {
+                let mut mac = d[0..6].to_vec();
+                mac.reverse();
+                Some(
+                    Reading::new(mac_string(&mac), "PVVX thermometer", self.name(), adv)
+                        .with(Metric::Temperature, le_i16(d, 6) as f64 / 100.0)
+                        .with(Metric::Humidity, le_u16(d, 8) as f64 / 100.0)
+                        .with(Metric::Battery, d[12] as f64)
+                        .with(Metric::Voltage, le_u16(d, 10) as f64 / 1000.0),
+                )
+            }
+            _ => None,
+        }
+    }
+}
+
+// Xiaomi MiBeacon, unencrypted frames only.
+pub struct MiBeaconDecoder;
+
+impl AdvertisementDecoder for MiBeaconDecoder {
+    fn name(&self) -> &'static str {
+        "Xiaomi MiBeacon"
+    }
+
+    fn keys(&self) -> Vec<DecoderKey> {
+        vec![DecoderKey::ServiceData(XIAOMI_MIBEACON_UUID)]
+    }
+
+    fn decode(&self, adv: &Advertisement) -> Option<Reading> {
+        let d = adv.service_data(XIAOMI_MIBEACON_UUID)?;
+        if d.len() < 5 {
+            return None;
+        }
+        let frame_control = le_u16(d, 0);
+        let has_mac = frame_control & 0x0010 != 0;
+        let has_capability = frame_control & 0x0020 != 0;
+        let has_object = frame_control & 0x0040 != 0;
+        let encrypted = frame_control & 0x0008 != 0;
+        if encrypted || !has_object {
+            return None;
+        }
+        let product_id = le_u16(d, 2);
+        let mut pos = 5;
+        let mut id = adv.addr.to_string();
+        if has_mac {
+            let mut mac = d.get(pos..pos + 6)?.to_vec();
+            mac.reverse();
+            id = mac_string(&mac);
+            pos += 6;
+        }
+        if has_capability {
+            let capability = *d.get(pos)?;
+            pos += if capability & 0x20 != 0 { 3 } else { 1 };
+        }
+
+        let mut reading = Reading::new(id, "Xiaomi sensor", self.name(), adv)
+            .with_info("Product id", format!("0x{:04X}", product_id));
+        while pos + 3 <= d.len() {
+            let object_type = le_u16(d, pos);
+            let len = d[pos + 2] as usize;
+            let v = d.get(pos + 3..pos + 3 + len)?;
+            reading = match (object_type, len) {
+                (0x1004, 2) => reading.with(Metric::Temperature, le_i16(v, 0) as f64 / 10.0),
+                (0x1006, 2) => reading.with(Metric::Humidity, le_u16(v, 0) as f64 / 10.0),
+                (0x100A, 1) => reading.with(Metric::Battery, v[0] as f64),
+                (0x100D, 4) => reading
+                    .with(Metric::Temperature, le_i16(v, 0) as f64 / 10.0)
+                    .with(Metric::Humidity, le_u16(v, 2) as f64 / 10.0),
+                (0x1007, 3) => reading.with(Metric::Light, le_u24(v, 0) as f64),
+                _ => reading,
+            };
+            pos += 3 + len;
+        }
+        if reading.values.is_empty() { None } else { Some(reading) }
+    }
+}
+
+// BTHome v2, unencrypted. Objects are only understood as far as their sizes are
+// known, decoding stops at the first unknown object id.
+pub struct BtHomeDecoder;
+
+// Size in bytes of the value of a BTHome object id.
+fn bthome_object_size(id: u8) -> Option<usize> {
+    match id {
+        0x00 | 0x01 | 0x09 | 0x0F..=0x11 | 0x15..=0x2F | 0x3A | 0x46 => Some(1),
+        0x02 | 0x03 | 0x06..=0x08 | 0x0C..=0x0E | 0x12..=0x14 | 0x3C | 0x3D
+        | 0x3F..=0x41 | 0x43..=0x45 | 0x47..=0x4A | 0x51 | 0x52 => Some(2),
+        0x04 | 0x05 | 0x0A | 0x0B | 0x42 | 0x4B => Some(3),
+        0x3E | 0x4C..=0x50 => Some(4),
+        _ => None,
+    }
+}
+
+impl AdvertisementDecoder for BtHomeDecoder {
+    fn name(&self) -> &'static str {
+        "BTHome v2"
+    }
+
+    fn keys(&self) -> Vec<DecoderKey> {
+        vec![DecoderKey::ServiceData(BTHOME_UUID)]
+    }
+
+    fn decode(&self, adv: &Advertisement) -> Option<Reading> {
+        let d = adv.service_data(BTHOME_UUID)?;
+        let device_info = *d.first()?;
+        let encrypted = device_info & 0x01 != 0;
+        let version = device_info >> 5;
+        if encrypted || version != 2 {
+            return None;
+        }
+        let mut reading = Reading::new(adv.addr.to_string(), "BTHome sensor", self.name(), adv);
+        let mut pos = 1;
+        while pos < d.len() {
+            let id = d[pos];
+            let Some(size) = bthome_object_size(id) else {
+                break;
+            };
+            let Some(v) = d.get(pos + 1..pos + 1 + size) else {
+                break;
+            };
+            reading = match id {
+                0x01 => reading.with(Metric::Battery, v[0] as f64),
+                0x02 => reading.with(Metric::Temperature, le_i16(v, 0) as f64 * 0.01),
+                0x03 => reading.with(Metric::Humidity, le_u16(v, 0) as f64 * 0.01),
+                0x04 => reading.with(Metric::Pressure, le_u24(v, 0) as f64 * 0.01),
+                0x05 => reading.with(Metric::Light, le_u24(v, 0) as f64 * 0.01),
+                0x0C => reading.with(Metric::Voltage, le_u16(v, 0) as f64 * 0.001),
+                0x0D => reading.with(Metric::Pm2_5, le_u16(v, 0) as f64),
+                0x0E => reading.with(Metric::Pm10, le_u16(v, 0) as f64),
+                0x12 => reading.with(Metric::Co2, le_u16(v, 0) as f64),
+                0x13 => reading.with(Metric::Tvoc, le_u16(v, 0) as f64),
+                0x2E => reading.with(Metric::Humidity, v[0] as f64),
+                0x45 => reading.with(Metric::Temperature, le_i16(v, 0) as f64 * 0.1),
+                0x4A => reading.with(Metric::Voltage, le_u16(v, 0) as f64 * 0.1),
+                _ => reading,
+            };
+            pos += 1 + size;
+        }
+        if reading.values.is_empty() { None } else { Some(reading) }
+    }
+}
+
+// Apple iBeacon. Has no measurements, but the distance can be estimated from
+// the RSSI and the calibrated power at 1 m.
+pub struct IBeaconDecoder;
+
+impl AdvertisementDecoder for IBeaconDecoder {
+    fn name(&self) -> &'static str {
+        "iBeacon"
+    }
+
+    fn keys(&self) -> Vec<DecoderKey> {
+        vec![DecoderKey::Manufacturer(APPLE_COMPANY_ID)]
+    }
+
+    fn decode(&self, adv: &Advertisement) -> Option<Reading> {
+        let d = adv.manufacturer_data(APPLE_COMPANY_ID)?;
+        if d.len() != 23 || d[0] != 0x02 || d[1] != 0x15 {
+            return None;
+        }
+        let mut uuid = [0u8; 16];
+        uuid.copy_from_slice(&d[2..18]);
+        let uuid = format_uuid128(&uuid);
+        let major = be_u16(d, 18);
+        let minor = be_u16(d, 20);
+        let measured_power = d[22] as i8;
+
+        let mut reading = Reading::new(format!("{}-{}-{}", uuid, major, minor), "iBeacon", self.name(), adv)
+            .with_info("UUID", uuid)
+            .with_info("Major", major.to_string())
+            .with_info("Minor", minor.to_string())
+            .with_info("Measured power", format!("{} dBm", measured_power));
+        if let Some(rssi) = adv.rssi {
+            reading = reading.with(Metric::Distance, estimate_distance(rssi, measured_power));
+        }
+        Some(reading)
+    }
+}
+
+// Google Eddystone UID, URL and TLM frames.
+pub struct EddystoneDecoder;
+
+fn eddystone_url(scheme: u8, encoded: &[u8]) -> String {
+    let mut url = match scheme {
+        0x00 => "http://www.",
+        0x01 => "https://www.",
+        0x02 => "http://",
+        0x03 => "https://",
+        _ => "",
+    }
+    .to_string();
+    for &b in encoded {
+        match b {
+            0x00 => url.push_str(".com/"),
+            0x01 => url.push_str(".org/"),
+            0x02 => url.push_str(".edu/"),
+            0x03 => url.push_str(".net/"),
+            0x04 => url.push_str(".info/"),
+            0x05 => url.push_str(".biz/"),
+            0x06 => url.push_str(".gov/"),
+            0x07 => url.push_str(".com"),
+            0x08 => url.push_str(".org"),
+            0x09 => url.push_str(".edu"),
+            0x0A => url.push_str(".net"),
+            0x0B => url.push_str(".info"),
+            0x0C => url.push_str(".biz"),
+            0x0D => url.push_str(".gov"),
+            0x21..=0x7E => url.push(b as char),
+            _ => {}
+        }
+    }
+    url
+}
+
+impl AdvertisementDecoder for EddystoneDecoder {
+    fn name(&self) -> &'static str {
+        "Eddystone"
+    }
+
+    fn keys(&self) -> Vec<DecoderKey> {
+        vec![DecoderKey::ServiceData(EDDYSTONE_UUID)]
+    }
+
+    fn decode(&self, adv: &Advertisement) -> Option<Reading> {
+        let d = adv.service_data(EDDYSTONE_UUID)?;
+        match *d.first()? {
+            // UID: TX power at 0 m, 10 byte namespace, 6 byte instance
+            0x00 if d.len() >= 18 => {
+                let tx_power = d[1] as i8;
+                let namespace = to_hex(&d[2..12]);
+                let instance = to_hex(&d[12..18]);
+                let mut reading = Reading::new(format!("{}-{}", namespace, instance), "Eddystone UID", self.name(), adv)
+                    .with_info("Namespace", namespace)
+                    .with_info("Instance", instance);
+                if let Some(rssi) = adv.rssi {
+                    // TX power is given at 0 m, 41 dBm is the usual loss over the first metre.
+                    reading = reading.with(Metric::Distance, estimate_distance(rssi, tx_power.saturating_sub(41)));
+                }
+                Some(reading)
+            }
+            // URL: TX power, scheme prefix, encoded URL
+            0x10 if d.len() >= 3 => {
+                let url = eddystone_url(d[2], &d[3..]);
+                Some(Reading::new(url.clone(), "Eddystone URL", self.name(), adv).with_info("URL", url))
+            }
+            // TLM (unencrypted): version, battery mV, temperature 8.8 fixed point, PDU count, uptime 0.1 s
+            0x20 if d.len() >= 14 && d[1] == 0x00 => {
+                let mut reading = Reading::new(adv.addr.to_string(), "Eddystone TLM", self.name(), adv);
+                let voltage = be_u16(d, 2);
+                if voltage != 0 {
+                    reading = reading.with(Metric::Voltage, voltage as f64 / 1000.0);
+                }
+                let temp = be_i16(d, 4);
+                if temp != i16::MIN {
+                    reading = reading.with(Metric::Temperature, temp as f64 / 256.0);
+                }
+                let adv_count = u32::from_be_bytes([d[6], d[7], d[8], d[9]]);
+                let uptime = u32::from_be_bytes([d[10], d[11], d[12], d[13]]);
+                Some(
+                    reading
+                        .with_info("Advertisements", adv_count.to_string())
+                        .with_info("Uptime", format!("{} s", uptime / 10)),
+                )
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::advertising::{parse_ad_structures, parse_hex};
+
+    const ADDR: &str = "D3:2A:7C:1B:4F:57";
+
+    // Advertising data with one AD structure of `ad_type` holding `id` (little
+    // endian) and the hex payload.
+    fn ad(ad_type: u8, id: u16, payload: &str) -> Vec<u8> {
+        let payload = parse_hex(payload).unwrap();
+        let mut data = vec![(3 + payload.len()) as u8, ad_type];
+        data.extend_from_slice(&id.to_le_bytes());
+        data.extend_from_slice(&payload);
+        data
+    }
+
+    fn decode(decoder: &dyn AdvertisementDecoder, data: &[u8], rssi: Option<i16>) -> Option<Reading> {
+        let ad = parse_ad_structures(data);
+        decoder.decode(&Advertisement { addr: ADDR, rssi, data, ad: &ad })
+    }
+
+    fn assert_value(reading: &Reading, metric: Metric, expected: f64) {
+        let value = reading.get(metric).unwrap_or_else(|| panic!("no {:?} in {:?}", metric, reading.values));
+        assert!((value - expected).abs() < 1e-6, "{:?} is {}, expected {}", metric, value, expected);
+    }
+
+    fn info<'a>(reading: &'a Reading, key: &str) -> &'a str {
+        reading.info.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str()).unwrap()
+    }
+
+    #[test]
+    fn ruuvi_valid_data() {
+        // The "valid data" test vector of the RAWv2 specification.
+        let data = ad(0xFF, RUUVI_COMPANY_ID, "0512FC5394C37C0004FFFC040CAC364200CDCBB8334C884F");
+        let r = decode(&RuuviDecoder, &data, Some(-70)).unwrap();
+        assert_eq!(r.id, "CB:B8:33:4C:88:4F");
+        assert_value(&r, Metric::Temperature, 24.3);
+        assert_value(&r, Metric::Humidity, 53.49);
+        assert_value(&r, Metric::Pressure, 1000.44);
+        assert_value(&r, Metric::Voltage, 2.977);
+        assert_eq!(info(&r, "Acceleration"), "4 / -4 / 1036 mG");
+        assert_eq!(info(&r, "TX power"), "4 dBm");
+        assert_eq!(info(&r, "Movements"), "66");
+        assert_eq!(info(&r, "Sequence"), "205");
+    }
+
+    #[test]
+    fn ruuvi_invalid_values_are_left_out() {
+        // The "invalid values" test vector of the specification.
+        let data = ad(0xFF, RUUVI_COMPANY_ID, "058000FFFFFFFF800080008000FFFFFFFFFFFFFFFFFFFFFF");
+        let r = decode(&RuuviDecoder, &data, None).unwrap();
+        assert!(r.values.is_empty(), "{:?}", r.values);
+    }
+
+    #[test]
+    fn ruuvi_other_formats_and_truncated() {
+        assert!(decode(&RuuviDecoder, &ad(0xFF, RUUVI_COMPANY_ID, "0312FC5394C37C0004FFFC040CAC364200CDCBB8334C884F"), None).is_none());
+        assert!(decode(&RuuviDecoder, &ad(0xFF, RUUVI_COMPANY_ID, "0512FC5394C37C0004FFFC040CAC364200CDCBB8334C88"), None).is_none());
+        assert!(decode(&RuuviDecoder, &ad(0xFF, RUUVI_COMPANY_ID, "05"), None).is_none());
+    }
+
+    #[test]
+    fn atc1441() {
+        let data = ad(0x16, ENVIRONMENTAL_SENSING_UUID, "A4C13811223300E62D5A0BB801");
+        let r = decode(&AtcPvvxDecoder, &data, None).unwrap();
+        assert_eq!(r.id, "A4:C1:38:11:22:33");
+        assert_eq!(r.model, "ATC thermometer");
+        assert_value(&r, Metric::Temperature, 23.0);
+        assert_value(&r, Metric::Humidity, 45.0);
+        assert_value(&r, Metric::Battery, 90.0);
+        assert_value(&r, Metric::Voltage, 3.0);
+    }
+
+    #[test]
+    fn pvvx() {
+        let data = ad(0x16, ENVIRONMENTAL_SENSING_UUID, "33221138C1A40B09A811860B550704");
+        let r = decode(&AtcPvvxDecoder, &data, None).unwrap();
+        assert_eq!(r.id, "A4:C1:38:11:22:33");
+        assert_eq!(r.model, "PVVX thermometer");
+        assert_value(&r, Metric::Temperature, 23.15);
+        assert_value(&r, Metric::Humidity, 45.2);
+        assert_value(&r, Metric::Voltage, 2.95);
+        assert_value(&r, Metric::Battery, 85.0);
+    }
+
+    #[test]
+    fn atc_pvvx_other_lengths() {
+        assert!(decode(&AtcPvvxDecoder, &ad(0x16, ENVIRONMENTAL_SENSING_UUID, "A4C13811223300E62D5A0BB8"), None).is_none());
+        assert!(decode(&AtcPvvxDecoder, &ad(0x16, ENVIRONMENTAL_SENSING_UUID, ""), None).is_none());
+    }
+
+    #[test]
+    fn mibeacon() {
+        // Frame control 0x5050 (MAC and object), product 0x055B, counter,
+        // MAC reversed, temperature and humidity object, battery object.
+        let data = ad(0x16, XIAOMI_MIBEACON_UUID, "50505B0501332211A8654C0D1004E600C2010A10015D");
+        let r = decode(&MiBeaconDecoder, &data, None).unwrap();
+        assert_eq!(r.id, "4C:65:A8:11:22:33");
+        assert_eq!(info(&r, "Product id"), "0x055B");
+        assert_value(&r, Metric::Temperature, 23.0);
+        assert_value(&r, Metric::Humidity, 45.0);
+        assert_value(&r, Metric::Battery, 93.0);
+    }
+
+    #[test]
+    fn mibeacon_without_mac_uses_the_address() {
+        let r = decode(&MiBeaconDecoder, &ad(0x16, XIAOMI_MIBEACON_UUID, "40505B0501041002FBFF"), None).unwrap();
+        assert_eq!(r.id, ADDR);
+        assert_value(&r, Metric::Temperature, -0.5);
+    }
+
+    #[test]
+    fn mibeacon_encrypted_and_truncated() {
+        // Encrypted.
+        assert!(decode(&MiBeaconDecoder, &ad(0x16, XIAOMI_MIBEACON_UUID, "58505B0501332211A8654C0D1004E600C201"), None).is_none());
+        // No object.
+        assert!(decode(&MiBeaconDecoder, &ad(0x16, XIAOMI_MIBEACON_UUID, "10505B0501332211A8654C"), None).is_none());
+        // MAC cut short.
+        assert!(decode(&MiBeaconDecoder, &ad(0x16, XIAOMI_MIBEACON_UUID, "50505B05013322"), None).is_none());
+        // Object longer than the data.
+        assert!(decode(&MiBeaconDecoder, &ad(0x16, XIAOMI_MIBEACON_UUID, "50505B0501332211A8654C0D1004E600"), None).is_none());
+        assert!(decode(&MiBeaconDecoder, &ad(0x16, XIAOMI_MIBEACON_UUID, "5050"), None).is_none());
+    }
+
+    #[test]
+    fn bthome() {
+        // The example of the BTHome v2 format description, with a battery object added.
+        let data = ad(0x16, BTHOME_UUID, "4002CA0903BF130164");
+        let r = decode(&BtHomeDecoder, &data, None).unwrap();
+        assert_eq!(r.id, ADDR);
+        assert_value(&r, Metric::Temperature, 25.06);
+        assert_value(&r, Metric::Humidity, 50.55);
+        assert_value(&r, Metric::Battery, 100.0);
+    }
+
+    #[test]
+    fn bthome_stops_at_unknown_or_truncated_objects() {
+        let r = decode(&BtHomeDecoder, &ad(0x16, BTHOME_UUID, "4002CA09F0010164"), None).unwrap();
+        assert_eq!(r.values.len(), 1);
+        assert_value(&r, Metric::Temperature, 25.06);
+        let r = decode(&BtHomeDecoder, &ad(0x16, BTHOME_UUID, "40126402037D"), None).unwrap();
+        assert_eq!(r.values, vec![(Metric::Co2, 612.0)]);
+        assert!(decode(&BtHomeDecoder, &ad(0x16, BTHOME_UUID, "4002CA"), None).is_none());
+        // Encrypted, and version 1.
+        assert!(decode(&BtHomeDecoder, &ad(0x16, BTHOME_UUID, "4102CA09"), None).is_none());
+        assert!(decode(&BtHomeDecoder, &ad(0x16, BTHOME_UUID, "2002CA09"), None).is_none());
+        assert!(decode(&BtHomeDecoder, &ad(0x16, BTHOME_UUID, ""), None).is_none());
+    }
+
+    #[test]
+    fn ibeacon() {
+        let data = ad(0xFF, APPLE_COMPANY_ID, "0215E2C56DB5DFFB48D2B060D0F5A71096E000010002C5");
+        let r = decode(&IBeaconDecoder, &data, Some(-59)).unwrap();
+        assert_eq!(r.id, "E2C56DB5-DFFB-48D2-B060-D0F5A71096E0-1-2");
+        assert_eq!(info(&r, "Measured power"), "-59 dBm");
+        // The RSSI equals the power at 1 m.
+        assert_value(&r, Metric::Distance, 1.0);
+        let r = decode(&IBeaconDecoder, &data, None).unwrap();
+        assert!(r.get(Metric::Distance).is_none());
+    }
+
+    #[test]
+    fn ibeacon_wrong_length_or_type() {
+        assert!(decode(&IBeaconDecoder, &ad(0xFF, APPLE_COMPANY_ID, "0215E2C56DB5DFFB48D2B060D0F5A71096E000010002"), None).is_none());
+        assert!(decode(&IBeaconDecoder, &ad(0xFF, APPLE_COMPANY_ID, "1005031C0C2A51"), None).is_none());
+    }
+
+    #[test]
+    fn eddystone_uid() {
+        let data = ad(0x16, EDDYSTONE_UUID, "00E700112233445566778899AABBCCDDEEFF0000");
+        let r = decode(&EddystoneDecoder, &data, Some(-66)).unwrap();
+        assert_eq!(r.id, "00112233445566778899-AABBCCDDEEFF");
+        // -25 dBm at 0 m is -66 dBm at 1 m.
+        assert_value(&r, Metric::Distance, 1.0);
+    }
+
+    #[test]
+    fn eddystone_url() {
+        let data = ad(0x16, EDDYSTONE_UUID, "10EB036578616D706C6507");
+        let r = decode(&EddystoneDecoder, &data, None).unwrap();
+        assert_eq!(r.id, "https://example.com");
+        let data = ad(0x16, EDDYSTONE_UUID, "10EB0067697468756200");
+        assert_eq!(decode(&EddystoneDecoder, &data, None).unwrap().id, "http://www.github.com/");
+    }
+
+    #[test]
+    fn eddystone_tlm() {
+        let data = ad(0x16, EDDYSTONE_UUID, "20000BB817800000006400000C80");
+        let r = decode(&EddystoneDecoder, &data, None).unwrap();
+        assert_eq!(r.id, ADDR);
+        assert_value(&r, Metric::Voltage, 3.0);
+        assert_value(&r, Metric::Temperature, 23.5);
+        assert_eq!(info(&r, "Advertisements"), "100");
+        assert_eq!(info(&r, "Uptime"), "320 s");
+    }
+
+    #[test]
+    fn eddystone_truncated() {
+        assert!(decode(&EddystoneDecoder, &ad(0x16, EDDYSTONE_UUID, "00E700112233445566778899AABBCCDDEE"), None).is_none());
+        assert!(decode(&EddystoneDecoder, &ad(0x16, EDDYSTONE_UUID, "10EB"), None).is_none());
+        assert!(decode(&EddystoneDecoder, &ad(0x16, EDDYSTONE_UUID, "20000BB817800000006400000C"), None).is_none());
+        // Encrypted TLM.
+        assert!(decode(&EddystoneDecoder, &ad(0x16, EDDYSTONE_UUID, "20010BB817800000006400000C80"), None).is_none());
+        assert!(decode(&EddystoneDecoder, &ad(0x16, EDDYSTONE_UUID, ""), None).is_none());
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// Number of packets kept per sensor for RSSI history and rate/loss estimates.
+const HISTORY_LEN: usize = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RssiSample {
+    pub at: Instant,    // when the packet was received
+    pub rssi: Option<i16>,  // signal strength in dBm, None when the dongle did not report it
+}
+
+// Reception statistics for one sensor, fed with every advertisement packet received.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SensorDiagnostics {
+    first_seen: Instant,
+    packets: u64,                       // total number of packets since first seen
+    history: VecDeque<RssiSample>,      // the last HISTORY_LEN packets
+    min_gap: Option<Duration>,          // shortest time seen between two packets
+}
+
+impl SensorDiagnostics {
+    pub fn new() -> Self {
+        Self {
+            first_seen: Instant::now(),
+            packets: 0,
+            history: VecDeque::with_capacity(HISTORY_LEN),
+            min_gap: None,
+        }
+    }
+
+    // Register a received packet. Packets without RSSI count for the rate and
+    // loss but not for the signal statistics.
+    pub fn record(&mut self, rssi: Option<i16>) {
+        self.record_at(Instant::now(), rssi);
+    }
+
+    pub fn record_at(&mut self, at: Instant, rssi: Option<i16>) {
+        if let Some(last) = self.history.back() {
+            let gap = at.saturating_duration_since(last.at);
+            // Packets reported twice in the same burst (e.g. scan responses) would
+            // make the interval estimate useless, so ignore very short gaps.
+            if gap >= Duration::from_millis(20) {
+                self.min_gap = Some(self.min_gap.map_or(gap, |g| g.min(gap)));
+            }
+        }
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(RssiSample { at, rssi });
+        self.packets += 1;
+    }
+
+    pub fn history(&self) -> impl Iterator<Item = &RssiSample> {
+        self.history.iter()
+    }
+
+    pub fn packets(&self) -> u64 {
+        self.packets
+    }
+
+    pub fn first_seen(&self) -> Instant {
+        self.first_seen
+    }
+
+    fn rssi(&self) -> impl DoubleEndedIterator<Item = i16> + '_ {
+        self.history.iter().filter_map(|s| s.rssi)
+    }
+
+    pub fn last_rssi(&self) -> Option<i16> {
+        self.rssi().next_back()
+    }
+
+    pub fn min_rssi(&self) -> Option<i16> {
+        self.rssi().min()
+    }
+
+    pub fn max_rssi(&self) -> Option<i16> {
+        self.rssi().max()
+    }
+
+    pub fn avg_rssi(&self) -> Option<f64> {
+        let (count, sum) = self.rssi().fold((0, 0.0), |(n, sum), r| (n + 1, sum + r as f64));
+        (count > 0).then(|| sum / count as f64)
+    }
+
+    // Time span covered by the packet history.
+    fn window(&self) -> Option<Duration> {
+        let first = self.history.front()?;
+        let last = self.history.back()?;
+        let span = last.at.saturating_duration_since(first.at);
+        if span.is_zero() { None } else { Some(span) }
+    }
+
+    // Received packets per minute over the history window.
+    pub fn packet_rate(&self) -> Option<f64> {
+        let span = self.window()?;
+        Some((self.history.len() - 1) as f64 * 60.0 / span.as_secs_f64())
+    }
+
+    // The sensor's advertising interval, estimated as the shortest gap seen
+    // between two packets. Lost packets only ever make the gaps longer.
+    pub fn adv_interval(&self) -> Option<Duration> {
+        self.min_gap
+    }
+
+    // Estimated share of advertisements (0.0 - 1.0) that never reached the dongle,
+    // comparing the packets received in the history window with the number the
+    // sensor should have sent at its advertising interval.
+    pub fn estimated_loss(&self) -> Option<f64> {
+        let span = self.window()?;
+        let interval = self.adv_interval()?;
+        let expected = span.as_secs_f64() / interval.as_secs_f64() + 1.0;
+        let received = self.history.len() as f64;
+        Some((1.0 - received / expected).clamp(0.0, 1.0))
+    }
+}
+
+impl Default for SensorDiagnostics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_rssi_is_not_a_strong_signal() {
+        let start = Instant::now();
+        let mut d = SensorDiagnostics::new();
+        d.record_at(start, Some(-80));
+        d.record_at(start + Duration::from_secs(1), None);
+        d.record_at(start + Duration::from_secs(2), Some(-70));
+        d.record_at(start + Duration::from_secs(3), None);
+        assert_eq!(d.packets(), 4);
+        assert_eq!(d.last_rssi(), Some(-70));
+        assert_eq!((d.min_rssi(), d.max_rssi()), (Some(-80), Some(-70)));
+        assert_eq!(d.avg_rssi(), Some(-75.0));
+        assert_eq!(d.packet_rate(), Some(60.0));
+        assert_eq!(d.adv_interval(), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn no_rssi_at_all() {
+        let start = Instant::now();
+        let mut d = SensorDiagnostics::new();
+        d.record_at(start, None);
+        d.record_at(start + Duration::from_secs(2), None);
+        assert_eq!(d.packets(), 2);
+        assert_eq!((d.last_rssi(), d.min_rssi(), d.max_rssi(), d.avg_rssi()), (None, None, None, None));
+        assert_eq!(d.packet_rate(), Some(30.0));
+    }
+}
@@ -0,0 +1,100 @@
+// Floor plans: an image per floor with the known sensors placed on it. Images
+// are copied to the settings directory when imported and shown as data URLs,
+// so the webview needs no access to the files. Sensor positions are fractions
+// of the width and height of the image and hold at any size it is shown in.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::decoder::Metric;
+use crate::i18n::tr;
+use crate::paths;
+
+const PLAN_DIR: &str = "floor_plans";
+
+// Metrics a plan can be coloured by.
+pub const PLAN_METRICS: [Metric; 3] = [Metric::Co2, Metric::Pm2_5, Metric::Temperature];
+
+// Image types that can be imported.
+pub const PLAN_EXTENSIONS: [&str; 4] = ["png", "svg", "jpg", "jpeg"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FloorPlan {
+    pub floor: String,              // as in KnownSensor
+    pub image: PathBuf,             // the copy in the settings directory
+}
+
+impl FloorPlan {
+    // Copy `source` to the settings directory as the plan of `floor`.
+    pub fn import(floor: &str, source: &Path) -> io::Result<Self> {
+        let extension = source
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .filter(|e| PLAN_EXTENSIONS.contains(&e.as_str()))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, tr!("Not a PNG, SVG or JPEG image")))?;
+        let name: String = floor.chars().map(|c| if c.is_alphanumeric() { c } else { '_' }).collect();
+        let dir = paths::config_dir().join(PLAN_DIR);
+        fs::create_dir_all(&dir)?;
+        let image = dir.join(format!("{}.{}", name, extension));
+        fs::copy(source, &image)?;
+        Ok(Self { floor: floor.to_string(), image })
+    }
+
+    // The image for the src of an img element.
+    pub fn data_url(&self) -> io::Result<String> {
+        let mime = match self.image.extension().and_then(|e| e.to_str()) {
+            Some("svg") => "image/svg+xml",
+            Some("png") => "image/png",
+            _ => "image/jpeg",
+        };
+        Ok(format!("data:{};base64,{}", mime, base64(&fs::read(&self.image)?)))
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+// A CSS colour for a value, green for good air and red for bad. Temperatures
+// go from blue when cold to red when hot.
+pub fn color(metric: Metric, value: f64) -> String {
+    let (low, high, hue) = match metric {
+        Metric::Co2 => (600.0, 1500.0, 120.0),      // ppm
+        Metric::Pm2_5 => (10.0, 50.0, 120.0),       // µg/m³
+        Metric::Temperature => (16.0, 28.0, 240.0), // °C
+        _ => (0.0, 100.0, 120.0),
+    };
+    let t = ((value - low) / (high - low)).clamp(0.0, 1.0);
+    format!("hsl({:.0}, 80%, 45%)", hue * (1.0 - t))
+}
+
+// The value at (x, y) weighted by the inverse square distance to the points
+// (x, y, value) that have one.
+pub fn interpolate(points: &[(f64, f64, f64)], x: f64, y: f64) -> Option<f64> {
+    let mut sum = 0.0;
+    let mut weights = 0.0;
+    for &(px, py, value) in points {
+        let d2 = (px - x).powi(2) + (py - y).powi(2);
+        if d2 < 1e-9 {
+            return Some(value);
+        }
+        sum += value / d2;
+        weights += 1.0 / d2;
+    }
+    (weights > 0.0).then(|| sum / weights)
+}
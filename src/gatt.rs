@@ -0,0 +1,395 @@
+use std::fmt;
+
+use serde_json::Value;
+use tokio::sync::broadcast;
+use tokio::time::{Duration, Instant};
+
+use crate::advertising::{parse_hex, to_hex};
+use crate::bleuio::{BleuIOClient, BleuIOCommand, BleuIOErrorCode, CommandError, CommandReply};
+use crate::i18n::tr;
+
+// How long to wait for the connection event after AT+GAPCONNECT.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GattError {
+    Timeout,
+    Dongle(Option<BleuIOErrorCode>, String),    // error reported in the A line
+    NotConnected,
+    InvalidResponse(String),
+    Closed,                                     // the serial task is gone
+}
+
+impl fmt::Display for GattError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GattError::Timeout => f.write_str(tr!("timeout waiting for the dongle")),
+            GattError::Dongle(Some(code), msg) => write!(f, "{} ({})", code.describe(), msg),
+            GattError::Dongle(None, msg) => f.write_str(&tr!("dongle error: {}", msg)),
+            GattError::NotConnected => f.write_str(tr!("not connected")),
+            GattError::InvalidResponse(s) => f.write_str(&tr!("unexpected response: {}", s)),
+            GattError::Closed => f.write_str(tr!("connection to the dongle closed")),
+        }
+    }
+}
+
+impl std::error::Error for GattError {}
+
+impl From<CommandError> for GattError {
+    fn from(e: CommandError) -> Self {
+        match e {
+            CommandError::Timeout => GattError::Timeout,
+            CommandError::Dongle(code, msg) => GattError::Dongle(code, msg),
+            CommandError::Closed => GattError::Closed,
+        }
+    }
+}
+
+pub type GattResult<T> = std::result::Result<T, GattError>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GattCharacteristic {
+    pub uuid: String,
+    pub handle: u16,            // value handle, used for read/write/notify
+    pub properties: String,     // as reported by the dongle, e.g. "READ, NOTIFY"
+}
+
+impl GattCharacteristic {
+    pub fn can(&self, property: &str) -> bool {
+        self.properties.to_uppercase().contains(property)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GattService {
+    pub uuid: String,
+    pub start_handle: Option<u16>,
+    pub end_handle: Option<u16>,
+    pub characteristics: Vec<GattCharacteristic>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GattNotification {
+    pub handle: u16,
+    pub value: Vec<u8>,
+}
+
+// Connection state changes and notifications, from event lines.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GattEvent {
+    Connected(String),
+    Disconnected,
+    Notification(GattNotification),
+}
+
+// Parse a handle given as "0x0012", "0012" or a number.
+fn parse_handle(v: &Value) -> Option<u16> {
+    match v {
+        Value::Number(n) => n.as_u64().and_then(|n| u16::try_from(n).ok()),
+        Value::String(s) => u16::from_str_radix(s.trim_start_matches("0x"), 16).ok(),
+        _ => None,
+    }
+}
+
+fn field<'a>(v: &'a Value, names: &[&str]) -> Option<&'a Value> {
+    names.iter().find_map(|n| v.get(n))
+}
+
+fn parse_value(v: &Value) -> Option<Vec<u8>> {
+    if let Some(hex) = field(v, &["hex", "value"]).and_then(Value::as_str) {
+        return parse_hex(&hex.trim_start_matches("0x").replace([' ', ':'], ""));
+    }
+    field(v, &["ascii"]).and_then(Value::as_str).map(|s| s.as_bytes().to_vec())
+}
+
+// Interpret an event line. The dongle reports events as {"<event code>":"<connection
+// index>", ...}, we go by the fields present rather than the codes.
+pub fn parse_event(v: &Value) -> Option<GattEvent> {
+    if let (Some(handle), Some(value)) = (field(v, &["handle"]).and_then(parse_handle), parse_value(v)) {
+        return Some(GattEvent::Notification(GattNotification { handle, value }));
+    }
+    let action = field(v, &["action", "evt"]).and_then(Value::as_str).unwrap_or("").to_lowercase();
+    if action.contains("disconnected") {
+        Some(GattEvent::Disconnected)
+    } else if action.contains("connected") {
+        let addr = field(v, &["addr", "address"]).and_then(Value::as_str).unwrap_or("");
+        Some(GattEvent::Connected(addr.to_string()))
+    } else {
+        None
+    }
+}
+
+// Collect services and characteristics from the reply lines of AT+GETSERVICES.
+// Objects with a UUID and properties are characteristics belonging to the
+// service seen last, other objects with a UUID are services.
+pub fn parse_services(replies: &[Value]) -> Vec<GattService> {
+    fn walk(v: &Value, services: &mut Vec<GattService>) {
+        match v {
+            Value::Array(items) => items.iter().for_each(|i| walk(i, services)),
+            Value::Object(o) => {
+                if let Some(uuid) = field(v, &["uuid", "UUID"]).and_then(Value::as_str) {
+                    if let Some(properties) = field(v, &["properties", "prop", "property"]) {
+                        // A characteristic before any service has nothing to belong to.
+                        if let Some(service) = services.last_mut() {
+                            let properties = match properties {
+                                Value::Array(a) => a.iter().filter_map(Value::as_str).collect::<Vec<_>>().join(", "),
+                                p => p.as_str().map(str::to_string).unwrap_or_else(|| p.to_string()),
+                            };
+                            let handle = field(v, &["value_handle", "handle_value", "handle"]).and_then(parse_handle).unwrap_or(0);
+                            service.characteristics.push(GattCharacteristic { uuid: uuid.to_string(), handle, properties });
+                        }
+                    } else {
+                        services.push(GattService {
+                            uuid: uuid.to_string(),
+                            start_handle: field(v, &["start", "start_handle", "handle"]).and_then(parse_handle),
+                            end_handle: field(v, &["end", "end_handle"]).and_then(parse_handle),
+                            characteristics: Vec::new(),
+                        });
+                    }
+                }
+                for child in o.values().filter(|c| c.is_array() || c.is_object()) {
+                    walk(child, services);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut services = Vec::new();
+    for r in replies {
+        walk(r, &mut services);
+    }
+    services
+}
+
+// GATT client on top of the BleuIO connection. Cheap to clone, all clones talk
+// to the same serial task.
+#[derive(Clone)]
+pub struct GattClient {
+    dongle: BleuIOClient,
+    events: broadcast::Sender<Value>,
+}
+
+impl GattClient {
+    pub fn new(dongle: BleuIOClient, events: broadcast::Sender<Value>) -> Self {
+        Self { dongle, events }
+    }
+
+    // Events from the dongle as they arrive.
+    pub fn events(&self) -> broadcast::Receiver<Value> {
+        self.events.subscribe()
+    }
+
+    // Send a command and wait for all its lines.
+    pub async fn request(&self, line: &str) -> GattResult<CommandReply> {
+        Ok(self.dongle.request(line).await?)
+    }
+
+    // Connect to a peripheral. `addr` may include the address type, as in
+    // "[1]C8:47:8C:00:00:01", without it a public address is assumed.
+    pub async fn connect(&self, addr: &str) -> GattResult<()> {
+        // Scanning and connecting can not be done at the same time, the sensor
+        // scan is paused until `disconnect`.
+        self.dongle.send(BleuIOCommand::PauseScan)?;
+        let result = self.try_connect(addr).await;
+        if result.is_err() {
+            self.dongle.send(BleuIOCommand::ResumeScan).ok();
+        }
+        result
+    }
+
+    async fn try_connect(&self, addr: &str) -> GattResult<()> {
+        let addr = if addr.starts_with('[') { addr.to_string() } else { format!("[0]{}", addr) };
+        let mut events = self.events();
+        match self.request("AT+CENTRAL").await {
+            Ok(_) | Err(GattError::Dongle(Some(BleuIOErrorCode::AlreadyDone), _)) => {}
+            Err(e) => return Err(e),
+        }
+        self.request(&format!("AT+GAPCONNECT={}", addr)).await?;
+
+        let deadline = Instant::now() + CONNECT_TIMEOUT;
+        loop {
+            let v = match tokio::time::timeout_at(deadline, events.recv()).await {
+                Ok(Ok(v)) => v,
+                Ok(Err(broadcast::error::RecvError::Lagged(_))) => continue,
+                Ok(Err(broadcast::error::RecvError::Closed)) => return Err(GattError::Closed),
+                Err(_) => return Err(GattError::Timeout),
+            };
+            match parse_event(&v) {
+                Some(GattEvent::Connected(_)) => return Ok(()),
+                Some(GattEvent::Disconnected) => return Err(GattError::NotConnected),
+                _ => {}
+            }
+        }
+    }
+
+    // Disconnect and start the sensor scan again, also when the dongle reports
+    // an error.
+    pub async fn disconnect(&self) -> GattResult<()> {
+        let result = self.request("AT+GAPDISCONNECT").await.map(|_| ());
+        self.dongle.send(BleuIOCommand::ResumeScan)?;
+        result
+    }
+
+    pub async fn discover(&self) -> GattResult<Vec<GattService>> {
+        let reply = self.request("AT+GETSERVICES").await?;
+        Ok(parse_services(&reply.replies))
+    }
+
+    pub async fn read(&self, handle: u16) -> GattResult<Vec<u8>> {
+        let reply = self.request(&format!("AT+GATTCREAD={:04X}", handle)).await?;
+        reply
+            .replies
+            .iter()
+            .find_map(parse_value)
+            .ok_or_else(|| GattError::InvalidResponse(format!("{:?}", reply.replies)))
+    }
+
+    pub async fn write(&self, handle: u16, value: &[u8]) -> GattResult<()> {
+        self.request(&format!("AT+GATTCWRITEB={:04X} {}", handle, to_hex(value)))
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn subscribe(&self, handle: u16) -> GattResult<()> {
+        self.request(&format!("AT+SETNOTI={:04X}", handle)).await.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    fn lines(lines: &[&str]) -> Vec<Value> {
+        lines.iter().map(|l| serde_json::from_str(l).unwrap()).collect()
+    }
+
+    fn characteristic(uuid: &str, handle: u16, properties: &str) -> GattCharacteristic {
+        GattCharacteristic { uuid: uuid.to_string(), handle, properties: properties.to_string() }
+    }
+
+    #[test]
+    fn services_one_per_line() {
+        let replies = lines(&[
+            r#"{"R":12,"uuid":"1800","start":"0x0001","end":"0x0007"}"#,
+            r#"{"R":12,"uuid":"2A00","properties":"READ","value_handle":"0x0003"}"#,
+            r#"{"R":12,"uuid":"2A01","properties":"READ","value_handle":"0005"}"#,
+            r#"{"R":12,"uuid":"0000180F-0000-1000-8000-00805F9B34FB","start_handle":12,"end_handle":15}"#,
+            r#"{"R":12,"uuid":"2A19","prop":["READ","NOTIFY"],"handle":"0x000E"}"#,
+        ]);
+        let services = parse_services(&replies);
+        assert_eq!(
+            services,
+            [
+                GattService {
+                    uuid: "1800".to_string(),
+                    start_handle: Some(1),
+                    end_handle: Some(7),
+                    characteristics: vec![characteristic("2A00", 3, "READ"), characteristic("2A01", 5, "READ")],
+                },
+                GattService {
+                    uuid: "0000180F-0000-1000-8000-00805F9B34FB".to_string(),
+                    start_handle: Some(12),
+                    end_handle: Some(15),
+                    characteristics: vec![characteristic("2A19", 14, "READ, NOTIFY")],
+                },
+            ]
+        );
+        let battery = &services[1].characteristics[0];
+        assert!(battery.can("NOTIFY") && !battery.can("WRITE"));
+    }
+
+    #[test]
+    fn services_nested_in_one_line() {
+        let replies = vec![json!({"R": 12, "services": [
+            {"UUID": "180A", "handle": 16, "characteristics": [
+                {"UUID": "2A29", "property": "read", "handle_value": 18},
+                {"UUID": "2A24", "property": 2, "handle_value": 20},
+            ]},
+            {"UUID": "6E400001-B5A3-F393-E0A9-E50E24DCCA9E", "characteristics": []},
+        ]})];
+        let services = parse_services(&replies);
+        assert_eq!(services.len(), 2);
+        assert_eq!(services[0].start_handle, Some(16));
+        assert_eq!(services[0].end_handle, None);
+        // Properties given as a number are kept as text.
+        assert_eq!(services[0].characteristics, [characteristic("2A29", 18, "read"), characteristic("2A24", 20, "2")]);
+        assert!(services[0].characteristics[0].can("READ"));
+        assert!(services[1].characteristics.is_empty());
+    }
+
+    #[test]
+    fn malformed_service_lines() {
+        let replies = lines(&[
+            // Belongs to no service.
+            r#"{"R":12,"uuid":"2A00","properties":"READ","value_handle":"0x0003"}"#,
+            r#"{"R":12,"text":"Services:"}"#,
+            r#"{"R":12,"uuid":"1801","start":"0xZZZZ"}"#,
+            r#"{"R":12,"uuid":"2A05","properties":"INDICATE","value_handle":"not a handle"}"#,
+            r#"{"R":12,"uuid":42}"#,
+            r#""SERVICES""#,
+        ]);
+        let services = parse_services(&replies);
+        assert_eq!(services.len(), 1);
+        assert_eq!(services[0].uuid, "1801");
+        assert_eq!(services[0].start_handle, None);
+        assert_eq!(services[0].characteristics, [characteristic("2A05", 0, "INDICATE")]);
+        assert!(parse_services(&[]).is_empty());
+    }
+
+    #[test]
+    fn notifications() {
+        let events = lines(&[
+            r#"{"256":"0000","evt":"notification","handle":"0x000E","hex":"0x5A"}"#,
+            r#"{"256":"0000","handle":"0012","value":"01 02:03"}"#,
+            r#"{"256":"0000","handle":18,"ascii":"HibouAir"}"#,
+        ]);
+        let parsed: Vec<_> = events.iter().map(parse_event).collect();
+        assert_eq!(
+            parsed,
+            [
+                Some(GattEvent::Notification(GattNotification { handle: 0x0E, value: vec![0x5A] })),
+                Some(GattEvent::Notification(GattNotification { handle: 0x12, value: vec![1, 2, 3] })),
+                Some(GattEvent::Notification(GattNotification { handle: 18, value: b"HibouAir".to_vec() })),
+            ]
+        );
+    }
+
+    #[test]
+    fn connection_events() {
+        let connected = json!({"1024": "0000", "action": "Connected", "addr": "[1]C8:47:8C:00:00:01"});
+        assert_eq!(parse_event(&connected), Some(GattEvent::Connected("[1]C8:47:8C:00:00:01".to_string())));
+        let connected = json!({"1024": "0000", "evt": "connected"});
+        assert_eq!(parse_event(&connected), Some(GattEvent::Connected(String::new())));
+        let disconnected = json!({"1025": "0000", "action": "disconnected", "addr": "[1]C8:47:8C:00:00:01"});
+        assert_eq!(parse_event(&disconnected), Some(GattEvent::Disconnected));
+    }
+
+    #[test]
+    fn malformed_events() {
+        let events = lines(&[
+            // Odd number of hex digits, a handle out of range, no value.
+            r#"{"256":"0000","handle":"0x000E","hex":"0x5A1"}"#,
+            r#"{"256":"0000","handle":70000,"hex":"5A"}"#,
+            r#"{"256":"0000","handle":"0x000E"}"#,
+            r#"{"512":"0000","action":"advertising"}"#,
+            r#"{"512":"0000"}"#,
+        ]);
+        for event in &events {
+            assert_eq!(parse_event(event), None, "{}", event);
+        }
+    }
+
+    #[test]
+    fn handles() {
+        assert_eq!(parse_handle(&json!("0x0012")), Some(0x12));
+        assert_eq!(parse_handle(&json!("FFFF")), Some(0xFFFF));
+        assert_eq!(parse_handle(&json!(65535)), Some(0xFFFF));
+        assert_eq!(parse_handle(&json!(65536)), None);
+        assert_eq!(parse_handle(&json!(-1)), None);
+        assert_eq!(parse_handle(&json!("12345")), None);
+        assert_eq!(parse_handle(&json!(null)), None);
+    }
+}
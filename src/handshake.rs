@@ -0,0 +1,410 @@
+// Connection state machine for bringing up the dongle:
+//
+//     Opening -> Resetting -> Configuring(step...) -> Ready <-> Scanning
+//                                   |
+//                                   +--> Error (after too many failed attempts)
+//
+// Resetting stops a scan left running by a previous session and waits until
+// the dongle has been quiet for a moment, so stale output is not taken as the
+// answer to our first command. Every configuring step has a timeout and is
+// retried a few times before giving up. Scanning is started by the scan
+// scheduler once the link is Ready.
+//
+// The state machine does no I/O, it is fed parsed lines and clock ticks and
+// returns the actions for the serial task to carry out.
+
+use serde_json::Value;
+use tokio::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::bleuio::{parse_error_code, BleuIOResponseType, Compatibility, DongleInfo, DongleModel};
+use crate::bleuio::{ATE0, ATI, ATV1, AT_SHOWRSSI, STOP_SCAN};
+use crate::i18n::tr;
+
+// How long the dongle must be quiet before the stale output counts as flushed.
+const QUIET_TIME: Duration = Duration::from_millis(500);
+// Longest time spent flushing, a dongle that keeps talking is configured anyway.
+const FLUSH_TIME: Duration = Duration::from_secs(3);
+// How long to wait for the E line of a configuring step.
+const STEP_TIMEOUT: Duration = Duration::from_secs(2);
+// Attempts per step before the handshake fails.
+const MAX_ATTEMPTS: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Step {
+    EchoOff,
+    Verbose,
+    Info,
+    ShowRssi,
+}
+
+impl Step {
+    pub fn command(&self) -> &'static [u8] {
+        match self {
+            Step::EchoOff => ATE0,
+            Step::Verbose => ATV1,
+            Step::Info => ATI,
+            Step::ShowRssi => AT_SHOWRSSI,
+        }
+    }
+
+    fn command_text(&self) -> String {
+        String::from_utf8_lossy(self.command()).trim().to_string()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkState {
+    Opening,
+    Resetting,
+    Configuring(Step),
+    Scanning,
+    Ready,              // configured, no scan running
+    Error(String),
+}
+
+impl LinkState {
+    pub fn description(&self) -> String {
+        match self {
+            LinkState::Opening => tr!("Opening port").to_string(),
+            LinkState::Resetting => tr!("Resetting dongle").to_string(),
+            LinkState::Configuring(step) => tr!("Configuring ({})", step.command_text()),
+            LinkState::Scanning => tr!("Scanning").to_string(),
+            LinkState::Ready => tr!("Ready").to_string(),
+            LinkState::Error(e) => tr!("Error: {}", e),
+        }
+    }
+}
+
+// What the serial task should do.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Send(&'static [u8]),
+    StateChanged(LinkState),
+    StepDone(Step),
+    Compatibility(Compatibility),
+}
+
+pub struct Handshake {
+    state: LinkState,
+    model: Option<DongleModel>,
+    deadline: Instant,          // of the current step
+    flush_until: Instant,       // hard limit for Resetting
+    attempts: u32,
+    index: Option<i64>,         // command index of the current step
+    error: i64,                 // from the A line of the current step
+    info: DongleInfo,
+    saw_scan: bool,             // scan lines while resetting
+}
+
+impl Handshake {
+    pub fn new(model: Option<DongleModel>, now: Instant) -> Self {
+        Self {
+            state: LinkState::Opening,
+            model,
+            deadline: now,
+            flush_until: now,
+            attempts: 0,
+            index: None,
+            error: 0,
+            info: DongleInfo::default(),
+            saw_scan: false,
+        }
+    }
+
+    pub fn state(&self) -> &LinkState {
+        &self.state
+    }
+
+    fn set_state(&mut self, state: LinkState, actions: &mut Vec<Action>) {
+        info!(target: "protocol", "{}", state.description());
+        self.state = state.clone();
+        actions.push(Action::StateChanged(state));
+    }
+
+    // The port is open, (re)start from the beginning.
+    pub fn start(&mut self, now: Instant) -> Vec<Action> {
+        let mut actions = Vec::new();
+        self.saw_scan = false;
+        self.deadline = now + QUIET_TIME;
+        self.flush_until = now + FLUSH_TIME;
+        self.set_state(LinkState::Resetting, &mut actions);
+        actions.push(Action::Send(STOP_SCAN));
+        actions
+    }
+
+    fn begin(&mut self, step: Step, now: Instant, actions: &mut Vec<Action>) {
+        self.attempts = 1;
+        self.send_step(step, now, actions);
+    }
+
+    fn send_step(&mut self, step: Step, now: Instant, actions: &mut Vec<Action>) {
+        self.index = None;
+        self.error = 0;
+        self.deadline = now + STEP_TIMEOUT;
+        if step == Step::Info {
+            self.info = DongleInfo::default();
+        }
+        self.set_state(LinkState::Configuring(step), actions);
+        actions.push(Action::Send(step.command()));
+    }
+
+    fn retry(&mut self, step: Step, reason: &str, now: Instant, actions: &mut Vec<Action>) {
+        if self.attempts >= MAX_ATTEMPTS {
+            warn!(target: "protocol", step = %step.command_text(), attempts = self.attempts, reason, "{}", tr!("Giving up"));
+            let message = tr!("{} failed after {} attempts", step.command_text(), self.attempts);
+            self.set_state(LinkState::Error(message), actions);
+        } else {
+            self.attempts += 1;
+            info!(target: "protocol", step = %step.command_text(), reason, "{}", tr!("Trying again"));
+            self.send_step(step, now, actions);
+        }
+    }
+
+    // The step after `step`, given what is known about the firmware.
+    fn next(&mut self, step: Step, now: Instant, actions: &mut Vec<Action>) {
+        actions.push(Action::StepDone(step));
+        let next = match step {
+            Step::EchoOff => Step::Verbose,
+            Step::Verbose => Step::Info,
+            Step::Info => {
+                let compat = Compatibility { model: self.model, firmware: self.info.firmware_version() };
+                if let Some(warning) = compat.warning() {
+                    warn!(target: "protocol", "{}", warning);
+                }
+                let show_rssi = compat.show_rssi();
+                actions.push(Action::Compatibility(compat));
+                if !show_rssi {
+                    self.set_state(LinkState::Ready, actions);
+                    return;
+                }
+                Step::ShowRssi
+            }
+            Step::ShowRssi => {
+                self.set_state(LinkState::Ready, actions);
+                return;
+            }
+        };
+        self.begin(next, now, actions);
+    }
+
+    // Feed a line from the dongle.
+    pub fn on_value(&mut self, t: BleuIOResponseType, v: &Value, now: Instant) -> Vec<Action> {
+        let mut actions = Vec::new();
+        match self.state.clone() {
+            LinkState::Resetting => {
+                // Everything now is left over from before, wait for silence.
+                if matches!(t, BleuIOResponseType::ScanDataResponse | BleuIOResponseType::ScanFindDataResponse | BleuIOResponseType::ScanTargetResponse) {
+                    self.saw_scan = true;
+                }
+                self.deadline = now + QUIET_TIME;
+            }
+            LinkState::Configuring(step) => match t {
+                BleuIOResponseType::CommandResponse
+                    if v["cmd"].as_str().is_some_and(|c| c.trim().eq_ignore_ascii_case(&step.command_text())) =>
+                {
+                    self.index = v["C"].as_i64();
+                }
+                BleuIOResponseType::AcknowledgementResponse if self.index.is_some() && v["A"].as_i64() == self.index => {
+                    self.error = parse_error_code(v);
+                }
+                BleuIOResponseType::ReplyResponse if step == Step::Info && v["R"].as_i64() == self.index => {
+                    self.info.add_replies(std::slice::from_ref(v));
+                }
+                BleuIOResponseType::EndResponse if self.index.is_some() && v["E"].as_i64() == self.index => {
+                    // AlreadyDone (2) is fine, and ATI is optional.
+                    if self.error == 0 || self.error == 2 || step == Step::Info {
+                        self.next(step, now, &mut actions);
+                    } else {
+                        let reason = tr!("error code {}", self.error);
+                        self.retry(step, &reason, now, &mut actions);
+                    }
+                }
+                _ => {}
+            },
+            LinkState::Scanning | LinkState::Ready => match t {
+                BleuIOResponseType::ScanEndedResponse => self.set_state(LinkState::Ready, &mut actions),
+                BleuIOResponseType::ReplyResponse if v["action"].as_str() == Some("scanning") => {
+                    self.set_state(LinkState::Scanning, &mut actions)
+                }
+                _ => {}
+            },
+            LinkState::Opening | LinkState::Error(_) => {}
+        }
+        actions
+    }
+
+    // Called regularly, handles timeouts.
+    pub fn on_tick(&mut self, now: Instant) -> Vec<Action> {
+        let mut actions = Vec::new();
+        match self.state.clone() {
+            LinkState::Resetting if now >= self.deadline || now >= self.flush_until => {
+                if self.saw_scan {
+                    info!(target: "protocol", "{}", tr!("The dongle was already scanning, scan stopped"));
+                }
+                self.begin(Step::EchoOff, now, &mut actions);
+            }
+            LinkState::Configuring(step) if now >= self.deadline => {
+                self.retry(step, tr!("no answer"), now, &mut actions);
+            }
+            _ => {}
+        }
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bleuio::{get_bleuio_result_type, FirmwareVersion};
+
+    fn feed(h: &mut Handshake, line: &str, now: Instant) -> Vec<Action> {
+        let v: Value = serde_json::from_str(line).unwrap();
+        h.on_value(get_bleuio_result_type(&v), &v, now)
+    }
+
+    // The C, A, R and E lines of a command as the dongle sends them.
+    fn answer(h: &mut Handshake, cmd: &str, index: i64, err: i64, replies: &[&str], now: Instant) -> Vec<Action> {
+        let mut actions = feed(h, &format!(r#"{{"C":{},"cmd":"{}"}}"#, index, cmd), now);
+        actions.extend(feed(h, &format!(r#"{{"A":{},"err":{},"errMsg":"ok"}}"#, index, err), now));
+        for reply in replies {
+            actions.extend(feed(h, &format!(r#"{{"R":{},{}}}"#, index, reply), now));
+        }
+        actions.extend(feed(h, &format!(r#"{{"E":{},"nol":{}}}"#, index, 3 + replies.len()), now));
+        actions
+    }
+
+    // A handshake past Resetting, configuring ATE0.
+    fn configuring(now: Instant) -> Handshake {
+        let mut h = Handshake::new(Some(DongleModel::BleuIO), now);
+        h.start(now);
+        h.on_tick(now + QUIET_TIME);
+        assert_eq!(h.state(), &LinkState::Configuring(Step::EchoOff));
+        h
+    }
+
+    fn configure(h: &mut Handshake, firmware: &str, now: Instant) -> Vec<Action> {
+        let mut actions = answer(h, "ATE0", 1, 0, &[], now);
+        actions.extend(answer(h, "ATV1", 2, 0, &[], now));
+        actions.extend(answer(h, "ATI", 3, 0, &[&format!(r#""fwVer":"{}""#, firmware)], now));
+        actions
+    }
+
+    #[test]
+    fn resetting_waits_for_silence() {
+        let now = Instant::now();
+        let mut h = Handshake::new(None, now);
+        assert_eq!(h.start(now), [Action::StateChanged(LinkState::Resetting), Action::Send(STOP_SCAN)]);
+        let later = now + Duration::from_millis(400);
+        feed(&mut h, r#"{"E":7,"nol":3}"#, later);
+        assert!(h.on_tick(now + QUIET_TIME).is_empty());
+        assert_eq!(h.state(), &LinkState::Resetting);
+        let actions = h.on_tick(later + QUIET_TIME);
+        assert_eq!(actions.last(), Some(&Action::Send(ATE0)));
+        assert_eq!(h.state(), &LinkState::Configuring(Step::EchoOff));
+        assert!(!h.saw_scan);
+    }
+
+    #[test]
+    fn flushing_is_capped() {
+        let now = Instant::now();
+        let mut h = Handshake::new(None, now);
+        h.start(now);
+        let mut t = now;
+        while t < now + FLUSH_TIME {
+            feed(&mut h, r#"{"SF":7,"addr":"F5:50:35:CF:B1:ED","type":0,"data":"0201061BFF5B07"}"#, t);
+            assert!(h.on_tick(t).is_empty());
+            t += Duration::from_millis(100);
+        }
+        assert!(h.saw_scan);
+        h.on_tick(now + FLUSH_TIME);
+        assert_eq!(h.state(), &LinkState::Configuring(Step::EchoOff));
+    }
+
+    #[test]
+    fn configures_and_becomes_ready() {
+        let now = Instant::now();
+        let mut h = configuring(now);
+        let actions = configure(&mut h, "2.7.9", now);
+        assert!(actions.contains(&Action::StepDone(Step::Info)));
+        assert!(actions.contains(&Action::Compatibility(Compatibility {
+            model: Some(DongleModel::BleuIO),
+            firmware: Some(FirmwareVersion::new(2, 7, 9)),
+        })));
+        assert_eq!(actions.last(), Some(&Action::Send(AT_SHOWRSSI)));
+        let actions = answer(&mut h, "AT+SHOWRSSI=1", 4, 0, &[], now);
+        assert_eq!(actions, [Action::StepDone(Step::ShowRssi), Action::StateChanged(LinkState::Ready)]);
+    }
+
+    #[test]
+    fn old_firmware_skips_show_rssi() {
+        let now = Instant::now();
+        let mut h = configuring(now);
+        let actions = configure(&mut h, "2.2.0", now);
+        assert!(!actions.contains(&Action::Send(AT_SHOWRSSI)));
+        assert_eq!(actions.last(), Some(&Action::StateChanged(LinkState::Ready)));
+    }
+
+    #[test]
+    fn already_done_is_accepted() {
+        let now = Instant::now();
+        let mut h = configuring(now);
+        let actions = answer(&mut h, "ATE0", 1, 2, &[], now);
+        assert_eq!(actions.first(), Some(&Action::StepDone(Step::EchoOff)));
+        assert_eq!(h.state(), &LinkState::Configuring(Step::Verbose));
+    }
+
+    #[test]
+    fn info_is_optional() {
+        let now = Instant::now();
+        let mut h = configuring(now);
+        answer(&mut h, "ATE0", 1, 0, &[], now);
+        answer(&mut h, "ATV1", 2, 0, &[], now);
+        // Unknown firmware is assumed to be recent.
+        let actions = answer(&mut h, "ATI", 3, 5, &[], now);
+        assert!(actions.contains(&Action::Compatibility(Compatibility { model: Some(DongleModel::BleuIO), firmware: None })));
+        assert_eq!(h.state(), &LinkState::Configuring(Step::ShowRssi));
+    }
+
+    #[test]
+    fn other_commands_are_ignored() {
+        let now = Instant::now();
+        let mut h = configuring(now);
+        // Lines of another command do not finish the step.
+        assert!(answer(&mut h, "AT+GAPSCAN", 9, 0, &[], now).is_empty());
+        assert_eq!(h.state(), &LinkState::Configuring(Step::EchoOff));
+    }
+
+    #[test]
+    fn retries_and_gives_up() {
+        let now = Instant::now();
+        let mut h = configuring(now);
+        // ATE0 was sent when Resetting ended.
+        let mut t = now + QUIET_TIME;
+        assert!(h.on_tick(t + STEP_TIMEOUT - Duration::from_millis(1)).is_empty());
+        for _ in 1..MAX_ATTEMPTS {
+            t += STEP_TIMEOUT;
+            assert_eq!(h.on_tick(t).last(), Some(&Action::Send(ATE0)));
+            assert_eq!(h.state(), &LinkState::Configuring(Step::EchoOff));
+        }
+        // An error code counts as a failed attempt like a timeout.
+        let actions = answer(&mut h, "ATE0", 1, 1, &[], t);
+        assert!(matches!(actions.last(), Some(Action::StateChanged(LinkState::Error(_)))));
+        assert!(matches!(h.state(), LinkState::Error(_)));
+        assert!(h.on_tick(t + STEP_TIMEOUT).is_empty());
+    }
+
+    #[test]
+    fn scanning_and_ready() {
+        let now = Instant::now();
+        let mut h = configuring(now);
+        configure(&mut h, "2.2.0", now);
+        let actions = feed(&mut h, r#"{"R":5,"action":"scanning"}"#, now);
+        assert_eq!(actions, [Action::StateChanged(LinkState::Scanning)]);
+        assert!(feed(&mut h, r#"{"SF":5,"addr":"F5:50:35:CF:B1:ED","type":0,"data":"0201061BFF5B07"}"#, now).is_empty());
+        let actions = feed(&mut h, r#"{"SE":5,"action":"scan completed"}"#, now);
+        assert_eq!(actions, [Action::StateChanged(LinkState::Ready)]);
+        assert_eq!(h.state(), &LinkState::Ready);
+    }
+}
@@ -0,0 +1,324 @@
+use std::fmt;
+
+use tracing::trace;
+
+use crate::advertising::to_hex;
+use crate::decoder::{Advertisement, AdvertisementDecoder, DecoderKey, Metric, Reading};
+use crate::i18n::tr;
+
+// Company id of Smart Sensor Devices AB, the "5B07" in the FF5B07 scan filter.
+pub const HIBOUAIR_COMPANY_ID: u16 = 0x075B;
+
+pub enum VocType {
+    Old = 0,
+    Resistance = 1,
+    Ppm = 2,
+    Iaq = 3,
+}
+
+#[derive(Debug, Clone, PartialEq, Copy)]
+pub struct HibouAir {
+    mfid: u16,          // the manufacturer id of the device
+    beacon_nr: u8,      // type of beacon
+    board_type: u8,     // type of device
+    board_id: [u8;3],   // unique board id
+    als: u16,           // ambient light sensor
+    bar: u16,           // pressure
+    temp: u16,          // temperature
+    hum: u16,           // humidity
+    voc: u16,           // volatile organic compounds
+    pm1_0: u16,         // particle matter PM1.0
+    pm2_5: u16,         // particle matter PM2.5
+    pm10: u16,          // particle matter PM10.0
+    co2: u16,           // carbon dioxide
+    voc_type: u8,       // 0 = old, 1 = resistance, 2 = ppm, 3 = IAQ
+    rssi: i16,          // signal strength in dBm as reported by the dongle (0 if unknown)
+}
+// 0201061BFF5B07050422005A0000BA27C60017013E0000000000000001C002
+
+impl HibouAir {
+    pub fn new(data: &str)  -> Self {
+        // Parse the scan data string and populate the struct fields.
+        // Return None if parsing fails.
+        trace!(target: "decode", len = data.len(), data, "HibouAir advertisement");
+        Self {
+            mfid: data.get(10..14).and_then(|s| u16::from_str_radix(s, 16).ok()).unwrap_or(0),
+            beacon_nr: data.get(14..16).and_then(|s| u8::from_str_radix(s, 16).ok()).unwrap_or(0),
+            board_type: data.get(16..18).and_then(|s| u8::from_str_radix(s, 16).ok()).unwrap_or(0),
+            board_id: data.get(18..24).and_then(|s| {
+                if s.len() == 6 {
+                    let b1 = u8::from_str_radix(&s[0..2], 16).ok()?;
+                    let b2 = u8::from_str_radix(&s[2..4], 16).ok()?;
+                    let b3 = u8::from_str_radix(&s[4..6], 16).ok()?;
+                    Some([b1, b2, b3])
+                } else {
+                    None
+                }
+            }).unwrap_or([0,0,0]),
+            als: data.get(24..28).and_then(|s| u16::from_str_radix(s, 16).ok()).unwrap_or(0),
+            bar: data.get(28..32).and_then(|s| u16::from_str_radix(s, 16).ok()).unwrap_or(0),
+            temp: data.get(32..36).and_then(|s| u16::from_str_radix(s, 16).ok()).unwrap_or(0),
+            hum: data.get(36..40).and_then(|s| u16::from_str_radix(s, 16).ok()).unwrap_or(0),
+            voc: data.get(40..44).and_then(|s| u16::from_str_radix(s, 16).ok()).unwrap_or(0),
+            pm1_0: data.get(44..48).and_then(|s| u16::from_str_radix(s, 16).ok()).unwrap_or(0),
+            pm2_5: data.get(48..52).and_then(|s| u16::from_str_radix(s, 16).ok()).unwrap_or(0),
+            pm10: data.get(52..56).and_then(|s| u16::from_str_radix(s, 16).ok()).unwrap_or(0),
+            co2: data.get(56..60).and_then(|s| u16::from_str_radix(s, 16).ok()).unwrap_or(0),
+            voc_type: data.get(60..62).and_then(|s| u8::from_str_radix(s, 16).ok()).unwrap_or(0),
+            rssi: 0,
+        }
+    }
+
+    // Attach the signal strength of the advertisement this reading came from.
+    pub fn with_rssi(mut self, rssi: i16) -> Self {
+        self.rssi = rssi;
+        self
+    }
+
+    pub fn get_id(&self) -> u32 {
+        ((self.board_id[0] as u32) << 16) | ((self.board_id[1] as u32) << 8) | (self.board_id[2] as u32)
+    }
+
+    pub fn get_board_id_string(&self) -> String {
+        format!("{:02X}", self.get_id())
+    }
+
+    pub fn get_board_type(&self) -> u8 {
+        self.board_type
+    }
+
+    pub fn get_board_type_string(&self) -> String {
+        match self.board_type {
+            0x03 => "PM".to_string(),
+            0x04 => "CO2".to_string(),
+            _ => tr!("Unknown").to_string(),
+        }
+    }
+
+    pub fn get_als(&self) -> u16 {
+        self.als.swap_bytes()
+    }
+
+    pub fn get_bar(&self) -> f64 {
+        self.bar.swap_bytes() as f64 / 10.0
+    }
+
+    pub fn get_temp(&self) -> f64 {
+        (self.temp.swap_bytes() as i16) as f64 / 10.0
+    }
+
+    pub fn get_hum(&self) -> f64 {
+        self.hum.swap_bytes() as f64 / 10.0
+    }
+
+    pub fn get_co2(&self) -> u16 {
+        self.co2
+    }
+
+    pub fn get_voc(&self) -> f64 {
+        let mut v: f64 = self.voc.swap_bytes() as f64 ;
+        if self.voc_type == 2 {
+            v /= 100.0;
+        }
+        v
+    }
+
+    pub fn get_voc_type(&self) -> u8 {
+        self.voc_type
+    }
+
+    pub fn get_voc_unit(&self) -> String {
+        match self.voc_type {
+            0 => "".to_string(),
+            1 => "".to_string(),
+            2 => "ppm".to_string(),
+            3 => "IAQ".to_string(),
+            _ => "".to_string(),
+        }
+    }
+
+    pub fn get_voc_view(&self) -> String {
+        match self.voc_type {
+            0 => "".to_string(),
+            1 => "".to_string(),
+            2 => format!("{:.1} {}",self.get_voc(), self.get_voc_unit()),
+            3 => format!("{:.1} {}",self.get_voc(), self.get_voc_unit()),
+            _ => "".to_string(),
+        }
+    }
+
+    pub fn get_pm1_0(&self) -> f64 {
+        self.pm1_0.swap_bytes() as f64 / 10.0
+    }
+
+    pub fn get_pm2_5(&self) -> f64 {
+        self.pm2_5.swap_bytes() as f64 / 10.0
+    }
+
+    pub fn get_pm10(&self) -> f64 {
+        self.pm10.swap_bytes() as f64 / 10.0
+    }
+
+    pub fn get_rssi(&self) -> i16 {
+        self.rssi
+    }
+}
+
+impl fmt::Display for HibouAir {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "HibouAir(mfid: {}, beacon_nr: {}, board_type: {}, board_id: {:02X?}, als: {}, bar: {}, temp: {}, hum: {}, voc: {}, pm1_0: {}, pm2_5: {}, pm10: {}, co2: {}, voc_type: {}, rssi: {})",
+            self.mfid,
+            self.beacon_nr,
+            self.board_type,
+            self.board_id,
+            self.als,
+            self.bar,
+            self.temp,
+            self.hum,
+            self.voc,
+            self.pm1_0,
+            self.pm2_5,
+            self.pm10,
+            self.co2,
+            self.voc_type,
+            self.rssi
+        )
+    }
+}
+
+// Decodes the advertisements of HibouAir sensors (manufacturer data 0x075B).
+pub struct HibouAirDecoder;
+
+impl AdvertisementDecoder for HibouAirDecoder {
+    fn name(&self) -> &'static str {
+        "HibouAir"
+    }
+
+    fn keys(&self) -> Vec<DecoderKey> {
+        vec![DecoderKey::Manufacturer(HIBOUAIR_COMPANY_ID)]
+    }
+
+    fn decode(&self, adv: &Advertisement) -> Option<Reading> {
+        // HibouAir::new expects the sensor data right after flags and the
+        // manufacturer data header, as in 0201061BFF5B07...
+        let data = to_hex(adv.data);
+        if data.len() <= 60 || data.get(8..14) != Some("FF5B07") {
+            return None;
+        }
+        let hibou = HibouAir::new(&data).with_rssi(adv.rssi.unwrap_or(0));
+        Some(hibou.to_reading(adv))
+    }
+}
+
+impl HibouAir {
+    pub fn to_reading(&self, adv: &Advertisement) -> Reading {
+        let model = match self.board_type {
+            0x03 => "HibouAir PM",
+            0x04 => "HibouAir CO2",
+            _ => "HibouAir",
+        };
+        let mut reading = Reading::new(self.get_board_id_string(), model, "HibouAir", adv);
+        match self.board_type {
+            0x04 => reading = reading.with(Metric::Co2, self.get_co2() as f64),
+            0x03 => {
+                reading = reading
+                    .with(Metric::Pm10, self.get_pm10())
+                    .with(Metric::Pm2_5, self.get_pm2_5())
+                    .with(Metric::Pm1_0, self.get_pm1_0());
+            }
+            _ => {}
+        }
+        match self.voc_type {
+            2 => reading = reading.with(Metric::Voc, self.get_voc()),
+            3 => reading = reading.with(Metric::Iaq, self.get_voc()),
+            _ => {}
+        }
+        reading
+            .with(Metric::Humidity, self.get_hum())
+            .with(Metric::Temperature, self.get_temp())
+            .with(Metric::Pressure, self.get_bar())
+            .with(Metric::Light, self.get_als() as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::advertising::{parse_ad_structures, parse_hex};
+
+    const ADDR: &str = "F5:50:35:CF:B1:ED";
+
+    fn decode(hex: &str, rssi: Option<i16>) -> Option<Reading> {
+        let data = parse_hex(hex).unwrap();
+        let ad = parse_ad_structures(&data);
+        HibouAirDecoder.decode(&Advertisement { addr: ADDR, rssi, data: &data, ad: &ad })
+    }
+
+    fn assert_values(reading: &Reading, expected: &[(Metric, f64)]) {
+        assert_eq!(reading.values.len(), expected.len(), "{:?}", reading.values);
+        for ((metric, value), (expected_metric, expected_value)) in reading.values.iter().zip(expected) {
+            assert_eq!(metric, expected_metric);
+            assert!((value - expected_value).abs() < 1e-6, "{:?} is {}, expected {}", metric, value, expected_value);
+        }
+    }
+
+    #[test]
+    fn co2_sensor() {
+        // Captured from a HibouAir CO2 sensor.
+        let r = decode("0201061BFF5B07050422013FBD007D27E000BB00F419000000000000020A02", Some(-61)).unwrap();
+        assert_eq!(r.id, "22013F");
+        assert_eq!(r.model, "HibouAir CO2");
+        assert_eq!(r.decoder, "HibouAir");
+        assert_eq!(r.address, ADDR);
+        assert_eq!(r.rssi, Some(-61));
+        assert_values(
+            &r,
+            &[
+                (Metric::Co2, 522.0),
+                (Metric::Voc, 66.44),
+                (Metric::Humidity, 18.7),
+                (Metric::Temperature, 22.4),
+                (Metric::Pressure, 1010.9),
+                (Metric::Light, 189.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn second_co2_sensor() {
+        let r = decode("0201061BFF5B07050422005A0000BA27C60017013E0000000000000001C002", None).unwrap();
+        assert_eq!(r.id, "22005A");
+        assert_eq!(r.rssi, None);
+        assert_values(
+            &r,
+            &[
+                (Metric::Co2, 448.0),
+                (Metric::Voc, 0.62),
+                (Metric::Humidity, 27.9),
+                (Metric::Temperature, 19.8),
+                (Metric::Pressure, 1017.0),
+                (Metric::Light, 0.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn fields() {
+        let hibou = HibouAir::new("0201061BFF5B07050422013FBD007D27E000BB00F419000000000000020A02").with_rssi(-61);
+        assert_eq!(hibou.get_board_type_string(), "CO2");
+        assert_eq!(hibou.get_voc_view(), "66.4 ppm");
+        assert_eq!(hibou.get_rssi(), -61);
+        assert!(hibou.to_string().starts_with("HibouAir(mfid: 23303, beacon_nr: 5, board_type: 4, board_id: [22, 01, 3F]"));
+    }
+
+    #[test]
+    fn other_and_truncated_data() {
+        // Another company id in the same layout.
+        assert!(decode("0201061BFF4C00050422013FBD007D27E000BB00F419000000000000020A02", None).is_none());
+        // Cut off before the CO2 value.
+        assert!(decode("02010617FF5B07050422013FBD007D27E000BB00F4190000000000", None).is_none());
+        assert!(decode("02010603FF5B07", None).is_none());
+    }
+}
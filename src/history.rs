@@ -0,0 +1,396 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::decoder::{Metric, Reading};
+use crate::i18n::{self, tr};
+use crate::paths;
+use crate::units;
+
+// Live advertisements arrive every few seconds, one sample per minute is kept.
+pub const LIVE_INTERVAL: i64 = 60;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sample {
+    pub t: i64,                     // unix time, seconds
+    pub values: Vec<(Metric, f64)>,
+}
+
+impl Sample {
+    pub fn from_reading(reading: &Reading) -> Self {
+        Self { t: reading.at.timestamp(), values: reading.values.clone() }
+    }
+
+    pub fn get(&self, metric: Metric) -> Option<f64> {
+        self.values.iter().find(|(m, _)| *m == metric).map(|(_, v)| *v)
+    }
+}
+
+// Where the history is kept unless told otherwise.
+pub fn default_dir() -> PathBuf {
+    paths::data_dir().join("history")
+}
+
+// The first line of a sensor file. File names can not hold every character of
+// an id, so the id itself is kept here, with the address to find the sensor in
+// the registry before it is seen again.
+#[derive(Serialize, Deserialize)]
+struct Header {
+    id: String,
+    #[serde(default)]
+    address: Option<String>,
+}
+
+// The header of a file, None for files written before there was one.
+fn read_header(path: &Path) -> io::Result<Option<Header>> {
+    let mut line = String::new();
+    BufReader::new(File::open(path)?).read_line(&mut line)?;
+    Ok(serde_json::from_str::<Header>(&line).ok())
+}
+
+// The file of one sensor in `dir`.
+pub fn sensor_file(dir: &Path, id: &str) -> PathBuf {
+    let name: String = id.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    dir.join(format!("{}.jsonl", name))
+}
+
+// Read the samples of one file. Lines that can not be parsed, like the header,
+// are skipped.
+pub fn load_file(path: &Path) -> io::Result<BTreeMap<i64, Sample>> {
+    let mut series = BTreeMap::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        if let Ok(sample) = serde_json::from_str::<Sample>(&line?) {
+            series.insert(sample.t, sample);
+        }
+    }
+    Ok(series)
+}
+
+// Measurements of all sensors, kept in memory and appended to one JSON lines
+// file per sensor in `dir`.
+#[derive(Debug, Default)]
+pub struct HistoryStore {
+    dir: PathBuf,
+    series: HashMap<String, BTreeMap<i64, Sample>>,
+    addresses: HashMap<String, String>,
+}
+
+impl HistoryStore {
+    // Load everything stored in `dir`. Lines that can not be parsed are skipped.
+    pub fn open(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let mut store = Self { dir: dir.to_path_buf(), ..Self::default() };
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "jsonl") {
+                // Without a header only the file name is known, the series is
+                // moved to the id when the sensor is next recorded.
+                let (id, address) = match read_header(&path)? {
+                    Some(header) => (header.id, header.address),
+                    None => match path.file_stem() {
+                        Some(stem) => (stem.to_string_lossy().to_string(), None),
+                        None => continue,
+                    },
+                };
+                if let Some(address) = address {
+                    store.addresses.insert(id.clone(), address);
+                }
+                store.series.insert(id, load_file(&path)?);
+            }
+        }
+        Ok(store)
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        sensor_file(&self.dir, id)
+    }
+
+    fn append(&self, id: &str, samples: &[Sample]) -> io::Result<()> {
+        let path = self.path(id);
+        let new = !path.exists();
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if new {
+            let header = Header { id: id.to_string(), address: self.addresses.get(id).cloned() };
+            writeln!(file, "{}", serde_json::to_string(&header)?)?;
+        }
+        for sample in samples {
+            writeln!(file, "{}", serde_json::to_string(sample)?)?;
+        }
+        Ok(())
+    }
+
+    // Move a series loaded from a file without a header to the sensor's id and
+    // write the file again with one.
+    fn adopt(&mut self, id: &str) -> io::Result<()> {
+        let path = self.path(id);
+        let Some(stem) = path.file_stem().map(|s| s.to_string_lossy().to_string()) else {
+            return Ok(());
+        };
+        if stem == id || self.series.contains_key(id) {
+            return Ok(());
+        }
+        let Some(series) = self.series.remove(&stem) else {
+            return Ok(());
+        };
+        let samples: Vec<Sample> = series.values().cloned().collect();
+        self.series.insert(id.to_string(), series);
+        fs::remove_file(&path)?;
+        self.append(id, &samples)
+    }
+
+    // Store a live reading unless one was stored for the sensor within LIVE_INTERVAL.
+    pub fn record(&mut self, reading: &Reading) -> io::Result<bool> {
+        self.addresses.insert(reading.id.clone(), reading.address.clone());
+        self.adopt(&reading.id)?;
+        let sample = Sample::from_reading(reading);
+        let series = self.series.entry(reading.id.clone()).or_default();
+        if series.range(sample.t - LIVE_INTERVAL + 1..).next().is_some() {
+            return Ok(false);
+        }
+        series.insert(sample.t, sample.clone());
+        self.append(&reading.id, &[sample])?;
+        Ok(true)
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = &String> {
+        self.series.keys()
+    }
+
+    // The address of the sensor, for finding it in the registry.
+    pub fn address(&self, id: &str) -> Option<&str> {
+        self.addresses.get(id).map(String::as_str)
+    }
+
+    pub fn samples(&self, id: &str, since: i64) -> impl Iterator<Item = &Sample> {
+        self.series.get(id).into_iter().flat_map(move |s| s.range(since..).map(|(_, v)| v))
+    }
+
+    // Values of one metric since `since`, see `segments`.
+    pub fn segments(&self, id: &str, metric: Metric, since: i64, max_gap: i64) -> Vec<Vec<(i64, f64)>> {
+        segments(self.samples(id, since), metric, max_gap)
+    }
+}
+
+// Values of one metric, split where samples are further apart than `max_gap`
+// so charts can show the gaps.
+pub fn segments<'a>(samples: impl Iterator<Item = &'a Sample>, metric: Metric, max_gap: i64) -> Vec<Vec<(i64, f64)>> {
+    let mut segments: Vec<Vec<(i64, f64)>> = Vec::new();
+    let mut last = None;
+    for sample in samples {
+        let Some(value) = sample.get(metric) else {
+            continue;
+        };
+        if last.is_none_or(|t| sample.t - t > max_gap) {
+            segments.push(Vec::new());
+        }
+        if let Some(segment) = segments.last_mut() {
+            segment.push((sample.t, value));
+        }
+        last = Some(sample.t);
+    }
+    segments
+}
+
+// Statistics of one metric over a range of samples.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub p90: f64,
+}
+
+impl Stats {
+    // None if no sample has a value for the metric.
+    pub fn of<'a>(samples: impl Iterator<Item = &'a Sample>, metric: Metric) -> Option<Self> {
+        let mut values: Vec<f64> = samples.filter_map(|s| s.get(metric)).collect();
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(f64::total_cmp);
+        Some(Self {
+            count: values.len(),
+            min: values[0],
+            max: values[values.len() - 1],
+            mean: values.iter().sum::<f64>() / values.len() as f64,
+            median: percentile(&values, 50.0),
+            p90: percentile(&values, 90.0),
+        })
+    }
+}
+
+// Percentile `p` of sorted values, interpolated between the nearest ones.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p / 100.0 * (sorted.len() - 1) as f64;
+    let (below, above) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[below] + (sorted[above] - sorted[below]) * (rank - below as f64)
+}
+
+// Samples as CSV for a spreadsheet, with the dates, decimals and separator of
+// the current language and in the units picked by the user. Metrics a sample
+// has no value for are left empty.
+pub fn to_csv<'a>(samples: impl Iterator<Item = &'a Sample>, metrics: &[Metric]) -> String {
+    let separator = i18n::csv_separator();
+    let units = units::units();
+    let row = |fields: Vec<String>| {
+        let fields: Vec<String> = fields
+            .into_iter()
+            .map(|f| if f.contains(separator) || f.contains('"') { format!("\"{}\"", f.replace('"', "\"\"")) } else { f })
+            .collect();
+        fields.join(&separator.to_string()) + "\n"
+    };
+    let mut header = vec![tr!("Unix time").to_string(), tr!("Time").to_string()];
+    header.extend(metrics.iter().map(|m| format!("{} ({})", m.label(), units.unit(*m))));
+    let mut out = row(header);
+    for sample in samples {
+        let at = DateTime::from_timestamp(sample.t, 0).map(|t| i18n::datetime(&t.with_timezone(&Local))).unwrap_or_default();
+        let mut fields = vec![sample.t.to_string(), at];
+        fields.extend(metrics.iter().map(|m| sample.get(*m).map(|v| i18n::decimal(units.convert(*m, v), units.decimals(*m))).unwrap_or_default()));
+        out.push_str(&row(fields));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    // An empty directory of its own for each test.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("dxbleuio-history-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        dir
+    }
+
+    fn reading(id: &str, t: i64, co2: f64) -> Reading {
+        Reading {
+            id: id.to_string(),
+            address: id.to_string(),
+            model: "HibouAir CO2".to_string(),
+            decoder: "HibouAir".to_string(),
+            at: Local.timestamp_opt(t, 0).unwrap(),
+            rssi: None,
+            values: vec![(Metric::Co2, co2)],
+            info: Vec::new(),
+        }
+    }
+
+    fn sample(t: i64, co2: f64) -> Sample {
+        Sample { t, values: vec![(Metric::Co2, co2)] }
+    }
+
+    fn co2(store: &HistoryStore, id: &str) -> Vec<(i64, f64)> {
+        store.samples(id, 0).map(|s| (s.t, s.get(Metric::Co2).unwrap())).collect()
+    }
+
+    #[test]
+    fn reopen_keeps_the_id() {
+        let dir = temp_dir("reopen");
+        let id = "C8:47:8C:00:00:01";
+        let mut store = HistoryStore::open(&dir).unwrap();
+        assert!(store.record(&reading(id, 1_000, 600.0)).unwrap());
+        assert!(store.record(&reading(id, 1_100, 650.0)).unwrap());
+
+        let store = HistoryStore::open(&dir).unwrap();
+        assert_eq!(store.ids().collect::<Vec<_>>(), [id]);
+        assert_eq!(store.address(id), Some(id));
+        assert_eq!(co2(&store, id), [(1_000, 600.0), (1_100, 650.0)]);
+        assert_eq!(store.segments(id, Metric::Co2, 1_050, 180), [vec![(1_100, 650.0)]]);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn file_without_header_is_adopted() {
+        let dir = temp_dir("adopt");
+        let id = "C8:47:8C:00:00:02";
+        fs::create_dir_all(&dir).unwrap();
+        let old = [sample(1_000, 600.0), sample(1_100, 650.0)].map(|s| serde_json::to_string(&s).unwrap()).join("\n");
+        fs::write(sensor_file(&dir, id), old + "\n").unwrap();
+
+        let mut store = HistoryStore::open(&dir).unwrap();
+        assert_eq!(store.ids().collect::<Vec<_>>(), ["C8_47_8C_00_00_02"]);
+        assert!(store.record(&reading(id, 1_200, 700.0)).unwrap());
+        assert_eq!(store.ids().collect::<Vec<_>>(), [id]);
+
+        let store = HistoryStore::open(&dir).unwrap();
+        assert_eq!(store.ids().collect::<Vec<_>>(), [id]);
+        assert_eq!(co2(&store, id), [(1_000, 600.0), (1_100, 650.0), (1_200, 700.0)]);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn old_samples_with_a_source_still_load() {
+        let dir = temp_dir("source");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("old.jsonl");
+        fs::write(&path, "{\"t\":1000,\"source\":\"Live\",\"values\":[[\"Co2\",600.0]]}\n").unwrap();
+        assert_eq!(load_file(&path).unwrap().into_values().collect::<Vec<_>>(), [sample(1_000, 600.0)]);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn record_keeps_one_sample_per_interval() {
+        let dir = temp_dir("interval");
+        let id = "sensor";
+        let mut store = HistoryStore::open(&dir).unwrap();
+        assert!(store.record(&reading(id, 1_000, 600.0)).unwrap());
+        assert!(!store.record(&reading(id, 1_000 + LIVE_INTERVAL - 1, 610.0)).unwrap());
+        assert!(store.record(&reading(id, 1_000 + LIVE_INTERVAL, 620.0)).unwrap());
+        assert!(!store.record(&reading(id, 1_000 + LIVE_INTERVAL + 30, 630.0)).unwrap());
+        assert_eq!(co2(&store, id), [(1_000, 600.0), (1_000 + LIVE_INTERVAL, 620.0)]);
+        assert_eq!(co2(&HistoryStore::open(&dir).unwrap(), id), co2(&store, id));
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    fn stats(values: &[f64]) -> Option<Stats> {
+        let samples: Vec<Sample> = values.iter().enumerate().map(|(i, v)| sample(i as i64, *v)).collect();
+        Stats::of(samples.iter(), Metric::Co2)
+    }
+
+    #[test]
+    fn stats_of_one_value() {
+        assert_eq!(stats(&[600.0]), Some(Stats { count: 1, min: 600.0, max: 600.0, mean: 600.0, median: 600.0, p90: 600.0 }));
+        assert_eq!(stats(&[]), None);
+        let other = [Sample { t: 0, values: vec![(Metric::Humidity, 40.0)] }];
+        assert_eq!(Stats::of(other.iter(), Metric::Co2), None);
+    }
+
+    #[test]
+    fn stats_of_odd_count() {
+        let stats = stats(&[500.0, 900.0, 700.0]).unwrap();
+        assert_eq!((stats.count, stats.min, stats.max), (3, 500.0, 900.0));
+        assert_eq!(stats.mean, 700.0);
+        assert_eq!(stats.median, 700.0);
+        // Rank 1.8 of 500, 700, 900.
+        assert!((stats.p90 - 860.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn stats_of_even_count() {
+        let stats = stats(&[800.0, 400.0, 600.0, 500.0]).unwrap();
+        assert_eq!((stats.count, stats.min, stats.max), (4, 400.0, 800.0));
+        assert_eq!(stats.mean, 575.0);
+        assert_eq!(stats.median, 550.0);
+        // Rank 2.7 of 400, 500, 600, 800.
+        assert!((stats.p90 - 740.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn percentile_interpolates() {
+        let sorted: Vec<f64> = (1..=11).map(|v| v as f64 * 10.0).collect();
+        assert_eq!(percentile(&sorted, 0.0), 10.0);
+        assert_eq!(percentile(&sorted, 50.0), 60.0);
+        assert_eq!(percentile(&sorted, 90.0), 100.0);
+        assert_eq!(percentile(&sorted, 100.0), 110.0);
+        assert!((percentile(&[1.0, 2.0], 90.0) - 1.9).abs() < 1e-9);
+        assert!((percentile(&[10.0, 20.0, 30.0, 40.0, 50.0], 90.0) - 46.0).abs() < 1e-9);
+    }
+}
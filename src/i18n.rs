@@ -0,0 +1,602 @@
+// Translations and locale aware formatting. Texts are looked up by their
+// English wording, gettext style, so the code reads the same as before:
+//
+//     tr!("Clear log")                            -> "Rensa loggen" in Swedish
+//     tr!("Saved {}", path.display())             -> "Sparade /tmp/x"
+//     tr!("{1} of {0} devices", total, shown)     -> arguments by position
+//
+// A text without a translation is shown in English. The language can be
+// switched at runtime: components read it through a global signal, so they
+// are rendered again when it changes, code outside the UI (the collector,
+// sink threads) gets it from an atomic.
+
+use std::collections::HashMap;
+use std::fmt::{self, Display, Write};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Datelike, Local};
+use dioxus::core::Runtime;
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    En,
+    Sv,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::En, Language::Sv];
+
+    // The name of the language in the language itself.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::En => "English",
+            Language::Sv => "Svenska",
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::En => "en",
+            Language::Sv => "sv",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|l| code.to_lowercase().starts_with(l.code()))
+    }
+
+    // From a locale like "sv_SE.UTF-8" in LC_ALL, LC_MESSAGES or LANG.
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|v| std::env::var(v).ok())
+            .find(|v| !v.is_empty())
+            .and_then(|v| Self::from_code(&v))
+            .unwrap_or_default()
+    }
+}
+
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+static LANGUAGE: GlobalSignal<Language> = Signal::global(stored_language);
+
+fn stored_language() -> Language {
+    match CURRENT.load(Ordering::Relaxed) {
+        1 => Language::Sv,
+        _ => Language::En,
+    }
+}
+
+// The language in use. Read in a component, the component is rendered again
+// when the language changes.
+pub fn language() -> Language {
+    if Runtime::try_current().is_some() {
+        LANGUAGE()
+    } else {
+        stored_language()
+    }
+}
+
+pub fn set_language(language: Language) {
+    CURRENT.store(language as u8, Ordering::Relaxed);
+    if Runtime::try_current().is_some() && *LANGUAGE.peek() != language {
+        *LANGUAGE.write() = language;
+    }
+}
+
+// English text and its Swedish translation.
+const CATALOGUE: &[(&str, &str)] = &[
+    // Metrics and charts
+    ("Humidity", "Fuktighet"),
+    ("Pressure", "Lufttryck"),
+    ("Light", "Ljus"),
+    ("Temperature", "Temperatur"),
+    ("Battery", "Batteri"),
+    ("Voltage", "Spänning"),
+    ("Distance", "Avstånd"),
+    ("7 days", "7 dagar"),
+    ("30 days", "30 dagar"),
+    ("No stored {} values in this range.", "Inga sparade värden för {} i det här intervallet."),
+    ("Unix time", "Unixtid"),
+    ("Time", "Tid"),
+    // Decoded details
+    ("TX power", "Sändeffekt"),
+    ("Movements", "Rörelser"),
+    ("Sequence", "Sekvens"),
+    ("Product id", "Produkt-id"),
+    ("Measured power", "Uppmätt effekt"),
+    ("Instance", "Instans"),
+    ("Advertisements", "Annonseringar"),
+    ("Uptime", "Drifttid"),
+    // Tabs and header
+    ("Sensor Dashboard", "Sensoröversikt"),
+    ("Dashboard", "Översikt"),
+    ("Scanner", "Skanner"),
+    ("Console", "Konsol"),
+    ("Dongle", "Dongel"),
+    ("Resume", "Återuppta"),
+    ("Retry", "Försök igen"),
+    ("Opening port", "Öppnar porten"),
+    ("Resetting dongle", "Startar om dongeln"),
+    ("Configuring ({})", "Konfigurerar ({})"),
+    ("Scanning", "Skannar"),
+    ("Ready", "Klar"),
+    ("Error: {}", "Fel: {}"),
+    ("starting scan", "startar skanning"),
+    ("next scan in {} s", "nästa skanning om {} s"),
+    ("sensor scan paused", "sensorskanningen pausad"),
+    ("sensor scan paused during a connection", "sensorskanningen pausad under en anslutning"),
+    // Dashboard layout
+    ("Sort by", "Sortera efter"),
+    ("Group by", "Gruppera efter"),
+    ("Room", "Rum"),
+    ("Floor", "Våning"),
+    ("No grouping", "Ingen gruppering"),
+    ("No room", "Inget rum"),
+    ("No floor", "Ingen våning"),
+    ("Panels", "Paneler"),
+    ("Tiles", "Rutor"),
+    ("Back", "Tillbaka"),
+    ("Decoder", "Avkodare"),
+    // Comparison
+    ("Compare", "Jämför"),
+    ("Select a room:", "Välj ett rum:"),
+    ("None", "Inga"),
+    ("Select the sensors to compare.", "Välj sensorerna att jämföra."),
+    ("Samples", "Mätningar"),
+    ("Mean", "Medel"),
+    // Floor plans
+    ("Floor plan", "Planritning"),
+    ("Heatmap", "Värmekarta"),
+    ("Import plan...", "Importera ritning..."),
+    ("Images", "Bilder"),
+    ("Place", "Placera"),
+    ("Remove from plan", "Ta bort från ritningen"),
+    ("No plan for this floor yet.", "Ingen ritning för våningen ännu."),
+    ("Give the known sensors a floor in the Dongle tab to make a plan of it.", "Ge de kända sensorerna en våning under fliken Dongel för att göra en ritning av den."),
+    ("Imported the floor plan of {}", "Importerade ritningen för {}"),
+    ("Can not import {}: {}", "Kan inte importera {}: {}"),
+    ("Can not read the floor plan: {}", "Kan inte läsa ritningen: {}"),
+    ("Not a PNG, SVG or JPEG image", "Inte en PNG-, SVG- eller JPEG-bild"),
+    // History
+    ("Exported the history to {}", "Exporterade historiken till {}"),
+    ("Can not export the history to {}: {}", "Kan inte exportera historiken till {}: {}"),
+    ("Hide history", "Dölj historik"),
+    ("History", "Historik"),
+    ("Export CSV", "Exportera CSV"),
+    ("Can not read history from {}: {}", "Kan inte läsa historik från {}: {}"),
+    ("Can not store history: {}", "Kan inte spara historik: {}"),
+    ("Can not load the history: {}", "Kan inte hämta historiken: {}"),
+    // Diagnostics
+    ("Hide diagnostics", "Dölj diagnostik"),
+    ("Diagnostics", "Diagnostik"),
+    ("Average", "Medel"),
+    ("Min / Max", "Min / max"),
+    ("Packets", "Paket"),
+    ("Rate", "Takt"),
+    ("Interval", "Intervall"),
+    ("Est. loss", "Uppsk. förlust"),
+    ("Seen for", "Sedd i"),
+    // Console and log
+    ("Saved {}", "Sparade {}"),
+    ("AT command", "AT-kommando"),
+    ("Send", "Skicka"),
+    ("Clear console", "Rensa konsolen"),
+    ("Save transcript", "Spara utskrift"),
+    ("Stop recording", "Stoppa inspelning"),
+    ("Record capture", "Spela in trafik"),
+    ("Hide log", "Dölj logg"),
+    ("Show log", "Visa logg"),
+    ("● Recording to {}", "● Spelar in till {}"),
+    ("Search", "Sök"),
+    ("Clear log", "Rensa loggen"),
+    // Scanner
+    ("Scan all devices", "Skanna alla enheter"),
+    ("hex, e.g. FF4C00", "hex, t.ex. FF4C00"),
+    ("Scan advertising data", "Skanna annonseringsdata"),
+    ("Back to HibouAir scan", "Tillbaka till HibouAir-skanning"),
+    ("Clear", "Rensa"),
+    ("Filter on address, name or manufacturer", "Filtrera på adress, namn eller tillverkare"),
+    ("{} of {} devices", "{} av {} enheter"),
+    ("Address", "Adress"),
+    ("Name", "Namn"),
+    ("Type", "Typ"),
+    ("Last seen", "Senast sedd"),
+    (" (public)", " (publik)"),
+    (" (random)", " (slumpad)"),
+    ("{} s ago", "{} s sedan"),
+    ("No advertising data received, try scanning for advertising data.", "Ingen annonseringsdata mottagen, prova att skanna efter annonseringsdata."),
+    ("Raw: {}", "Rådata: {}"),
+    ("Unknown", "Okänd"),
+    ("unknown", "okänd"),
+    ("Flags 0x{} ({})", "Flaggor 0x{} ({})"),
+    ("16-bit services: {}", "16-bitarstjänster: {}"),
+    ("32-bit services: {}", "32-bitarstjänster: {}"),
+    ("128-bit services: {}", "128-bitarstjänster: {}"),
+    ("Complete name: {}", "Fullständigt namn: {}"),
+    ("Short name: {}", "Kort namn: {}"),
+    ("TX power: {} dBm", "Sändeffekt: {} dBm"),
+    ("Service data 0x{}: {}", "Tjänstedata 0x{}: {}"),
+    ("Manufacturer data 0x{} ({}): {}", "Tillverkardata 0x{} ({}): {}"),
+    ("AD type 0x{}: {}", "AD-typ 0x{}: {}"),
+    // GATT
+    ("Connecting to {}...", "Ansluter till {}..."),
+    ("not available", "inte tillgängligt"),
+    ("Manufacturer", "Tillverkare"),
+    ("Model", "Modell"),
+    ("Cancel", "Avbryt"),
+    ("Connected {}", "Ansluten {}"),
+    ("Disconnected", "Frånkopplad"),
+    ("Connect failed: {}", "Anslutningen misslyckades: {}"),
+    ("Connect", "Anslut"),
+    ("Disconnect failed: {}", "Frånkopplingen misslyckades: {}"),
+    ("Disconnect", "Koppla från"),
+    ("{} services", "{} tjänster"),
+    ("Discovery failed: {}", "Sökningen misslyckades: {}"),
+    ("Discover services", "Sök tjänster"),
+    ("Service {}", "Tjänst {}"),
+    ("Read 0x{} failed: {}", "Läsning av 0x{} misslyckades: {}"),
+    ("Read", "Läs"),
+    ("Subscribed to 0x{}", "Prenumererar på 0x{}"),
+    ("Subscribe 0x{} failed: {}", "Prenumeration på 0x{} misslyckades: {}"),
+    ("Subscribe", "Prenumerera"),
+    ("Not a hex value: {}", "Inte ett hexvärde: {}"),
+    ("Wrote {} bytes to 0x{}", "Skrev {} byte till 0x{}"),
+    ("Write 0x{} failed: {}", "Skrivning till 0x{} misslyckades: {}"),
+    ("Write", "Skriv"),
+    ("Notifications", "Notifieringar"),
+    ("timeout waiting for the dongle", "tidsgränsen nåddes i väntan på dongeln"),
+    ("dongle error: {}", "dongelfel: {}"),
+    ("connection to the dongle closed", "anslutningen till dongeln stängdes"),
+    ("not connected", "inte ansluten"),
+    ("unexpected response: {}", "oväntat svar: {}"),
+    // Dongle
+    ("on", "på"),
+    ("off", "av"),
+    ("Could not read dongle information: {}", "Kunde inte läsa information från dongeln: {}"),
+    ("Scan interval and window must be between {} and {} ms", "Skanningsintervall och -fönster måste vara mellan {} och {} ms"),
+    ("The scan window can not be longer than the interval", "Skanningsfönstret kan inte vara längre än intervallet"),
+    ("Scan parameters set", "Skanningsparametrarna är satta"),
+    ("Could not set scan parameters: {}", "Kunde inte sätta skanningsparametrarna: {}"),
+    ("TX power must be a whole number of dBm", "Sändeffekten måste vara ett heltal dBm"),
+    ("TX power set", "Sändeffekten är satt"),
+    ("Could not set TX power: {}", "Kunde inte sätta sändeffekten: {}"),
+    ("Scan duration and pause must be whole seconds", "Skanningstid och paus måste vara hela sekunder"),
+    ("Could not save settings to {}: {}", "Kunde inte spara inställningarna till {}: {}"),
+    ("Scan schedule set", "Skanningsschemat är satt"),
+    ("Could not set scan schedule: {}", "Kunde inte sätta skanningsschemat: {}"),
+    ("Dongle is restarting, the connection is lost until the app is restarted", "Dongeln startar om, anslutningen är bruten tills appen startas om"),
+    ("Could not reset the dongle: {}", "Kunde inte starta om dongeln: {}"),
+    ("Role", "Roll"),
+    ("MAC address", "MAC-adress"),
+    ("Connection", "Anslutning"),
+    ("Echo", "Eko"),
+    ("Verbose", "Utförlig"),
+    ("RSSI display", "Visa RSSI"),
+    ("Scan interval", "Skanningsintervall"),
+    ("Scan window", "Skanningsfönster"),
+    ("default", "standard"),
+    ("Refresh", "Uppdatera"),
+    ("{}, firmware {} (minimum {})", "{}, firmware {} (minst {})"),
+    ("Hide details", "Dölj detaljer"),
+    ("All reported values", "Alla rapporterade värden"),
+    ("Scan interval / window (ms)", "Skanningsintervall / -fönster (ms)"),
+    ("Set", "Sätt"),
+    ("Not supported by this firmware", "Stöds inte av den här firmwaren"),
+    ("Scan duration / pause (s)", "Skanningstid / paus (s)"),
+    ("no limit", "ingen gräns"),
+    ("Scans for the given time, then waits before scanning again. Leave the duration empty to scan until the dongle stops.", "Skannar under den angivna tiden och väntar sedan innan nästa skanning. Lämna tiden tom för att skanna tills dongeln slutar."),
+    ("TX power (dBm)", "Sändeffekt (dBm)"),
+    ("Restart the dongle?", "Starta om dongeln?"),
+    ("Yes, reset", "Ja, starta om"),
+    ("Reset dongle", "Starta om dongeln"),
+    ("{} firmware {} is too old, version {} or later is needed. Please update the dongle firmware.", "{} firmware {} är för gammal, version {} eller senare behövs. Uppdatera dongelns firmware."),
+    ("Could not read the firmware version of the dongle, some functions may not work.", "Kunde inte läsa dongelns firmwareversion, vissa funktioner kanske inte fungerar."),
+    ("Success", "Lyckades"),
+    ("Generic failure", "Allmänt fel"),
+    ("Already done", "Redan gjort"),
+    ("Operation already in progress", "Åtgärden pågår redan"),
+    ("Invalid parameter", "Ogiltig parameter"),
+    ("Not allowed", "Inte tillåtet"),
+    ("Not connected", "Inte ansluten"),
+    ("Not supported", "Stöds inte"),
+    ("Not accepted", "Inte accepterat"),
+    ("Busy", "Upptagen"),
+    ("Request timed out", "Tidsgränsen för begäran nåddes"),
+    ("Not supported by the sensor", "Stöds inte av sensorn"),
+    ("Canceled", "Avbruten"),
+    ("Encryption key missing, pair with the sensor first", "Krypteringsnyckel saknas, para ihop med sensorn först"),
+    ("Insufficient resources", "Otillräckliga resurser"),
+    ("Not found", "Hittades inte"),
+    ("No credits available on L2CAP CoC", "Inga krediter tillgängliga på L2CAP CoC"),
+    ("MTU exceeded on L2CAP CoC", "MTU överskriden på L2CAP CoC"),
+    ("Insufficient bandwidth", "Otillräcklig bandbredd"),
+    ("Unknown error", "Okänt fel"),
+    // Scan filters and known sensors
+    ("Filter {} saved", "Filtret {} sparat"),
+    ("Scan filters", "Skanningsfilter"),
+    ("Edit", "Redigera"),
+    ("Remove", "Ta bort"),
+    ("Manufacturer id", "Tillverkar-id"),
+    ("Service UUID", "Tjänste-UUID"),
+    ("Name prefix", "Namnprefix"),
+    ("Addresses", "Adresser"),
+    ("Min RSSI", "Minsta RSSI"),
+    ("Save filter", "Spara filter"),
+    ("{} is already in the list", "{} finns redan i listan"),
+    ("{} sensors added", "{} sensorer tillagda"),
+    ("Known sensors", "Kända sensorer"),
+    ("Follow only these sensors (AT+SCANTARGET)", "Följ bara dessa sensorer (AT+SCANTARGET)"),
+    ("Add", "Lägg till"),
+    ("Add all found sensors", "Lägg till alla funna sensorer"),
+    ("The filter needs a name", "Filtret behöver ett namn"),
+    ("The name prefix must be plain ASCII text", "Namnprefixet måste vara ren ASCII-text"),
+    ("The filter needs a manufacturer id, service data UUID, name prefix or addresses", "Filtret behöver ett tillverkar-id, ett tjänstedata-UUID, ett namnprefix eller adresser"),
+    ("Minimum RSSI must be a whole number of dBm", "Minsta RSSI måste vara ett heltal dBm"),
+    ("manufacturer 0x{}", "tillverkare 0x{}"),
+    ("service data 0x{}", "tjänstedata 0x{}"),
+    ("name \"{}*\"", "namn \"{}*\""),
+    ("{} addresses", "{} adresser"),
+    ("{} is not a 16-bit hex number", "{} är inte ett 16-bitars hextal"),
+    ("{} is not a Bluetooth address (XX:XX:XX:XX:XX:XX)", "{} är inte en Bluetooth-adress (XX:XX:XX:XX:XX:XX)"),
+    ("Scan duration must be between {} and {} s", "Skanningstiden måste vara mellan {} och {} s"),
+    ("Pause must be between {} and {} s", "Pausen måste vara mellan {} och {} s"),
+    // Serial task and protocol log
+    ("Opening {}", "Öppnar {}"),
+    ("Can not open {}: {}", "Kan inte öppna {}: {}"),
+    ("Can not record to {}: {}", "Kan inte spela in till {}: {}"),
+    ("Replaying {}", "Spelar upp {}"),
+    ("Port open", "Porten öppen"),
+    ("Read error: {}", "Läsfel: {}"),
+    ("Sensor data", "Sensordata"),
+    ("Decoded", "Avkodad"),
+    ("New sensor, {} found", "Ny sensor, {} funna"),
+    ("Command {}", "Kommando {}"),
+    ("Recording to {}", "Spelar in till {}"),
+    ("Giving up", "Ger upp"),
+    ("{} failed after {} attempts", "{} misslyckades efter {} försök"),
+    ("Trying again", "Försöker igen"),
+    ("error code {}", "felkod {}"),
+    ("The dongle was already scanning, scan stopped", "Dongeln skannade redan, skanningen stoppad"),
+    ("no answer", "inget svar"),
+    // Settings, options and the collector
+    ("Ignoring invalid settings in {}: {}", "Ignorerar ogiltiga inställningar i {}: {}"),
+    ("Ignoring the log filter \"{}\": {}", "Ignorerar loggfiltret \"{}\": {}"),
+    ("Can not write log files to {}: {}", "Kan inte skriva loggfiler till {}: {}"),
+    ("Can not set up logging: {}", "Kan inte sätta upp loggningen: {}"),
+    ("Ignoring --http, built without the http-api feature", "Ignorerar --http, byggd utan funktionen http-api"),
+    ("Ignoring --headless, built without the server feature", "Ignorerar --headless, byggd utan funktionen server"),
+    ("--http needs an address like 127.0.0.1:8080", "--http behöver en adress som 127.0.0.1:8080"),
+    ("Ignoring unknown argument {}", "Ignorerar okänt argument {}"),
+    ("--headless needs --http <addr:port> to serve the dashboard on", "--headless behöver --http <adress:port> att visa översikten på"),
+    ("Can not start the collector: {}", "Kan inte starta insamlaren: {}"),
+    ("Serving the dashboard on http://{}", "Visar översikten på http://{}"),
+    ("Can not start the HTTP API: {}", "Kan inte starta HTTP-API:t: {}"),
+    ("HTTP API on {} stopped: {}", "HTTP-API:t på {} stoppades: {}"),
+    // Sinks
+    ("{} is not an http or https URL", "{} är inte en http- eller https-URL"),
+    ("Bucket, measurement and sensor tag can not be empty", "Bucket, measurement och sensortagg kan inte vara tomma"),
+    ("Batch size and flush interval must be at least 1", "Batchstorlek och tömningsintervall måste vara minst 1"),
+    ("Can not keep up, readings lost", "Hinner inte med, avläsningar förlorade"),
+    ("Working again", "Fungerar igen"),
+    ("Not using the sink {}: {}", "Använder inte utgången {}: {}"),
+    ("built without the influxdb feature", "byggd utan funktionen influxdb"),
+    ("File {}", "Fil {}"),
+    ("server answered {}", "servern svarade {}"),
+    ("InfluxDB buffer {} is full, dropping new points", "InfluxDB-bufferten {} är full, nya punkter kastas"),
+    ("InfluxDB rejected {} points: {}", "InfluxDB avvisade {} punkter: {}"),
+    ("not available, buffering points: {}", "inte tillgänglig, buffrar punkter: {}"),
+    ("Can not write the InfluxDB buffer {}: {}", "Kan inte skriva InfluxDB-bufferten {}: {}"),
+    // Web dashboard
+    ("Can not load the sensors: {}", "Kan inte hämta sensorerna: {}"),
+    ("Lost the connection to the collector, reconnecting...", "Förlorade anslutningen till insamlaren, ansluter igen..."),
+    ("Can not connect to the live feed: {}", "Kan inte ansluta till liveflödet: {}"),
+    ("No sensors seen yet.", "Inga sensorer sedda än."),
+    ("Loading...", "Laddar..."),
+];
+
+fn catalogue() -> &'static HashMap<&'static str, &'static str> {
+    static INDEX: OnceLock<HashMap<&'static str, &'static str>> = OnceLock::new();
+    INDEX.get_or_init(|| CATALOGUE.iter().copied().collect())
+}
+
+// The text in the current language.
+pub fn text(english: &str) -> &str {
+    language().text(english)
+}
+
+// Put arguments into a text, "{}" takes the next one and "{N}" the Nth.
+pub fn fill(text: &str, args: &[&dyn Display]) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut next = 0;
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}').map(|e| start + e) else {
+            rest = &rest[start..];
+            break;
+        };
+        let inner = &rest[start + 1..end];
+        let index = if inner.is_empty() {
+            next += 1;
+            Some(next - 1)
+        } else {
+            inner.parse::<usize>().ok()
+        };
+        match index.and_then(|i| args.get(i)) {
+            Some(arg) => write!(out, "{}", arg).unwrap_or(()),
+            None => out.push_str(&rest[start..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+macro_rules! tr {
+    ($text:expr) => {
+        $crate::i18n::text($text)
+    };
+    ($text:expr, $($arg:expr),+ $(,)?) => {
+        $crate::i18n::fill($crate::i18n::text($text), &[$(&$arg as &dyn std::fmt::Display),+])
+    };
+}
+pub(crate) use tr;
+
+// Decimal comma and space grouping in Swedish, point and comma in English.
+// Thousands are only grouped from five digits on, so 1013 hPa stays short.
+pub fn number(value: f64, decimals: usize) -> String {
+    language().format_number(value, decimals, true)
+}
+
+// Without grouping, for files read by other programs.
+pub fn decimal(value: f64, decimals: usize) -> String {
+    language().format_number(value, decimals, false)
+}
+
+const MONTHS_SV: [&str; 12] = ["jan", "feb", "mar", "apr", "maj", "jun", "jul", "aug", "sep", "okt", "nov", "dec"];
+
+// Date and time, e.g. "18 Oct 2026 21:48:05" or "18 okt 2026 21:48:05".
+pub fn datetime(at: &DateTime<Local>) -> String {
+    language().datetime(at)
+}
+
+// The time of day, the same in both languages.
+pub fn time(at: &DateTime<Local>) -> String {
+    at.format("%H:%M:%S").to_string()
+}
+
+// Field separator for CSV files. Spreadsheets in Swedish use decimal commas,
+// so fields are separated with semicolons.
+pub fn csv_separator() -> char {
+    language().csv_separator()
+}
+
+// What the functions above do in a given language.
+impl Language {
+    fn text(self, english: &str) -> &str {
+        match self {
+            Language::En => english,
+            Language::Sv => catalogue().get(english).copied().unwrap_or(english),
+        }
+    }
+
+    fn format_number(self, value: f64, decimals: usize, grouping: bool) -> String {
+        let (point, group) = match self {
+            Language::En => ('.', ','),
+            Language::Sv => (',', '\u{a0}'),
+        };
+        let formatted = format!("{:.*}", decimals, value.abs());
+        let (int, frac) = formatted.split_once('.').unwrap_or((&formatted, ""));
+        let mut out = String::new();
+        // Not for values that round to zero, "-0".
+        if value < 0.0 && formatted.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            out.push('-');
+        }
+        for (i, c) in int.chars().enumerate() {
+            if grouping && int.len() > 4 && i > 0 && (int.len() - i) % 3 == 0 {
+                out.push(group);
+            }
+            out.push(c);
+        }
+        if !frac.is_empty() {
+            out.push(point);
+            out.push_str(frac);
+        }
+        out
+    }
+
+    fn datetime(self, at: &DateTime<Local>) -> String {
+        match self {
+            Language::En => at.format("%-d %b %Y %H:%M:%S").to_string(),
+            Language::Sv => format!("{} {} {}", at.day(), MONTHS_SV[at.month0() as usize], at.format("%Y %H:%M:%S")),
+        }
+    }
+
+    fn csv_separator(self) -> char {
+        match self {
+            Language::En => ',',
+            Language::Sv => ';',
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    // The language is global, so the tests ask each Language directly instead
+    // of switching it under the other tests.
+
+    #[test]
+    fn numbers() {
+        for (language, one, grouped, plain) in [
+            (Language::En, "1.5", "12,345.7", "12345.7"),
+            (Language::Sv, "1,5", "12\u{a0}345,7", "12345,7"),
+        ] {
+            assert_eq!(language.format_number(1.5, 1, true), one);
+            assert_eq!(language.format_number(12345.67, 1, true), grouped);
+            assert_eq!(language.format_number(12345.67, 1, false), plain);
+            assert_eq!(language.format_number(-12345.67, 1, false), format!("-{}", plain));
+            assert_eq!(language.format_number(1013.0, 0, true), "1013");
+            assert_eq!(language.format_number(-0.04, 1, true), language.format_number(0.0, 1, true));
+        }
+        assert_eq!(Language::En.format_number(1234567.0, 0, true), "1,234,567");
+    }
+
+    #[test]
+    fn csv_separator_differs_from_the_decimal_mark() {
+        for language in Language::ALL {
+            let point = language.format_number(0.5, 1, false).chars().nth(1).unwrap();
+            assert_ne!(language.csv_separator(), point, "{}", language);
+        }
+    }
+
+    #[test]
+    fn dates() {
+        let at = Local.with_ymd_and_hms(2026, 5, 8, 7, 4, 5).unwrap();
+        assert_eq!(Language::En.datetime(&at), "8 May 2026 07:04:05");
+        assert_eq!(Language::Sv.datetime(&at), "8 maj 2026 07:04:05");
+    }
+
+    #[test]
+    fn translations() {
+        for (language, found) in [(Language::En, "Loading..."), (Language::Sv, "Laddar...")] {
+            assert_eq!(language.text("Loading..."), found);
+            // Not in the catalogue, shown in English.
+            assert_eq!(language.text("No such text {}"), "No such text {}");
+            assert_eq!(fill(language.text("No such text {}"), &[&3]), "No such text 3");
+        }
+        assert_eq!(fill(Language::Sv.text("Can not load the sensors: {}"), &[&"timeout"]), "Kan inte hämta sensorerna: timeout");
+    }
+
+    #[test]
+    fn catalogue_keeps_the_arguments() {
+        let args = |s: &str| s.matches('{').count();
+        for (english, swedish) in CATALOGUE {
+            assert_eq!(args(english), args(swedish), "{}", english);
+        }
+        assert_eq!(catalogue().len(), CATALOGUE.len(), "a text is listed twice");
+    }
+
+    #[test]
+    fn arguments() {
+        assert_eq!(fill("{} of {}", &[&1, &"two"]), "1 of two");
+        assert_eq!(fill("{1} of {0}", &[&10, &3]), "3 of 10");
+        assert_eq!(fill("{} and {}", &[&1]), "1 and {}");
+        assert_eq!(fill("{x} {5}", &[&1]), "{x} {5}");
+        assert_eq!(fill("open { brace", &[&1]), "open { brace");
+        assert_eq!(fill("no arguments", &[]), "no arguments");
+    }
+}
@@ -0,0 +1,232 @@
+// InfluxDB sink, built with the `influxdb` feature and enabled by a sink of
+// type "influxdb" in the settings. Every reading becomes one point in the
+// line protocol:
+//
+//     air_quality,sensor=HibouAir-1a2b3c,model=HibouAir\ CO2 co2=612,temperature=22.4,rssi=-61i 1768651200
+//
+// Points are sent in batches by the sink pipeline. When the server can not be
+// reached they are kept in a buffer file and sent before anything else once it
+// is back, so a restart of the database or the network leaves no gaps.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use tracing::{error, warn};
+
+use crate::config::InfluxConfig;
+use crate::decoder::{Metric, Reading};
+use crate::i18n::tr;
+use crate::paths;
+use crate::sinks::Sink;
+
+const BUFFER_FILE: &str = "influx-buffer.lp";
+// The buffer file is not grown past this, newer points are dropped instead.
+const MAX_BUFFER: u64 = 64 * 1024 * 1024;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+// Escape a measurement name, tag key, tag value or field key.
+fn escape(s: &str, measurement: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c == ',' || c == ' ' || (c == '=' && !measurement) || c == '\\' {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+// Default field name, the metric in lower case: co2, pm2_5, temperature...
+fn field_name(config: &InfluxConfig, metric: Metric) -> String {
+    config.fields.get(&metric).cloned().unwrap_or_else(|| format!("{:?}", metric).to_lowercase())
+}
+
+// One reading in the line protocol, None when it has no values.
+pub fn line(config: &InfluxConfig, reading: &Reading) -> Option<String> {
+    let mut fields: Vec<String> = reading
+        .values
+        .iter()
+        .filter(|(_, v)| v.is_finite())
+        .map(|(m, v)| format!("{}={}", escape(&field_name(config, *m), false), v))
+        .collect();
+    if fields.is_empty() {
+        return None;
+    }
+    if let Some(rssi) = reading.rssi {
+        fields.push(format!("rssi={}i", rssi));
+    }
+
+    let mut out = escape(&config.measurement, true);
+    let mut tags = vec![(config.sensor_tag.as_str(), reading.id.as_str())];
+    if let Some(key) = &config.model_tag {
+        tags.push((key, &reading.model));
+    }
+    tags.extend(config.tags.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+    for (key, value) in tags.into_iter().filter(|(_, v)| !v.is_empty()) {
+        out.push_str(&format!(",{}={}", escape(key, false), escape(value, false)));
+    }
+    out.push_str(&format!(" {} {}", fields.join(","), reading.at.timestamp()));
+    Some(out)
+}
+
+enum WriteError {
+    Rejected(String),       // the server will never take these points
+    Unavailable(String),    // try again later
+}
+
+struct Sender {
+    agent: ureq::Agent,
+    write_url: String,
+    token: Option<String>,
+}
+
+impl Sender {
+    fn new(config: &InfluxConfig) -> Self {
+        let write_url = format!(
+            "{}/api/v2/write?org={}&bucket={}&precision=s",
+            config.url.trim_end_matches('/'),
+            encode(&config.org),
+            encode(&config.bucket)
+        );
+        let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+        Self { agent, write_url, token: config.token.clone() }
+    }
+
+    fn write(&self, lines: &[String]) -> Result<(), WriteError> {
+        let mut request = self.agent.post(&self.write_url).set("Content-Type", "text/plain; charset=utf-8");
+        if let Some(token) = &self.token {
+            request = request.set("Authorization", &format!("Token {}", token));
+        }
+        match request.send_string(&lines.join("\n")) {
+            Ok(_) => Ok(()),
+            // Bad data, a bad request or too much at once, sending it again will not help.
+            Err(ureq::Error::Status(code @ (400 | 413 | 422), response)) => {
+                Err(WriteError::Rejected(format!("{} {}", code, response.into_string().unwrap_or_default())))
+            }
+            Err(ureq::Error::Status(code, _)) => Err(WriteError::Unavailable(tr!("server answered {}", code))),
+            Err(e) => Err(WriteError::Unavailable(e.to_string())),
+        }
+    }
+}
+
+fn encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+// Points waiting for the server, one line each.
+struct Buffer {
+    path: PathBuf,
+    full: bool,
+}
+
+impl Buffer {
+    fn load(&self) -> Vec<String> {
+        fs::read_to_string(&self.path)
+            .map(|s| s.lines().filter(|l| !l.is_empty()).map(str::to_string).collect())
+            .unwrap_or_default()
+    }
+
+    fn append(&mut self, lines: &[String]) -> io::Result<()> {
+        if lines.is_empty() {
+            return Ok(());
+        }
+        if fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0) > MAX_BUFFER {
+            if !self.full {
+                warn!(target: "sink", "{}", tr!("InfluxDB buffer {} is full, dropping new points", self.path.display()));
+            }
+            self.full = true;
+            return Ok(());
+        }
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        for line in lines {
+            writeln!(file, "{}", line)?;
+        }
+        Ok(())
+    }
+
+    fn replace(&mut self, lines: &[String]) -> io::Result<()> {
+        self.full = false;
+        if lines.is_empty() {
+            return match fs::remove_file(&self.path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            };
+        }
+        fs::write(&self.path, lines.join("\n") + "\n")
+    }
+}
+
+pub struct InfluxSink {
+    config: InfluxConfig,
+    sender: Sender,
+    buffer: Buffer,
+}
+
+impl InfluxSink {
+    pub fn new(config: InfluxConfig) -> Result<Self, String> {
+        config.validate()?;
+        let sender = Sender::new(&config);
+        let buffer = Buffer { path: paths::data_dir().join(BUFFER_FILE), full: false };
+        Ok(Self { config, sender, buffer })
+    }
+}
+
+impl Sink for InfluxSink {
+    fn name(&self) -> String {
+        format!("InfluxDB {}", self.config.url)
+    }
+
+    fn batch_size(&self) -> usize {
+        self.config.batch_size
+    }
+
+    fn flush_interval(&self) -> Option<Duration> {
+        Some(Duration::from_secs(self.config.flush_interval))
+    }
+
+    // Send the buffered points and then the new ones. Whatever can not be
+    // sent goes to the buffer file, in order.
+    fn write(&mut self, readings: &[Reading]) -> Result<(), String> {
+        let mut pending = self.buffer.load();
+        let buffered = pending.len();
+        pending.extend(readings.iter().filter_map(|r| line(&self.config, r)));
+        let mut sent = 0;
+        let mut result = Ok(());
+        while sent < pending.len() {
+            let end = (sent + self.config.batch_size).min(pending.len());
+            match self.sender.write(&pending[sent..end]) {
+                Ok(()) => {}
+                Err(WriteError::Rejected(e)) => warn!(target: "sink", "{}", tr!("InfluxDB rejected {} points: {}", end - sent, e)),
+                Err(WriteError::Unavailable(e)) => {
+                    result = Err(tr!("not available, buffering points: {}", e));
+                    break;
+                }
+            }
+            sent = end;
+        }
+
+        let rest = &pending[sent..];
+        let res = if sent == 0 && buffered > 0 {
+            // Nothing went out, only the new points need to be added.
+            self.buffer.append(&rest[buffered..])
+        } else if sent >= buffered {
+            self.buffer.replace(&[]).and_then(|_| self.buffer.append(rest))
+        } else {
+            self.buffer.replace(rest)
+        };
+        if let Err(e) = res {
+            error!(target: "sink", "{}", tr!("Can not write the InfluxDB buffer {}: {}", self.buffer.path.display(), e));
+        }
+        result
+    }
+}
@@ -0,0 +1,158 @@
+// How the dashboard shows the sensors: in which order, grouped by room or
+// floor, as full panels or compact tiles. Readings are kept in a HashMap, so
+// the order is made here, with the sensor id last to keep panels in place
+// between renders.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::AppConfig;
+use crate::decoder::{Metric, Reading};
+use crate::i18n::tr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortBy {
+    #[default]
+    Name,
+    Room,
+    Co2,            // highest first
+    LastSeen,       // latest first
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    #[default]
+    None,
+    Room,
+    Floor,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PanelView {
+    #[default]
+    Panels,
+    Tiles,
+}
+
+impl SortBy {
+    pub const ALL: [SortBy; 4] = [SortBy::Name, SortBy::Room, SortBy::Co2, SortBy::LastSeen];
+
+    pub fn label(&self) -> &'static str {
+        tr!(match self {
+            SortBy::Name => "Name",
+            SortBy::Room => "Room",
+            SortBy::Co2 => "CO2",
+            SortBy::LastSeen => "Last seen",
+        })
+    }
+}
+
+impl GroupBy {
+    pub const ALL: [GroupBy; 3] = [GroupBy::None, GroupBy::Room, GroupBy::Floor];
+
+    pub fn label(&self) -> &'static str {
+        tr!(match self {
+            GroupBy::None => "No grouping",
+            GroupBy::Room => "Room",
+            GroupBy::Floor => "Floor",
+        })
+    }
+}
+
+impl PanelView {
+    pub const ALL: [PanelView; 2] = [PanelView::Panels, PanelView::Tiles];
+
+    pub fn label(&self) -> &'static str {
+        tr!(match self {
+            PanelView::Panels => "Panels",
+            PanelView::Tiles => "Tiles",
+        })
+    }
+}
+
+// The layout of the dashboard, kept in the settings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DashboardLayout {
+    pub sort: SortBy,
+    pub group: GroupBy,
+    pub view: PanelView,
+}
+
+// A sensor on the dashboard with what the registry knows about it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacedSensor {
+    pub reading: Reading,
+    pub name: String,               // from the registry, else the sensor id
+    pub room: Option<String>,
+    pub floor: Option<String>,
+}
+
+impl PlacedSensor {
+    pub fn new(reading: &Reading, config: &AppConfig) -> Self {
+        let known = config.sensor(&reading.address);
+        Self {
+            reading: reading.clone(),
+            name: known.and_then(|s| s.name.clone()).unwrap_or_else(|| reading.id.clone()),
+            room: known.and_then(|s| s.room.clone()),
+            floor: known.and_then(|s| s.floor.clone()),
+        }
+    }
+
+    pub fn value(&self, metric: Metric) -> Option<f64> {
+        self.reading.values.iter().find(|(m, _)| *m == metric).map(|(_, v)| *v)
+    }
+
+    // E.g. "Office, 2nd floor", empty if the sensor has no place.
+    pub fn place(&self) -> String {
+        [self.room.as_deref(), self.floor.as_deref()].into_iter().flatten().collect::<Vec<_>>().join(", ")
+    }
+
+    fn by_name(&self, other: &Self) -> Ordering {
+        self.name.to_lowercase().cmp(&other.name.to_lowercase()).then_with(|| self.reading.id.cmp(&other.reading.id))
+    }
+}
+
+// Sensors under a heading, `label` is None when not grouping.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SensorGroup {
+    pub label: Option<String>,
+    pub sensors: Vec<PlacedSensor>,
+}
+
+impl DashboardLayout {
+    // The readings sorted and grouped. Groups come in the order of their names,
+    // the one for sensors without a room or floor last.
+    pub fn arrange<'a>(&self, readings: impl Iterator<Item = &'a Reading>, config: &AppConfig) -> Vec<SensorGroup> {
+        let mut sensors: Vec<PlacedSensor> = readings.map(|r| PlacedSensor::new(r, config)).collect();
+        match self.sort {
+            SortBy::Name => sensors.sort_by(PlacedSensor::by_name),
+            SortBy::Room => sensors.sort_by(|a, b| {
+                let room = |s: &PlacedSensor| (s.room.is_none(), s.room.as_deref().map(str::to_lowercase));
+                room(a).cmp(&room(b)).then_with(|| a.by_name(b))
+            }),
+            // Sensors without CO2 last.
+            SortBy::Co2 => sensors.sort_by(|a, b| {
+                b.value(Metric::Co2).partial_cmp(&a.value(Metric::Co2)).unwrap_or(Ordering::Equal).then_with(|| a.by_name(b))
+            }),
+            SortBy::LastSeen => sensors.sort_by(|a, b| b.reading.at.cmp(&a.reading.at).then_with(|| a.by_name(b))),
+        }
+
+        let (key, none): (fn(&PlacedSensor) -> Option<String>, _) = match self.group {
+            GroupBy::None => return vec![SensorGroup { label: None, sensors }],
+            GroupBy::Room => (|s| s.room.clone(), tr!("No room")),
+            GroupBy::Floor => (|s| s.floor.clone(), tr!("No floor")),
+        };
+        let mut groups: BTreeMap<(bool, String), Vec<PlacedSensor>> = BTreeMap::new();
+        for sensor in sensors {
+            let label = key(&sensor);
+            groups.entry((label.is_none(), label.unwrap_or_else(|| none.to_string()))).or_default().push(sensor);
+        }
+        groups.into_iter().map(|((_, label), sensors)| SensorGroup { label: Some(label), sensors }).collect()
+    }
+}
@@ -0,0 +1,193 @@
+// Logging with tracing. Events carry one of these targets, so they can be
+// filtered by area in the settings or with RUST_LOG, e.g. "info,serial=trace":
+//
+//     serial      lines sent to and received from the dongle
+//     protocol    handshake with the dongle
+//     decode      advertisements and decoded readings
+//     history     the history store and sync from sensors
+//     sink        output sinks and the HTTP API
+//     ui          commands from the user interface
+//
+// Everything that passes the filter goes to stderr, to the log viewer of the
+// desktop app (the latest LOG_CAPACITY events) and, when enabled, to rotated
+// log files in the log directory.
+
+use std::collections::VecDeque;
+use std::fmt::{self, Write};
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use chrono::{DateTime, Local};
+use tokio::sync::Notify;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+use crate::config::{LogConfig, LogRotation};
+use crate::i18n::tr;
+use crate::paths;
+
+// Events kept for the log viewer.
+pub const LOG_CAPACITY: usize = 2000;
+
+pub fn log_dir() -> PathBuf {
+    paths::data_dir().join("logs")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    pub at: DateTime<Local>,
+    pub level: Level,
+    pub target: String,
+    pub spans: String,          // e.g. "serial:handshake"
+    pub message: String,        // the message followed by the other fields
+}
+
+impl LogEntry {
+    pub fn timestamp(&self) -> String {
+        self.at.format("%H:%M:%S%.3f").to_string()
+    }
+
+    // Case insensitive search in target, spans and message.
+    pub fn contains(&self, text: &str) -> bool {
+        let text = text.to_lowercase();
+        [&self.target, &self.spans, &self.message].iter().any(|s| s.to_lowercase().contains(&text))
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: Mutex<VecDeque<LogEntry>>,
+    changed: Notify,
+}
+
+// The latest events, shared between the tracing layer and the log viewer.
+#[derive(Clone, Default)]
+pub struct LogBuffer {
+    inner: Arc<Inner>,
+}
+
+impl LogBuffer {
+    fn lock(&self) -> MutexGuard<'_, VecDeque<LogEntry>> {
+        self.inner.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn push(&self, entry: LogEntry) {
+        let mut entries = self.lock();
+        if entries.len() >= LOG_CAPACITY {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+        self.inner.changed.notify_one();
+    }
+
+    pub fn entries(&self) -> Vec<LogEntry> {
+        self.lock().iter().cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+        self.inner.changed.notify_one();
+    }
+
+    // Wait until an event is added or the buffer is cleared.
+    pub async fn changed(&self) {
+        self.inner.changed.notified().await;
+    }
+}
+
+// Collects the message and the other fields of an event as text.
+#[derive(Default)]
+struct Fields {
+    message: String,
+    rest: String,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            write!(self.rest, " {}={}", field.name(), value).ok();
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            write!(self.message, "{:?}", value).ok();
+        } else {
+            write!(self.rest, " {}={:?}", field.name(), value).ok();
+        }
+    }
+}
+
+struct BufferLayer(LogBuffer);
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for BufferLayer {
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let spans = ctx
+            .event_scope(event)
+            .map(|scope| scope.from_root().map(|s| s.name()).collect::<Vec<_>>().join(":"))
+            .unwrap_or_default();
+        self.0.push(LogEntry {
+            at: Local::now(),
+            level: *event.metadata().level(),
+            target: event.metadata().target().to_string(),
+            spans,
+            message: fields.message + &fields.rest,
+        });
+    }
+}
+
+fn file_appender(config: &LogConfig) -> Result<RollingFileAppender, String> {
+    let rotation = match config.rotation {
+        LogRotation::Hourly => Rotation::HOURLY,
+        LogRotation::Daily => Rotation::DAILY,
+        LogRotation::Never => Rotation::NEVER,
+    };
+    std::fs::create_dir_all(log_dir()).map_err(|e| e.to_string())?;
+    RollingFileAppender::builder()
+        .rotation(rotation)
+        .filename_prefix("dxbleuio")
+        .filename_suffix("log")
+        .max_log_files(config.max_files.max(1))
+        .build(log_dir())
+        .map_err(|e| e.to_string())
+}
+
+// Install the subscriber, once at start. The returned buffer is what the log
+// viewer shows.
+pub fn init(config: &LogConfig) -> LogBuffer {
+    let filter = EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new(&config.filter)).unwrap_or_else(|e| {
+        eprintln!("{}", tr!("Ignoring the log filter \"{}\": {}", config.filter, e));
+        EnvFilter::new("info")
+    });
+    let file = if config.file {
+        file_appender(config)
+            .map_err(|e| eprintln!("{}", tr!("Can not write log files to {}: {}", log_dir().display(), e)))
+            .ok()
+            .map(|appender| tracing_subscriber::fmt::layer().with_ansi(false).with_writer(appender))
+    } else {
+        None
+    };
+    // The fmt layers share the formatted span fields, colors would end up in the files.
+    let ansi = std::io::stderr().is_terminal() && file.is_none();
+    let buffer = LogBuffer::default();
+    let res = tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer().with_ansi(ansi).with_writer(std::io::stderr))
+        .with(file)
+        .with(BufferLayer(buffer.clone()))
+        .try_init();
+    if let Err(e) = res {
+        eprintln!("{}", tr!("Can not set up logging: {}", e));
+    }
+    buffer
+}
@@ -0,0 +1,61 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use crate::bleuio::find_bleuio;
+use crate::i18n::tr;
+use crate::transport::Transport;
+
+/* Command line options:
+    --replay <file>     read from a capture file instead of the dongle
+    --speed <factor>    replay speed, 1 = original timing, 0 = as fast as possible
+    --record <file>     record all serial traffic to a capture file from the start
+    --http <addr:port>  serve the REST API and live feed (needs the http-api feature)
+    --headless          run as a collector without a window (needs the server feature)
+    --web-root <dir>    web build of the dashboard served by the collector
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    pub transport: Transport,
+    pub record: Option<PathBuf>,
+    pub http: Option<SocketAddr>,
+    pub headless: bool,
+    pub web_root: Option<PathBuf>,
+}
+
+impl Options {
+    pub fn from_args() -> Self {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Self {
+        let mut replay: Option<PathBuf> = None;
+        let mut speed: f64 = 1.0;
+        let mut record: Option<PathBuf> = None;
+        let mut http: Option<SocketAddr> = None;
+        let mut headless = false;
+        let mut web_root: Option<PathBuf> = None;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--replay" => replay = args.next().map(PathBuf::from),
+                "--speed" => speed = args.next().and_then(|s| s.parse().ok()).unwrap_or(speed),
+                "--record" => record = args.next().map(PathBuf::from),
+                "--http" => match args.next().map(|a| a.parse::<SocketAddr>()) {
+                    Some(Ok(addr)) => http = Some(addr),
+                    _ => eprintln!("{}", tr!("--http needs an address like 127.0.0.1:8080")),
+                },
+                "--headless" => headless = true,
+                "--web-root" => web_root = args.next().map(PathBuf::from),
+                _ => eprintln!("{}", tr!("Ignoring unknown argument {}", arg)),
+            }
+        }
+
+        let transport = match replay {
+            Some(path) => Transport::Replay { path, speed },
+            None => Transport::Serial { port_name: find_bleuio() },
+        };
+
+        Self { transport, record, http, headless, web_root }
+    }
+}
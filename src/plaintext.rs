@@ -0,0 +1,341 @@
+// Parser for the dongle's non-verbose (ATV0) output. Text lines are turned into
+// the same JSON values the verbose protocol sends, so everything after
+// `parse_bleuio_result` works the same in both modes.
+//
+// Plain text has no command indices and no end marker for most commands. The
+// parser numbers the commands it sees being sent and ends a command on OK,
+// ERROR and similar lines, when the next command is sent, or when `finish` is
+// called after the dongle has been quiet for a while.
+//
+// Examples of lines understood:
+//     OK
+//     ERROR
+//     ECHO OFF
+//     SCANNING...
+//     [01] Device: [1]D3:2A:7C:1B:4F:57  RSSI: -74 (HibouAir)
+//     [D0:76:50:80:0F:49] Device Data [ADV]: 0201061BFF5B07...
+//     [D0:76:50:80:0F:49] RSSI: -61 Device Data [RESP]: 0B09...
+//     SCAN COMPLETE
+//     CONNECTED.
+//     DISCONNECTED.
+//     Firmware Version: 2.7.9.51
+
+use serde_json::{json, Map, Value};
+
+// Indices of synthesized lines start here, far from the dongle's own numbering.
+const FIRST_INDEX: i64 = 100_000;
+
+// Lines that end the command successfully.
+const SUCCESS_LINES: &[&str] = &["OK", "ECHO OFF", "ECHO ON", "VERBOSE ON", "VERBOSE OFF"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Sent,           // C line not produced yet
+    Running,        // C line produced, waiting for the result
+    Scanning,       // result produced, scan lines follow
+}
+
+#[derive(Debug)]
+struct Command {
+    index: i64,
+    line: String,
+    state: State,
+    lines: usize,
+}
+
+#[derive(Debug)]
+pub struct PlainTextParser {
+    next_index: i64,
+    current: Option<Command>,
+    finished: Option<Command>,      // ended by a new command, its E line is still due
+}
+
+impl Default for PlainTextParser {
+    fn default() -> Self {
+        Self { next_index: FIRST_INDEX, current: None, finished: None }
+    }
+}
+
+fn end_line(cmd: &Command) -> Value {
+    json!({"E": cmd.index, "nol": cmd.lines})
+}
+
+impl PlainTextParser {
+    // A command line was written to the dongle.
+    pub fn sent(&mut self, line: &str) {
+        let line = line.trim();
+        // Ctrl-C stops a scan, it is not a command of its own.
+        if line.is_empty() || line.starts_with('\x03') {
+            return;
+        }
+        // A command that got no answer at all is dropped, one in the middle of
+        // its answer gets its E line before the next command's lines.
+        if let Some(previous) = self.current.take() {
+            if previous.state == State::Running {
+                self.finished = Some(previous);
+            }
+        }
+        self.current = Some(Command { index: self.next_index, line: line.to_string(), state: State::Sent, lines: 0 });
+        self.next_index += 1;
+    }
+
+    // A verbose JSON line arrived, the dongle answers for itself.
+    pub fn clear(&mut self) {
+        self.current = None;
+        self.finished = None;
+    }
+
+    fn index(&self) -> i64 {
+        self.current.as_ref().map(|c| c.index).unwrap_or(FIRST_INDEX - 1)
+    }
+
+    fn is_scan_command(&self, name: &str) -> bool {
+        self.current.as_ref().is_some_and(|c| c.line.to_uppercase().starts_with(name))
+    }
+
+    // End the running command if the dongle has not finished it by itself.
+    pub fn finish(&mut self) -> Vec<Value> {
+        let mut out = Vec::new();
+        if let Some(cmd) = self.finished.take() {
+            out.push(end_line(&cmd));
+        }
+        if let Some(cmd) = self.current.as_mut() {
+            if cmd.state == State::Running {
+                out.push(end_line(cmd));
+                self.current = None;
+            }
+        }
+        out
+    }
+
+    // Turn one text line into zero or more verbose protocol values.
+    pub fn parse_line(&mut self, line: &str) -> Vec<Value> {
+        let text = line.trim();
+        let mut out = Vec::new();
+        if let Some(cmd) = self.finished.take() {
+            out.push(end_line(&cmd));
+        }
+        // The dongle echoes commands when ATE1 is on.
+        if text.is_empty() || self.current.as_ref().is_some_and(|c| c.line.eq_ignore_ascii_case(text)) {
+            return out;
+        }
+        if let Some(cmd) = self.current.as_mut() {
+            if cmd.state == State::Sent {
+                out.push(json!({"C": cmd.index, "cmd": cmd.line}));
+                cmd.state = State::Running;
+            }
+        }
+
+        let upper = text.to_uppercase();
+        if let Some(event) = parse_connection(&upper, text) {
+            out.push(event);
+        } else if let Some(v) = self.parse_scan_line(text) {
+            out.push(v);
+        } else if upper.starts_with("SCANNING") {
+            // Like in verbose mode the command ends here and the scan lines keep its index.
+            self.result(&mut out, 0, "ok", Some(json!({"action": "scanning"})));
+            if let Some(cmd) = self.current.as_mut() {
+                cmd.state = State::Scanning;
+            }
+        } else if upper.starts_with("SCAN COMPLETE") {
+            out.push(json!({"SE": self.index(), "action": "scan completed"}));
+            self.current = None;
+        } else if SUCCESS_LINES.contains(&upper.as_str()) {
+            let reply = (upper != "OK").then(|| json!({"action": text}));
+            self.result(&mut out, 0, "ok", reply);
+            self.current = None;
+        } else if upper.starts_with("ERROR") || upper.starts_with("INVALID") || upper.contains("NOT ALLOWED") {
+            self.result(&mut out, 1, text, None);
+            self.current = None;
+        } else if let Some(cmd) = self.current.as_mut() {
+            cmd.lines += 1;
+            out.push(reply_line(cmd.index, text));
+        }
+        out
+    }
+
+    // Produce the A, R and E lines of a finished command.
+    fn result(&mut self, out: &mut Vec<Value>, err: i64, msg: &str, reply: Option<Value>) {
+        let Some(cmd) = self.current.as_mut() else {
+            return;
+        };
+        out.push(json!({"A": cmd.index, "err": err, "errMsg": msg}));
+        if let Some(Value::Object(mut o)) = reply {
+            o.insert("R".to_string(), json!(cmd.index));
+            out.push(Value::Object(o));
+            cmd.lines += 1;
+        }
+        out.push(json!({"E": cmd.index, "nol": cmd.lines + 2}));
+    }
+
+    fn parse_scan_line(&mut self, text: &str) -> Option<Value> {
+        // [01] Device: [1]D3:2A:7C:1B:4F:57  RSSI: -74 (HibouAir)
+        if let Some(rest) = text.split_once("Device:").map(|(_, r)| r.trim()) {
+            let addr = rest.split_whitespace().next()?;
+            let mut v = json!({"S": self.index(), "addr": addr});
+            if let Some(rssi) = parse_rssi(rest) {
+                v["rssi"] = json!(rssi);
+            }
+            if let (Some(open), Some(close)) = (rest.find('('), rest.rfind(')')) {
+                if open < close {
+                    v["name"] = json!(&rest[open + 1..close]);
+                }
+            }
+            return Some(v);
+        }
+        // [D0:76:50:80:0F:49] Device Data [ADV]: 0201...
+        let (head, data) = text.split_once("Device Data")?;
+        let addr = head.trim().strip_prefix('[')?.split(']').next()?;
+        let (kind, data) = data.split_once(':')?;
+        let adv_type = if kind.contains("RESP") { 4 } else { 0 };
+        let key = if self.is_scan_command("AT+SCANTARGET") { "ST" } else { "SF" };
+        let mut v = json!({key: self.index(), "addr": addr, "type": adv_type, "data": data.trim()});
+        if let Some(rssi) = parse_rssi(head) {
+            v["rssi"] = json!(rssi);
+        }
+        Some(v)
+    }
+}
+
+fn parse_rssi(text: &str) -> Option<i64> {
+    let (_, rest) = text.split_once("RSSI:")?;
+    rest.split_whitespace().next()?.parse().ok()
+}
+
+// Connection messages become events, see gatt::parse_event.
+fn parse_connection(upper: &str, text: &str) -> Option<Value> {
+    let action = if upper.starts_with("DISCONNECTED") {
+        "disconnected"
+    } else if upper.starts_with("CONNECTED") {
+        "connected"
+    } else {
+        return None;
+    };
+    let mut v = json!({"0": "0000", "action": action});
+    if let Some(addr) = text.split_whitespace().find(|w| w.matches(':').count() == 5) {
+        v["addr"] = json!(addr.trim_end_matches('.'));
+    }
+    Some(v)
+}
+
+// A reply line, "Key: value" lines keep their key so they can be looked up.
+fn reply_line(index: i64, text: &str) -> Value {
+    let mut o = Map::new();
+    o.insert("R".to_string(), json!(index));
+    match text.split_once(": ") {
+        Some((key, value)) if !key.is_empty() && !key.contains('[') => {
+            o.insert(key.trim().to_string(), json!(value.trim()));
+        }
+        _ => {
+            o.insert("text".to_string(), json!(text));
+        }
+    }
+    Value::Object(o)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bleuio::{get_bleuio_result_type, BleuIOResponseType as T};
+
+    const I: i64 = FIRST_INDEX;
+
+    // Send `command` and feed the recorded `lines`.
+    fn run(parser: &mut PlainTextParser, command: &str, lines: &[&str]) -> Vec<Value> {
+        parser.sent(command);
+        lines.iter().flat_map(|l| parser.parse_line(l)).collect()
+    }
+
+    fn types(values: &[Value]) -> Vec<T> {
+        values.iter().map(get_bleuio_result_type).collect()
+    }
+
+    #[test]
+    fn ok_and_error() {
+        let mut p = PlainTextParser::default();
+        let out = run(&mut p, "AT+CENTRAL", &["OK"]);
+        assert_eq!(out, [json!({"C": I, "cmd": "AT+CENTRAL"}), json!({"A": I, "err": 0, "errMsg": "ok"}), json!({"E": I, "nol": 2})]);
+        assert_eq!(types(&out), [T::CommandResponse, T::AcknowledgementResponse, T::EndResponse]);
+
+        let out = run(&mut p, "AT+FOO", &["ERROR: Invalid command"]);
+        assert_eq!(out[1], json!({"A": I + 1, "err": 1, "errMsg": "ERROR: Invalid command"}));
+        assert_eq!(types(&out), [T::CommandResponse, T::AcknowledgementResponse, T::EndResponse]);
+        assert!(p.finish().is_empty());
+    }
+
+    #[test]
+    fn success_lines_are_replies() {
+        let mut p = PlainTextParser::default();
+        let out = run(&mut p, "ATE0", &["ATE0", "ECHO OFF"]);
+        assert_eq!(out[2], json!({"R": I, "action": "ECHO OFF"}));
+        assert_eq!(out[3], json!({"E": I, "nol": 3}));
+        assert_eq!(types(&out), [T::CommandResponse, T::AcknowledgementResponse, T::ReplyResponse, T::EndResponse]);
+    }
+
+    #[test]
+    fn gap_scan() {
+        let mut p = PlainTextParser::default();
+        let out = run(&mut p, "AT+GAPSCAN", &["SCANNING...", "[01] Device: [1]D3:2A:7C:1B:4F:57  RSSI: -74 (HibouAir)", "[02] Device: [0]C8:47:8C:00:00:01  RSSI: -80", "SCAN COMPLETE"]);
+        assert_eq!(
+            types(&out),
+            [T::CommandResponse, T::AcknowledgementResponse, T::ReplyResponse, T::EndResponse, T::ScanDataResponse, T::ScanDataResponse, T::ScanEndedResponse]
+        );
+        assert_eq!(out[2], json!({"R": I, "action": "scanning"}));
+        assert_eq!(out[4], json!({"S": I, "addr": "[1]D3:2A:7C:1B:4F:57", "rssi": -74, "name": "HibouAir"}));
+        assert_eq!(out[5], json!({"S": I, "addr": "[0]C8:47:8C:00:00:01", "rssi": -80}));
+        assert_eq!(out[6], json!({"SE": I, "action": "scan completed"}));
+    }
+
+    #[test]
+    fn find_scan_data() {
+        let mut p = PlainTextParser::default();
+        let out = run(&mut p, "AT+FINDSCANDATA=FF5B07", &["SCANNING...", "[D0:76:50:80:0F:49] Device Data [ADV]: 0201061BFF5B07", "[D0:76:50:80:0F:49] RSSI: -61 Device Data [RESP]: 0B09486962"]);
+        assert_eq!(types(&out)[4..], [T::ScanFindDataResponse, T::ScanFindDataResponse]);
+        assert_eq!(out[4], json!({"SF": I, "addr": "D0:76:50:80:0F:49", "type": 0, "data": "0201061BFF5B07"}));
+        assert_eq!(out[5], json!({"SF": I, "addr": "D0:76:50:80:0F:49", "type": 4, "data": "0B09486962", "rssi": -61}));
+
+        // Ctrl-C ends the scan without starting a command.
+        p.sent("\x03");
+        assert_eq!(p.parse_line("SCAN COMPLETE"), [json!({"SE": I, "action": "scan completed"})]);
+
+        let out = run(&mut p, "AT+SCANTARGET=[0]D0:76:50:80:0F:49", &["SCANNING...", "[D0:76:50:80:0F:49] Device Data [ADV]: 0201061BFF5B07"]);
+        assert_eq!(out[4], json!({"ST": I + 1, "addr": "D0:76:50:80:0F:49", "type": 0, "data": "0201061BFF5B07"}));
+        assert_eq!(get_bleuio_result_type(&out[4]), T::ScanTargetResponse);
+    }
+
+    #[test]
+    fn connection_events() {
+        let mut p = PlainTextParser::default();
+        let out = run(&mut p, "AT+GAPCONNECT=[0]C8:47:8C:00:00:01", &["OK", "CONNECTED."]);
+        assert_eq!(out[3], json!({"0": "0000", "action": "connected"}));
+        assert_eq!(get_bleuio_result_type(&out[3]), T::EventResponse);
+        let out = p.parse_line("DISCONNECTED from C8:47:8C:00:00:01.");
+        assert_eq!(out, [json!({"0": "0000", "action": "disconnected", "addr": "C8:47:8C:00:00:01"})]);
+        assert_eq!(types(&out), [T::EventResponse]);
+    }
+
+    #[test]
+    fn multi_line_reply_ended_by_finish() {
+        let mut p = PlainTextParser::default();
+        let out = run(&mut p, "ATI", &["Smart Sensor Devices AB", "Firmware Version: 2.7.9.51", "Dongle is in Dual role"]);
+        assert_eq!(out[1], json!({"R": I, "text": "Smart Sensor Devices AB"}));
+        assert_eq!(out[2], json!({"R": I, "Firmware Version": "2.7.9.51"}));
+        assert_eq!(types(&out), [T::CommandResponse, T::ReplyResponse, T::ReplyResponse, T::ReplyResponse]);
+        assert_eq!(p.finish(), [json!({"E": I, "nol": 3})]);
+        assert!(p.finish().is_empty());
+    }
+
+    #[test]
+    fn next_command_ends_the_previous_one() {
+        let mut p = PlainTextParser::default();
+        let out = run(&mut p, "ATI", &["Smart Sensor Devices AB"]);
+        assert_eq!(out.len(), 2);
+        // A command without any answer is dropped.
+        p.sent("AT+ADVSTART");
+        let out = run(&mut p, "AT", &["OK"]);
+        assert_eq!(out[0], json!({"E": I, "nol": 1}));
+        assert_eq!(out[1], json!({"C": I + 2, "cmd": "AT"}));
+        assert_eq!(types(&out), [T::EndResponse, T::CommandResponse, T::AcknowledgementResponse, T::EndResponse]);
+    }
+}
@@ -0,0 +1,286 @@
+// User defined scan filters. A filter is turned into one dongle command and a
+// check done on every scan line, since the dongle can only look for a single
+// hex string in the advertising data (AT+FINDSCANDATA) or follow a list of
+// addresses (AT+SCANTARGET).
+//
+// All criteria of a filter must match. The dongle gets the most selective one,
+// the rest are checked here. Advertising data and scan responses arrive as
+// separate lines, so a data criterion only rejects a line that carries that
+// kind of field with another value; address and RSSI always apply.
+
+use serde::{Deserialize, Serialize};
+
+use crate::advertising::{parse_ad_structures, parse_hex, to_hex, AdStructure};
+use crate::config::KnownSensor;
+use crate::hibouair::HIBOUAIR_COMPANY_ID;
+use crate::i18n::tr;
+use crate::scanner::split_addr;
+
+pub const DEFAULT_FILTER: &str = "HibouAir";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanFilter {
+    pub name: String,
+    pub manufacturer_id: Option<u16>,
+    pub service_uuid: Option<u16>,      // 16-bit UUID of service data
+    pub name_prefix: Option<String>,
+    pub addresses: Vec<String>,         // as in KnownSensor
+    pub min_rssi: Option<i16>,
+}
+
+impl Default for ScanFilter {
+    fn default() -> Self {
+        Self {
+            name: DEFAULT_FILTER.to_string(),
+            manufacturer_id: Some(HIBOUAIR_COMPANY_ID),
+            service_uuid: None,
+            name_prefix: None,
+            addresses: Vec::new(),
+            min_rssi: None,
+        }
+    }
+}
+
+// What the dongle is asked to do for a filter.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DongleScan {
+    FindData(String),           // AT+FINDSCANDATA=<hex>
+    Targets(Vec<String>),       // AT+SCANTARGET=[0]addr;[1]addr
+}
+
+impl ScanFilter {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err(tr!("The filter needs a name").to_string());
+        }
+        if self.name_prefix.as_deref().is_some_and(|p| p.is_empty() || !p.is_ascii()) {
+            return Err(tr!("The name prefix must be plain ASCII text").to_string());
+        }
+        for address in &self.addresses {
+            KnownSensor::new(address, None)?;
+        }
+        if self.dongle_scan().is_none() {
+            return Err(tr!("The filter needs a manufacturer id, service data UUID, name prefix or addresses").to_string());
+        }
+        Ok(())
+    }
+
+    // The hex string the dongle looks for in the advertising data.
+    fn pattern(&self) -> Option<String> {
+        if let Some(id) = self.manufacturer_id {
+            let id = id.to_le_bytes();
+            Some(format!("FF{:02X}{:02X}", id[0], id[1]))
+        } else if let Some(uuid) = self.service_uuid {
+            let uuid = uuid.to_le_bytes();
+            Some(format!("16{:02X}{:02X}", uuid[0], uuid[1]))
+        } else {
+            self.name_prefix.as_ref().map(|p| to_hex(p.as_bytes()))
+        }
+    }
+
+    // Addresses are the most selective, then the data criteria in the order
+    // manufacturer id, service data and name.
+    pub fn dongle_scan(&self) -> Option<DongleScan> {
+        if !self.addresses.is_empty() {
+            let targets = self.addresses.iter().filter_map(|a| KnownSensor::new(a, None).ok()).map(|s| s.target());
+            return Some(DongleScan::Targets(targets.collect()));
+        }
+        self.pattern().map(DongleScan::FindData)
+    }
+
+    // Check a SF or ST line.
+    pub fn matches(&self, addr: &str, rssi: Option<i16>, data: &str) -> bool {
+        if self.min_rssi.is_some_and(|min| rssi.is_some_and(|r| r < min)) {
+            return false;
+        }
+        if !self.addresses.is_empty() {
+            let mac = split_addr(addr).1;
+            if !self.addresses.iter().any(|a| split_addr(a).1.eq_ignore_ascii_case(&mac)) {
+                return false;
+            }
+        }
+        let ad = parse_ad_structures(&parse_hex(data).unwrap_or_default());
+        // Lines without the field pass, see above.
+        let companies: Vec<u16> = ad
+            .iter()
+            .filter_map(|s| match s {
+                AdStructure::ManufacturerData { company_id, .. } => Some(*company_id),
+                _ => None,
+            })
+            .collect();
+        if self.manufacturer_id.is_some_and(|id| !companies.is_empty() && !companies.contains(&id)) {
+            return false;
+        }
+        let uuids: Vec<u16> = ad
+            .iter()
+            .filter_map(|s| match s {
+                AdStructure::ServiceData16 { uuid, .. } => Some(*uuid),
+                _ => None,
+            })
+            .collect();
+        if self.service_uuid.is_some_and(|uuid| !uuids.is_empty() && !uuids.contains(&uuid)) {
+            return false;
+        }
+        if let Some(prefix) = &self.name_prefix {
+            let name = ad.iter().find_map(|s| match s {
+                AdStructure::LocalName { name, .. } => Some(name),
+                _ => None,
+            });
+            if name.is_some_and(|n| !n.starts_with(prefix.as_str())) {
+                return false;
+            }
+        }
+        true
+    }
+
+    // Build a filter from the text fields of the filter form.
+    pub fn from_form(name: &str, manufacturer: &str, service: &str, prefix: &str, addresses: &str, min_rssi: &str) -> Result<Self, String> {
+        let min_rssi = match min_rssi.trim() {
+            "" => None,
+            s => Some(s.parse::<i16>().map_err(|_| tr!("Minimum RSSI must be a whole number of dBm").to_string())?),
+        };
+        let filter = Self {
+            name: name.trim().to_string(),
+            manufacturer_id: parse_id(manufacturer)?,
+            service_uuid: parse_id(service)?,
+            name_prefix: Some(prefix.to_string()).filter(|p| !p.is_empty()),
+            addresses: addresses.split([',', ' ']).filter(|a| !a.is_empty()).map(str::to_string).collect(),
+            min_rssi,
+        };
+        filter.validate()?;
+        Ok(filter)
+    }
+
+    // One line summary for lists.
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(id) = self.manufacturer_id {
+            parts.push(tr!("manufacturer 0x{}", format!("{:04X}", id)));
+        }
+        if let Some(uuid) = self.service_uuid {
+            parts.push(tr!("service data 0x{}", format!("{:04X}", uuid)));
+        }
+        if let Some(prefix) = &self.name_prefix {
+            parts.push(tr!("name \"{}*\"", prefix));
+        }
+        if !self.addresses.is_empty() {
+            parts.push(tr!("{} addresses", self.addresses.len()));
+        }
+        if let Some(min) = self.min_rssi {
+            parts.push(format!("RSSI >= {} dBm", min));
+        }
+        parts.join(", ")
+    }
+}
+
+// Parse a 16-bit id typed as hex, with or without 0x.
+fn parse_id(s: &str) -> Result<Option<u16>, String> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Ok(None);
+    }
+    let hex = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")).unwrap_or(s);
+    u16::from_str_radix(hex, 16).map(Some).map_err(|_| tr!("{} is not a 16-bit hex number", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HIBOUAIR: &str = "02010605FF5B070504";
+    const APPLE: &str = "02010605FF4C000215";
+    const BTHOME: &str = "0201060416D2FC40";
+    const ATC_NAME: &str = "09094154435F31323334";
+    const OTHER_NAME: &str = "0409505658";
+
+    fn filter() -> ScanFilter {
+        ScanFilter { name: "Test".to_string(), manufacturer_id: None, ..ScanFilter::default() }
+    }
+
+    #[test]
+    fn dongle_scan() {
+        assert_eq!(ScanFilter::default().dongle_scan(), Some(DongleScan::FindData("FF5B07".to_string())));
+        let service = ScanFilter { service_uuid: Some(0xFCD2), ..filter() };
+        assert_eq!(service.dongle_scan(), Some(DongleScan::FindData("16D2FC".to_string())));
+        let prefix = ScanFilter { name_prefix: Some("ATC".to_string()), ..filter() };
+        assert_eq!(prefix.dongle_scan(), Some(DongleScan::FindData("415443".to_string())));
+        // The manufacturer id is the most selective data criterion.
+        let both = ScanFilter { manufacturer_id: Some(0x004C), service_uuid: Some(0xFCD2), ..filter() };
+        assert_eq!(both.dongle_scan(), Some(DongleScan::FindData("FF4C00".to_string())));
+        // Addresses go before everything, with a public address type unless given.
+        let addresses = ScanFilter { addresses: vec!["c8:47:8c:00:00:01".to_string(), "[1]D3:2A:7C:1B:4F:57".to_string()], ..ScanFilter::default() };
+        assert_eq!(
+            addresses.dongle_scan(),
+            Some(DongleScan::Targets(vec!["[0]C8:47:8C:00:00:01".to_string(), "[1]D3:2A:7C:1B:4F:57".to_string()]))
+        );
+        assert_eq!(filter().dongle_scan(), None);
+    }
+
+    #[test]
+    fn matches_manufacturer() {
+        let f = ScanFilter::default();
+        assert!(f.matches("F5:50:35:CF:B1:ED", Some(-60), HIBOUAIR));
+        assert!(!f.matches("F5:50:35:CF:B1:ED", Some(-60), APPLE));
+        // A scan response without manufacturer data is let through.
+        assert!(f.matches("F5:50:35:CF:B1:ED", Some(-60), OTHER_NAME));
+    }
+
+    #[test]
+    fn matches_service_data() {
+        let f = ScanFilter { service_uuid: Some(0xFCD2), ..filter() };
+        assert!(f.matches("A4:C1:38:00:00:01", None, BTHOME));
+        assert!(!f.matches("A4:C1:38:00:00:01", None, "0201060416AAFE00"));
+        assert!(f.matches("A4:C1:38:00:00:01", None, HIBOUAIR));
+    }
+
+    #[test]
+    fn matches_name_prefix() {
+        let f = ScanFilter { name_prefix: Some("ATC".to_string()), ..filter() };
+        assert!(f.matches("A4:C1:38:00:00:01", None, ATC_NAME));
+        assert!(!f.matches("A4:C1:38:00:00:01", None, OTHER_NAME));
+        assert!(f.matches("A4:C1:38:00:00:01", None, BTHOME));
+    }
+
+    #[test]
+    fn matches_addresses() {
+        let f = ScanFilter { addresses: vec!["c8:47:8c:00:00:01".to_string()], ..ScanFilter::default() };
+        assert!(f.matches("[1]C8:47:8C:00:00:01", None, HIBOUAIR));
+        assert!(f.matches("C8:47:8C:00:00:01", None, HIBOUAIR));
+        assert!(!f.matches("C8:47:8C:00:00:02", None, HIBOUAIR));
+        // The other criteria still apply.
+        assert!(!f.matches("C8:47:8C:00:00:01", None, APPLE));
+    }
+
+    #[test]
+    fn matches_min_rssi() {
+        let f = ScanFilter { min_rssi: Some(-70), ..ScanFilter::default() };
+        assert!(f.matches("F5:50:35:CF:B1:ED", Some(-70), HIBOUAIR));
+        assert!(!f.matches("F5:50:35:CF:B1:ED", Some(-71), HIBOUAIR));
+        // Lines without RSSI can not be checked.
+        assert!(f.matches("F5:50:35:CF:B1:ED", None, HIBOUAIR));
+    }
+
+    #[test]
+    fn from_form() {
+        let f = ScanFilter::from_form(" Ruuvi ", "0x0499", "", "", "", "-80").unwrap();
+        assert_eq!(f, ScanFilter { name: "Ruuvi".to_string(), manufacturer_id: Some(0x0499), min_rssi: Some(-80), ..filter() });
+        let f = ScanFilter::from_form("Mine", "", "fcd2", "ATC", "C8:47:8C:00:00:01, [1]D3:2A:7C:1B:4F:57", "").unwrap();
+        assert_eq!(f.service_uuid, Some(0xFCD2));
+        assert_eq!(f.name_prefix.as_deref(), Some("ATC"));
+        assert_eq!(f.addresses, ["C8:47:8C:00:00:01", "[1]D3:2A:7C:1B:4F:57"]);
+        assert_eq!(f.min_rssi, None);
+    }
+
+    #[test]
+    fn form_errors() {
+        assert!(ScanFilter::from_form(" ", "075B", "", "", "", "").is_err());
+        assert!(ScanFilter::from_form("Test", "12345", "", "", "", "").is_err());
+        assert!(ScanFilter::from_form("Test", "", "xyz", "", "", "").is_err());
+        assert!(ScanFilter::from_form("Test", "075B", "", "", "", "-80.5").is_err());
+        assert!(ScanFilter::from_form("Test", "", "", "", "C8:47:8C:00:00", "").is_err());
+        assert!(ScanFilter::from_form("Test", "", "", "Café", "", "").is_err());
+        // Nothing the dongle can look for.
+        assert!(ScanFilter::from_form("Test", "", "", "", "", "-80").is_err());
+    }
+}
@@ -0,0 +1,345 @@
+// Keeps the sensor scan running. The dongle ends a scan by itself when it has a
+// duration or when the firmware times it out, which is seen as the link going
+// from Scanning back to Ready. The scheduler then starts a new scan after the
+// configured pause, so a duration and a pause give a duty cycle:
+//
+//     Scanning (duration) -> Ready (pause) -> Scanning -> ...
+//
+// The scan command comes from the selected scan filter. With `targets_only` and
+// registered sensors AT+SCANTARGET is used instead, so only those sensors are
+// followed.
+//
+// Scans started by the user for other purposes (scanner tab, console) suspend
+// the scheduler until the sensor scan is started again. GATT connections pause
+// it only while they are open.
+//
+// Like the handshake it does no I/O, the serial task asks it on every tick if a
+// scan should be started.
+
+use tokio::time::{Duration, Instant};
+
+use crate::config::ScanConfig;
+use crate::handshake::LinkState;
+use crate::i18n::tr;
+use crate::scan_filter::{DongleScan, ScanFilter};
+// A scan that did not start (the dongle was busy) is tried again after this.
+const START_RETRY: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScanStatus {
+    Idle,               // the dongle is not ready
+    Scanning,
+    Waiting(u64),       // seconds until the next scan
+    Suspended,          // the user is running another scan
+    Paused,             // a GATT connection is open
+}
+
+impl ScanStatus {
+    pub fn description(&self) -> String {
+        match self {
+            ScanStatus::Idle | ScanStatus::Scanning => String::new(),
+            ScanStatus::Waiting(0) => tr!("starting scan").to_string(),
+            ScanStatus::Waiting(s) => tr!("next scan in {} s", s),
+            ScanStatus::Suspended => tr!("sensor scan paused").to_string(),
+            ScanStatus::Paused => tr!("sensor scan paused during a connection").to_string(),
+        }
+    }
+}
+
+// Scan commands typed in the console, the user takes over scanning.
+pub fn is_scan_command(line: &str) -> bool {
+    let line = line.trim().to_uppercase();
+    ["AT+GAPSCAN", "AT+FINDSCANDATA", "AT+SCANTARGET"].iter().any(|c| line.starts_with(c))
+}
+
+pub struct ScanScheduler {
+    config: ScanConfig,
+    targets: Vec<String>,           // "[0]D3:2A:7C:1B:4F:57"
+    filter: ScanFilter,
+    suspended: bool,
+    paused: bool,                   // for a GATT connection
+    scanning: bool,                 // state at the previous tick
+    next_start: Option<Instant>,
+    restart: bool,                  // start again without the pause, after a config change
+}
+
+impl ScanScheduler {
+    pub fn new(config: ScanConfig, targets: Vec<String>, filter: ScanFilter) -> Self {
+        Self { config, targets, filter, suspended: false, paused: false, scanning: false, next_start: None, restart: false }
+    }
+
+    fn following_targets(&self) -> bool {
+        self.config.targets_only && !self.targets.is_empty()
+    }
+
+    // The command line starting a sensor scan, without CR/LF.
+    pub fn command(&self) -> String {
+        let scan = if self.following_targets() {
+            DongleScan::Targets(self.targets.clone())
+        } else {
+            self.filter.dongle_scan().or_else(|| ScanFilter::default().dongle_scan()).unwrap_or(DongleScan::FindData(String::new()))
+        };
+        let scan = match scan {
+            DongleScan::FindData(pattern) => format!("AT+FINDSCANDATA={}", pattern),
+            DongleScan::Targets(targets) => format!("AT+SCANTARGET={}", targets.join(";")),
+        };
+        match self.config.duration {
+            Some(d) => format!("{}={}", scan, d),
+            None => scan,
+        }
+    }
+
+    // Use new settings. Returns true if the running scan should be stopped so
+    // the new settings take effect, a new one is started right away.
+    pub fn set_config(&mut self, config: ScanConfig, now: Instant) -> bool {
+        let changed = config != self.config;
+        self.config = config;
+        self.changed(changed, now)
+    }
+
+    // Use a new list of sensors, returns true like `set_config`.
+    pub fn set_targets(&mut self, targets: Vec<String>, now: Instant) -> bool {
+        let changed = targets != self.targets && self.config.targets_only;
+        self.targets = targets;
+        self.changed(changed, now)
+    }
+
+    // Use another filter, returns true like `set_config`.
+    pub fn set_filter(&mut self, filter: ScanFilter, now: Instant) -> bool {
+        let changed = filter != self.filter;
+        self.filter = filter;
+        self.changed(changed, now)
+    }
+
+    // Check a SF or ST line against the filter, the registered sensors need no check.
+    pub fn accepts(&self, addr: &str, rssi: Option<i16>, data: &str) -> bool {
+        self.following_targets() || self.filter.matches(addr, rssi, data)
+    }
+
+    fn changed(&mut self, changed: bool, now: Instant) -> bool {
+        if changed && self.scanning && !self.suspended {
+            self.restart = true;
+            return true;
+        }
+        if changed && self.next_start.is_some() {
+            self.next_start = Some(now);
+        }
+        false
+    }
+
+    pub fn suspend(&mut self) {
+        self.suspended = true;
+    }
+
+    // The sensor scan was started by the user.
+    pub fn resume(&mut self) {
+        self.suspended = false;
+    }
+
+    // Pause while a GATT connection is open. When it closes the scan is started
+    // again right away unless the user suspended it.
+    pub fn set_paused(&mut self, paused: bool, now: Instant) {
+        self.paused = paused;
+        if !paused && self.next_start.is_some() {
+            self.next_start = Some(now);
+        }
+    }
+
+    // Called regularly, returns the command to send if a scan should be started.
+    pub fn on_tick(&mut self, state: &LinkState, now: Instant) -> Option<String> {
+        match state {
+            LinkState::Scanning => {
+                self.scanning = true;
+                self.next_start = None;
+                None
+            }
+            LinkState::Ready => {
+                if self.scanning {
+                    // The scan ended.
+                    self.scanning = false;
+                    let pause = if self.restart { 0 } else { self.config.pause };
+                    self.restart = false;
+                    self.next_start = Some(now + Duration::from_secs(pause as u64));
+                } else if self.next_start.is_none() {
+                    // Just configured.
+                    self.next_start = Some(now);
+                }
+                if self.suspended || self.paused || self.next_start.is_some_and(|t| now < t) {
+                    return None;
+                }
+                self.next_start = Some(now + START_RETRY);
+                Some(self.command())
+            }
+            _ => {
+                self.scanning = false;
+                self.next_start = None;
+                None
+            }
+        }
+    }
+
+    pub fn status(&self, state: &LinkState, now: Instant) -> ScanStatus {
+        match state {
+            _ if self.paused => ScanStatus::Paused,
+            _ if self.suspended => ScanStatus::Suspended,
+            LinkState::Scanning => ScanStatus::Scanning,
+            LinkState::Ready => {
+                let left = self.next_start.map(|t| t.saturating_duration_since(now)).unwrap_or_default();
+                ScanStatus::Waiting(left.as_secs_f64().ceil() as u64)
+            }
+            _ => ScanStatus::Idle,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET: &str = "[1]C8:47:8C:00:00:01";
+
+    fn scheduler(duration: Option<u32>, pause: u32) -> ScanScheduler {
+        let config = ScanConfig { duration, pause, ..ScanConfig::default() };
+        ScanScheduler::new(config, vec![TARGET.to_string()], ScanFilter::default())
+    }
+
+    // Run a scan from start to end, returns when it ended.
+    fn scan(s: &mut ScanScheduler, now: Instant) -> Instant {
+        assert_eq!(s.on_tick(&LinkState::Ready, now), Some(s.command()));
+        assert_eq!(s.on_tick(&LinkState::Scanning, now), None);
+        let end = now + Duration::from_secs(30);
+        assert_eq!(s.on_tick(&LinkState::Ready, end), None);
+        end
+    }
+
+    #[test]
+    fn starts_when_ready() {
+        let mut s = scheduler(None, 5);
+        let now = Instant::now();
+        assert_eq!(s.on_tick(&LinkState::Configuring(crate::handshake::Step::Verbose), now), None);
+        assert_eq!(s.status(&LinkState::Opening, now), ScanStatus::Idle);
+        assert!(s.on_tick(&LinkState::Ready, now).is_some());
+        assert_eq!(s.status(&LinkState::Scanning, now), ScanStatus::Scanning);
+    }
+
+    #[test]
+    fn pauses_after_a_scan() {
+        let mut s = scheduler(Some(30), 5);
+        let end = scan(&mut s, Instant::now());
+        assert_eq!(s.status(&LinkState::Ready, end), ScanStatus::Waiting(5));
+        assert_eq!(s.on_tick(&LinkState::Ready, end + Duration::from_secs(4)), None);
+        assert_eq!(s.status(&LinkState::Ready, end + Duration::from_secs(4)), ScanStatus::Waiting(1));
+        assert_eq!(s.on_tick(&LinkState::Ready, end + Duration::from_secs(5)).as_deref(), Some("AT+FINDSCANDATA=FF5B07=30"));
+    }
+
+    #[test]
+    fn retries_a_scan_that_did_not_start() {
+        let mut s = scheduler(None, 5);
+        let now = Instant::now();
+        assert!(s.on_tick(&LinkState::Ready, now).is_some());
+        assert_eq!(s.on_tick(&LinkState::Ready, now + START_RETRY - Duration::from_secs(1)), None);
+        assert!(s.on_tick(&LinkState::Ready, now + START_RETRY).is_some());
+    }
+
+    #[test]
+    fn restarts_without_pause_after_a_change() {
+        let mut s = scheduler(None, 5);
+        let now = Instant::now();
+        assert!(s.on_tick(&LinkState::Ready, now).is_some());
+        assert_eq!(s.on_tick(&LinkState::Scanning, now), None);
+        // The same settings need no restart.
+        assert!(!s.set_config(s.config.clone(), now));
+        let filter = ScanFilter { manufacturer_id: None, service_uuid: Some(0xFCD2), ..ScanFilter::default() };
+        assert!(s.set_filter(filter, now));
+        let end = now + Duration::from_secs(1);
+        assert_eq!(s.on_tick(&LinkState::Ready, end).as_deref(), Some("AT+FINDSCANDATA=16D2FC"));
+
+        // Waiting for the next scan, a change starts it right away.
+        assert_eq!(s.on_tick(&LinkState::Scanning, end), None);
+        let end = end + Duration::from_secs(30);
+        assert_eq!(s.on_tick(&LinkState::Ready, end), None);
+        let config = ScanConfig { pause: 60, ..s.config.clone() };
+        assert!(!s.set_config(config, end));
+        assert!(s.on_tick(&LinkState::Ready, end).is_some());
+    }
+
+    #[test]
+    fn targets_only_restarts_when_the_sensors_change() {
+        let mut s = scheduler(None, 5);
+        let now = Instant::now();
+        assert!(!s.set_targets(vec![], now));
+        let config = ScanConfig { targets_only: true, ..s.config.clone() };
+        assert!(!s.set_config(config, now));
+        assert!(s.on_tick(&LinkState::Ready, now).is_some());
+        assert_eq!(s.on_tick(&LinkState::Scanning, now), None);
+        assert!(s.set_targets(vec![TARGET.to_string()], now));
+    }
+
+    #[test]
+    fn suspend_and_resume() {
+        let mut s = scheduler(None, 5);
+        let end = scan(&mut s, Instant::now());
+        s.suspend();
+        let later = end + Duration::from_secs(60);
+        assert_eq!(s.on_tick(&LinkState::Ready, later), None);
+        assert_eq!(s.status(&LinkState::Scanning, later), ScanStatus::Suspended);
+        // A change while the user scans does not stop their scan.
+        assert_eq!(s.on_tick(&LinkState::Scanning, later), None);
+        assert!(!s.set_filter(ScanFilter { min_rssi: Some(-80), ..ScanFilter::default() }, later));
+        s.resume();
+        assert_eq!(s.on_tick(&LinkState::Ready, later), None);
+        assert!(s.on_tick(&LinkState::Ready, later + Duration::from_secs(5)).is_some());
+    }
+
+    #[test]
+    fn paused_during_a_connection() {
+        let mut s = scheduler(None, 5);
+        let now = Instant::now();
+        assert!(s.on_tick(&LinkState::Ready, now).is_some());
+        assert_eq!(s.on_tick(&LinkState::Scanning, now), None);
+        s.set_paused(true, now);
+        assert_eq!(s.on_tick(&LinkState::Ready, now), None);
+        let later = now + Duration::from_secs(60);
+        assert_eq!(s.on_tick(&LinkState::Ready, later), None);
+        assert_eq!(s.status(&LinkState::Ready, later), ScanStatus::Paused);
+        s.set_paused(false, later);
+        assert!(s.on_tick(&LinkState::Ready, later).is_some());
+
+        // A scan suspended by the user stays suspended.
+        s.suspend();
+        s.set_paused(true, later);
+        s.set_paused(false, later);
+        assert_eq!(s.on_tick(&LinkState::Ready, later + Duration::from_secs(60)), None);
+        assert_eq!(s.status(&LinkState::Ready, later), ScanStatus::Suspended);
+    }
+
+    #[test]
+    fn command() {
+        let mut s = scheduler(None, 5);
+        assert_eq!(s.command(), "AT+FINDSCANDATA=FF5B07");
+        s.config.duration = Some(30);
+        assert_eq!(s.command(), "AT+FINDSCANDATA=FF5B07=30");
+        s.config.targets_only = true;
+        assert_eq!(s.command(), format!("AT+SCANTARGET={}=30", TARGET));
+        s.targets.push("[0]C8:47:8C:00:00:02".to_string());
+        s.config.duration = None;
+        assert_eq!(s.command(), format!("AT+SCANTARGET={};[0]C8:47:8C:00:00:02", TARGET));
+        // Without registered sensors the filter is used.
+        s.targets.clear();
+        s.filter = ScanFilter { manufacturer_id: None, name_prefix: Some("ATC".to_string()), ..ScanFilter::default() };
+        assert_eq!(s.command(), "AT+FINDSCANDATA=415443");
+        s.filter = ScanFilter { addresses: vec!["C8:47:8C:00:00:03".to_string()], ..ScanFilter::default() };
+        assert_eq!(s.command(), "AT+SCANTARGET=[0]C8:47:8C:00:00:03");
+        // A filter the dongle can not use falls back to the HibouAir one.
+        s.filter = ScanFilter { manufacturer_id: None, ..ScanFilter::default() };
+        assert_eq!(s.command(), "AT+FINDSCANDATA=FF5B07");
+    }
+
+    #[test]
+    fn scan_commands() {
+        assert!(is_scan_command(" at+gapscan"));
+        assert!(is_scan_command("AT+FINDSCANDATA=FF5B07"));
+        assert!(is_scan_command("AT+SCANTARGET=[0]C8:47:8C:00:00:01"));
+        assert!(!is_scan_command("AT+CENTRAL"));
+    }
+}
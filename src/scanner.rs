@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use serde_json::Value;
+
+use crate::advertising::{company_name, parse_ad_structures, parse_hex, AdStructure};
+
+// A BLE device seen by the dongle, combined from all its scan lines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScannedDevice {
+    pub addr: String,               // MAC address without the address type prefix
+    pub addr_type: Option<u8>,      // 0 = public, 1 = random (from "[1]C8:..." in GAPSCAN lines)
+    pub name: Option<String>,
+    pub rssi: Option<i16>,
+    pub adv_type: Option<u8>,
+    pub data: Vec<u8>,              // latest advertising data
+    pub ad: Vec<AdStructure>,       // `data` parsed
+    pub packets: u64,
+    pub last_seen: Instant,
+}
+
+impl ScannedDevice {
+    fn new(addr: String) -> Self {
+        Self {
+            addr,
+            addr_type: None,
+            name: None,
+            rssi: None,
+            adv_type: None,
+            data: Vec::new(),
+            ad: Vec::new(),
+            packets: 0,
+            last_seen: Instant::now(),
+        }
+    }
+
+    pub fn company_id(&self) -> Option<u16> {
+        self.ad.iter().find_map(|ad| match ad {
+            AdStructure::ManufacturerData { company_id, .. } => Some(*company_id),
+            _ => None,
+        })
+    }
+
+    pub fn company(&self) -> Option<&'static str> {
+        self.company_id().and_then(company_name)
+    }
+
+    pub fn tx_power(&self) -> Option<i8> {
+        self.ad.iter().find_map(|ad| match ad {
+            AdStructure::TxPower(p) => Some(*p),
+            _ => None,
+        })
+    }
+
+    // True if `filter` (lower case) is part of the address, name or company.
+    pub fn matches(&self, filter: &str) -> bool {
+        filter.is_empty()
+            || self.addr.to_lowercase().contains(filter)
+            || self.name.as_deref().is_some_and(|n| n.to_lowercase().contains(filter))
+            || self.company().is_some_and(|c| c.to_lowercase().contains(filter))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScannerSort {
+    Rssi,
+    Name,
+    Address,
+    LastSeen,
+}
+
+// Split "[1]C8:47:8C:00:00:01" into address type and address.
+pub fn split_addr(addr: &str) -> (Option<u8>, String) {
+    if let Some(rest) = addr.strip_prefix('[') {
+        if let Some((t, a)) = rest.split_once(']') {
+            return (t.parse().ok(), a.to_string());
+        }
+    }
+    (None, addr.to_string())
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scanner {
+    devices: HashMap<String, ScannedDevice>,
+}
+
+impl Scanner {
+    // Update from a S, SF or ST line. S lines (AT+GAPSCAN) carry name and RSSI,
+    // SF/ST lines the advertising data.
+    pub fn update(&mut self, v: &Value) {
+        let Some(addr) = v["addr"].as_str() else {
+            return;
+        };
+        let (addr_type, addr) = split_addr(addr);
+        let device = self
+            .devices
+            .entry(addr.clone())
+            .or_insert_with(|| ScannedDevice::new(addr));
+
+        if addr_type.is_some() {
+            device.addr_type = addr_type;
+        }
+        if let Some(name) = v["name"].as_str().filter(|n| !n.is_empty()) {
+            device.name = Some(name.to_string());
+        }
+        if let Some(rssi) = v["rssi"].as_i64() {
+            device.rssi = Some(rssi as i16);
+        }
+        if let Some(t) = v["type"].as_u64() {
+            device.adv_type = Some(t as u8);
+        }
+        if let Some(data) = v["data"].as_str().and_then(parse_hex) {
+            device.ad = parse_ad_structures(&data);
+            device.data = data;
+            // Fall back on the name in the advertising data if GAPSCAN did not give one.
+            if device.name.is_none() {
+                device.name = device.ad.iter().find_map(|ad| match ad {
+                    AdStructure::LocalName { name, .. } => Some(name.clone()),
+                    _ => None,
+                });
+            }
+        }
+        device.packets += 1;
+        device.last_seen = Instant::now();
+    }
+
+    pub fn clear(&mut self) {
+        self.devices.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.devices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
+
+    // Devices matching `filter`, sorted.
+    pub fn list(&self, filter: &str, sort: ScannerSort) -> Vec<ScannedDevice> {
+        let filter = filter.to_lowercase();
+        let mut list: Vec<ScannedDevice> = self
+            .devices
+            .values()
+            .filter(|d| d.matches(&filter))
+            .cloned()
+            .collect();
+        match sort {
+            ScannerSort::Rssi => list.sort_by_key(|d| std::cmp::Reverse(d.rssi.unwrap_or(i16::MIN))),
+            ScannerSort::Name => list.sort_by(|a, b| {
+                // Named devices first.
+                (a.name.is_none(), a.name.as_deref().map(str::to_lowercase), &a.addr)
+                    .cmp(&(b.name.is_none(), b.name.as_deref().map(str::to_lowercase), &b.addr))
+            }),
+            ScannerSort::Address => list.sort_by(|a, b| a.addr.cmp(&b.addr)),
+            ScannerSort::LastSeen => list.sort_by_key(|d| std::cmp::Reverse(d.last_seen)),
+        }
+        list
+    }
+}
@@ -0,0 +1,327 @@
+// The serial loop shared by the desktop app and the headless collector. Lines
+// from the transport go through the plain text parser, the request tracker,
+// the handshake and the scan scheduler, decoded readings go to the sinks and
+// everything a front end shows goes to its Frontend.
+
+use std::io;
+use std::path::PathBuf;
+
+use futures_util::{Stream, StreamExt};
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::time::{interval, sleep, Duration, Instant};
+use tracing::{debug, trace};
+
+use crate::bleuio::*;
+use crate::config::AppConfig;
+use crate::decoder::{DecoderRegistry, Reading};
+use crate::gatt::{self, GattEvent};
+use crate::handshake::{Action, Handshake};
+use crate::i18n::tr;
+use crate::plaintext::PlainTextParser;
+use crate::scan_schedule::{self, ScanScheduler, ScanStatus};
+use crate::sinks::SinkPipeline;
+use crate::transport::{TransportReader, TransportWriter};
+
+// How often the handshake checks for timeouts and the scan scheduler runs.
+pub const TICK: Duration = Duration::from_millis(250);
+// A plain text reply is taken as complete after this long without a line.
+const QUIET: Duration = Duration::from_secs(5);
+
+// What a front end does with what goes over the serial port. Everything is
+// optional, the collector only stores the readings.
+pub trait Frontend {
+    fn sent(&mut self, _line: &str) {}
+    fn received(&mut self, _line: &str, _kind: Option<BleuIOResponseType>) {}
+    // Every value from the dongle, after the serial loop has handled it.
+    fn value(&mut self, _kind: BleuIOResponseType, _v: &Value) {}
+    // The handshake's progress, everything but Action::Send.
+    fn action(&mut self, _action: Action) {}
+    fn ticked(&mut self, _scan: ScanStatus) {}
+    fn reading(&mut self, _reading: &Reading, _rssi: Option<i16>) {}
+    // Start (Some) or stop (None) recording a capture.
+    fn record(&mut self, _path: Option<PathBuf>) {}
+}
+
+pub struct SerialLoop<F> {
+    writer: TransportWriter,
+    plain: PlainTextParser,
+    requests: RequestTracker,
+    handshake: Handshake,
+    scheduler: ScanScheduler,
+    decoders: DecoderRegistry,
+    sinks: SinkPipeline,
+    frontend: F,
+}
+
+impl<F: Frontend> SerialLoop<F> {
+    pub fn new(writer: TransportWriter, model: Option<DongleModel>, config: &AppConfig, sinks: SinkPipeline, frontend: F) -> Self {
+        Self {
+            writer,
+            plain: PlainTextParser::default(),
+            requests: RequestTracker::default(),
+            handshake: Handshake::new(model, Instant::now()),
+            scheduler: ScanScheduler::new(config.scan.clone(), config.targets(), config.filter()),
+            decoders: DecoderRegistry::with_builtin(),
+            sinks,
+            frontend,
+        }
+    }
+
+    // Read from the dongle until the port or the command channel is closed. A
+    // replay already contains the dongle's answers, nothing is configured.
+    pub async fn run(&mut self, reader: TransportReader, mut commands: impl Stream<Item = BleuIOCommand> + Unpin, replay: bool) -> io::Result<()> {
+        // next_line keeps a partly read line when a tick or a command wins the
+        // select, read_line would lose it.
+        let mut lines = reader.lines();
+        let mut ticker = interval(TICK);
+        let quiet = sleep(QUIET);
+        tokio::pin!(quiet);
+
+        if !replay {
+            let actions = self.handshake.start(Instant::now());
+            self.run_actions(actions).await?;
+        }
+
+        loop {
+            tokio::select! {
+                line = lines.next_line() => {
+                    let Some(text) = line? else {
+                        return Ok(());
+                    };
+                    quiet.as_mut().reset(Instant::now() + QUIET);
+                    for v in self.parse(&text) {
+                        self.handle(v).await?;
+                    }
+                }
+
+                () = &mut quiet => {
+                    quiet.as_mut().reset(Instant::now() + QUIET);
+                    for v in self.plain.finish() {
+                        self.handle(v).await?;
+                    }
+                }
+
+                // Timeouts and retries of the handshake, restarts of the sensor scan.
+                _ = ticker.tick() => {
+                    let now = Instant::now();
+                    let actions = self.handshake.on_tick(now);
+                    self.run_actions(actions).await?;
+                    if let Some(cmd) = self.scheduler.on_tick(self.handshake.state(), now) {
+                        self.send(format!("{}\r\n", cmd).as_bytes()).await?;
+                    }
+                    self.frontend.ticked(self.scheduler.status(self.handshake.state(), now));
+                }
+
+                cmd = commands.next() => match cmd {
+                    Some(cmd) => self.execute(cmd).await?,
+                    None => return Ok(()),
+                },
+            }
+        }
+    }
+
+    // Lines that are not JSON come from non-verbose mode (or are malformed),
+    // the plain text parser turns them into the same values.
+    fn parse(&mut self, text: &str) -> Vec<Value> {
+        let values = match parse_bleuio_result(text) {
+            Ok(v) => {
+                self.plain.clear();
+                vec![v]
+            }
+            Err(_) => self.plain.parse_line(text),
+        };
+        trace!(target: "serial", "← {}", text);
+        self.frontend.received(text, values.last().map(get_bleuio_result_type));
+        values
+    }
+
+    async fn handle(&mut self, v: Value) -> io::Result<()> {
+        let t = get_bleuio_result_type(&v);
+        self.requests.handle(t, &v);
+        // The peripheral can end a GATT connection by itself.
+        if t == BleuIOResponseType::EventResponse && matches!(gatt::parse_event(&v), Some(GattEvent::Disconnected)) {
+            self.scheduler.set_paused(false, Instant::now());
+        }
+        let actions = self.handshake.on_value(t, &v, Instant::now());
+        self.run_actions(actions).await?;
+        self.frontend.value(t, &v);
+
+        // Sensor data, from AT+FINDSCANDATA or AT+SCANTARGET.
+        if !matches!(t, BleuIOResponseType::ScanFindDataResponse | BleuIOResponseType::ScanTargetResponse) {
+            return Ok(());
+        }
+        let data = v["data"].as_str().unwrap_or("");
+        let addr = v["addr"].as_str().unwrap_or("");
+        let rssi = v["rssi"].as_i64().map(|r| r as i16);
+        if !self.scheduler.accepts(addr, rssi, data) {
+            return Ok(());
+        }
+        trace!(target: "decode", addr, data, "{}", tr!("Sensor data"));
+        if let Some(reading) = self.decoders.decode_hex(addr, rssi, data) {
+            debug!(target: "decode", sensor = %reading.id, model = %reading.model, "{}", tr!("Decoded"));
+            self.frontend.reading(&reading, rssi);
+            self.sinks.publish(&reading);
+        }
+        Ok(())
+    }
+
+    // A line to the dongle, the plain text parser keeps track of the commands.
+    async fn send(&mut self, cmd: &[u8]) -> io::Result<()> {
+        let text = String::from_utf8_lossy(cmd);
+        trace!(target: "serial", "→ {}", text.trim_end());
+        self.plain.sent(&text);
+        self.frontend.sent(text.trim_end());
+        self.writer.write_all(cmd).await
+    }
+
+    // Carry out what the handshake asks for.
+    async fn run_actions(&mut self, actions: Vec<Action>) -> io::Result<()> {
+        for action in actions {
+            match action {
+                Action::Send(cmd) => self.send(cmd).await?,
+                action => self.frontend.action(action),
+            }
+        }
+        Ok(())
+    }
+
+    async fn execute(&mut self, cmd: BleuIOCommand) -> io::Result<()> {
+        debug!(target: "ui", "{}", tr!("Command {}", format!("{:?}", cmd)));
+        let now = Instant::now();
+        match cmd {
+            BleuIOCommand::At => self.send(AT).await?,
+            BleuIOCommand::AtI => self.send(b"ATI\r\n").await?,
+            BleuIOCommand::AtCentral => self.send(b"AT+CENTRAL\r\n").await?,
+            BleuIOCommand::AtShowRssi => self.send(AT_SHOWRSSI).await?,
+            BleuIOCommand::AtFindscandata => {
+                self.scheduler.resume();
+                self.send(format!("{}\r\n", self.scheduler.command()).as_bytes()).await?;
+            }
+            BleuIOCommand::AtGapScan => {
+                self.scheduler.suspend();
+                self.send(AT_GAPSCAN).await?;
+            }
+            BleuIOCommand::FindScanData(filter) => {
+                self.scheduler.suspend();
+                self.send(format!("AT+FINDSCANDATA={}\r\n", filter).as_bytes()).await?;
+            }
+            BleuIOCommand::StopScan => {
+                self.scheduler.suspend();
+                self.send(STOP_SCAN).await?;
+            }
+            BleuIOCommand::PauseScan => {
+                self.scheduler.set_paused(true, now);
+                self.send(STOP_SCAN).await?;
+            }
+            BleuIOCommand::ResumeScan => self.scheduler.set_paused(false, now),
+            BleuIOCommand::Raw(line) => {
+                if scan_schedule::is_scan_command(&line) {
+                    self.scheduler.suspend();
+                }
+                self.send(format!("{}\r\n", line).as_bytes()).await?;
+            }
+            BleuIOCommand::StartRecording(path) => self.frontend.record(Some(path)),
+            BleuIOCommand::StopRecording => self.frontend.record(None),
+            BleuIOCommand::Reinitialize => {
+                let actions = self.handshake.start(now);
+                self.run_actions(actions).await?;
+            }
+            BleuIOCommand::SetScanConfig(scan) => {
+                if self.scheduler.set_config(scan, now) {
+                    self.send(STOP_SCAN).await?;
+                }
+            }
+            BleuIOCommand::SetScanTargets(targets) => {
+                if self.scheduler.set_targets(targets, now) {
+                    self.send(STOP_SCAN).await?;
+                }
+            }
+            BleuIOCommand::SetScanFilter(filter) => {
+                if self.scheduler.set_filter(filter, now) {
+                    self.send(STOP_SCAN).await?;
+                }
+            }
+            BleuIOCommand::Request { line, reply } => {
+                self.requests.push(&line, reply);
+                self.send(format!("{}\r\n", line).as_bytes()).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::stream;
+    use tokio::io::{duplex, BufReader};
+
+    // The CO2 sensor of hibouair.rs.
+    const ADVERTISEMENT: &str = "0201061BFF5B07050422013FBD007D27E000BB00F419000000000000020A02";
+
+    #[derive(Default)]
+    struct Seen {
+        events: Vec<String>,
+    }
+
+    impl Frontend for Seen {
+        fn received(&mut self, line: &str, _kind: Option<BleuIOResponseType>) {
+            self.events.push(line.to_string());
+        }
+
+        fn ticked(&mut self, _scan: ScanStatus) {
+            if self.events.last().map(String::as_str) != Some("tick") {
+                self.events.push("tick".to_string());
+            }
+        }
+
+        fn reading(&mut self, reading: &Reading, _rssi: Option<i16>) {
+            self.events.push(format!("reading {}", reading.id));
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn line_split_by_a_tick() {
+        let line = format!(r#"{{"SF":1,"rssi":-61,"addr":"F5:50:35:CF:B1:ED","type":0,"data":"{}"}}"#, ADVERTISEMENT);
+        let (half, rest) = line.split_at(line.len() / 2);
+        let (mut port, reader) = duplex(256);
+        let mut serial = SerialLoop::new(Box::new(tokio::io::sink()), None, &AppConfig::default(), SinkPipeline::default(), Seen::default());
+
+        let dongle = async {
+            // Let the first tick pass, the next ones come while half the line is read.
+            sleep(TICK / 2).await;
+            port.write_all(half.as_bytes()).await.unwrap();
+            sleep(TICK * 3).await;
+            port.write_all(format!("{}\r\n", rest).as_bytes()).await.unwrap();
+            drop(port);
+        };
+        let (res, ()) = tokio::join!(serial.run(Box::new(BufReader::new(reader)), stream::pending(), true), dongle);
+        res.unwrap();
+
+        assert_eq!(serial.frontend.events, ["tick", line.as_str(), "reading 22013F"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn plain_reply_ends_when_quiet() {
+        let (mut port, reader) = duplex(256);
+        let mut serial = SerialLoop::new(Box::new(tokio::io::sink()), None, &AppConfig::default(), SinkPipeline::default(), Seen::default());
+        let (tx, rx) = futures_channel::mpsc::unbounded();
+        let (reply_tx, reply_rx) = futures_channel::oneshot::channel();
+
+        let dongle = async {
+            tx.unbounded_send(BleuIOCommand::Request { line: "AT+GETMAC".to_string(), reply: reply_tx }).unwrap();
+            sleep(TICK).await;
+            // Non-verbose mode has no E line, the reply ends when the dongle is quiet.
+            port.write_all(b"C8:47:8C:00:00:01\r\n").await.unwrap();
+            let reply = reply_rx.await.unwrap();
+            drop(tx);
+            reply
+        };
+        let (res, reply) = tokio::join!(serial.run(Box::new(BufReader::new(reader)), rx, true), dongle);
+        res.unwrap();
+
+        assert_eq!(reply.cmd, "AT+GETMAC");
+        assert!(reply.is_ok());
+    }
+}
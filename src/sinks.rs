@@ -0,0 +1,338 @@
+// Output sinks. Every decoded reading is published to the pipeline, which
+// hands a copy to each sink. A sink runs on a thread of its own with a bounded
+// queue in front of it, so a slow or failing sink (a database that is down, a
+// full disk) never holds up the serial task or the UI. When a queue is full
+// the sink's overflow policy decides which readings are lost.
+//
+// Sinks are listed in the "sinks" section of the settings. The HTTP API is
+// added as a sink when it is started. To add another kind of output, implement
+// `Sink` and add a `SinkKind` for it in config.rs.
+
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::config::{Overflow, SinkConfig, SinkKind};
+use crate::decoder::Reading;
+use crate::i18n::tr;
+
+pub trait Sink: Send + 'static {
+    // For messages, e.g. "InfluxDB http://localhost:8086".
+    fn name(&self) -> String;
+
+    // Write readings in the order they were decoded. An error is reported
+    // once, until a write succeeds again. With a flush interval this is also
+    // called with no readings, which must not fail by itself.
+    fn write(&mut self, readings: &[Reading]) -> Result<(), String>;
+
+    // Most readings passed to one write.
+    fn batch_size(&self) -> usize {
+        1
+    }
+
+    // Longest time a reading waits for the batch to fill up. The sink is then
+    // also called without readings, to retry what it has kept. None = write
+    // each reading as soon as it arrives.
+    fn flush_interval(&self) -> Option<Duration> {
+        None
+    }
+}
+
+struct QueueState {
+    readings: VecDeque<Reading>,
+    dropped: usize,
+}
+
+// The bounded queue between the pipeline and one sink thread.
+struct Queue {
+    state: Mutex<QueueState>,
+    ready: Condvar,
+    capacity: usize,
+    overflow: Overflow,
+}
+
+impl Queue {
+    fn new(capacity: usize, overflow: Overflow) -> Self {
+        Self {
+            state: Mutex::new(QueueState { readings: VecDeque::new(), dropped: 0 }),
+            ready: Condvar::new(),
+            capacity: capacity.max(1),
+            overflow,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn push(&self, reading: Reading) {
+        let mut state = self.lock();
+        if state.readings.len() >= self.capacity {
+            state.dropped += 1;
+            match self.overflow {
+                Overflow::DropOldest => {
+                    state.readings.pop_front();
+                }
+                Overflow::DropNewest => return,
+            }
+        }
+        state.readings.push_back(reading);
+        self.ready.notify_one();
+    }
+
+    // Wait for a full batch, or for the first reading when there is no wait.
+    // Also returns the number of readings lost since the last call.
+    fn take(&self, max: usize, wait: Option<Duration>) -> (Vec<Reading>, usize) {
+        let deadline = wait.map(|w| Instant::now() + w);
+        let mut state = self.lock();
+        loop {
+            let len = state.readings.len();
+            if len >= max || (deadline.is_none() && len > 0) {
+                break;
+            }
+            match deadline {
+                None => state = self.ready.wait(state).unwrap_or_else(|e| e.into_inner()),
+                Some(d) => {
+                    let now = Instant::now();
+                    if now >= d {
+                        break;
+                    }
+                    state = self.ready.wait_timeout(state, d - now).unwrap_or_else(|e| e.into_inner()).0;
+                }
+            }
+        }
+        let n = max.min(state.readings.len());
+        let batch = state.readings.drain(..n).collect();
+        (batch, std::mem::take(&mut state.dropped))
+    }
+}
+
+fn run(mut sink: Box<dyn Sink>, queue: Arc<Queue>) {
+    let name = sink.name();
+    let batch_size = sink.batch_size().max(1);
+    let wait = sink.flush_interval();
+    let mut failing = false;
+    loop {
+        let (batch, dropped) = queue.take(batch_size, wait);
+        if dropped > 0 {
+            warn!(target: "sink", sink = %name, dropped, "{}", tr!("Can not keep up, readings lost"));
+        }
+        match sink.write(&batch) {
+            Ok(()) if failing => {
+                info!(target: "sink", sink = %name, "{}", tr!("Working again"));
+                failing = false;
+            }
+            Ok(()) => {}
+            Err(e) => {
+                if !failing {
+                    warn!(target: "sink", sink = %name, "{}", e);
+                }
+                failing = true;
+            }
+        }
+    }
+}
+
+// Where the serial task publishes readings, cloned to wherever they are decoded.
+#[derive(Clone, Default)]
+pub struct SinkPipeline {
+    queues: Vec<Arc<Queue>>,
+}
+
+impl SinkPipeline {
+    // The enabled sinks of the settings. Sinks that can not be created are
+    // reported and left out.
+    pub fn from_config(sinks: &[SinkConfig]) -> Self {
+        let mut pipeline = Self::default();
+        for config in sinks.iter().filter(|s| s.enabled) {
+            match create(&config.kind) {
+                Ok(sink) => pipeline.add(sink, config.buffer, config.overflow),
+                Err(e) => warn!(target: "sink", "{}", tr!("Not using the sink {}: {}", format!("{:?}", config.kind), e)),
+            }
+        }
+        pipeline
+    }
+
+    // Start a thread for the sink.
+    pub fn add(&mut self, sink: Box<dyn Sink>, buffer: usize, overflow: Overflow) {
+        let queue = Arc::new(Queue::new(buffer, overflow));
+        let thread_queue = queue.clone();
+        std::thread::spawn(move || run(sink, thread_queue));
+        self.queues.push(queue);
+    }
+
+    // A new reading was decoded. Never blocks.
+    pub fn publish(&self, reading: &Reading) {
+        for queue in &self.queues {
+            queue.push(reading.clone());
+        }
+    }
+}
+
+fn create(kind: &SinkKind) -> Result<Box<dyn Sink>, String> {
+    match kind {
+        SinkKind::File { path } => Ok(Box::new(FileSink { path: path.clone() })),
+        #[cfg(feature = "influxdb")]
+        SinkKind::Influxdb(config) => Ok(Box::new(crate::influx::InfluxSink::new(*config.clone())?)),
+        #[cfg(not(feature = "influxdb"))]
+        SinkKind::Influxdb(_) => Err(tr!("built without the influxdb feature").to_string()),
+    }
+}
+
+// Appends every reading to a file as a line of JSON.
+struct FileSink {
+    path: PathBuf,
+}
+
+impl Sink for FileSink {
+    fn name(&self) -> String {
+        tr!("File {}", self.path.display())
+    }
+
+    fn write(&mut self, readings: &[Reading]) -> Result<(), String> {
+        if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path).map_err(|e| e.to_string())?;
+        for r in readings {
+            let values: serde_json::Map<_, _> = r.values.iter().map(|(m, v)| (format!("{:?}", m), json!(v))).collect();
+            let line = json!({
+                "id": r.id,
+                "address": r.address,
+                "model": r.model,
+                "t": r.at.timestamp(),
+                "rssi": r.rssi,
+                "values": values,
+            });
+            writeln!(file, "{}", line).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    use chrono::{Local, TimeZone};
+
+    use crate::decoder::Metric;
+
+    fn reading(t: i64) -> Reading {
+        Reading {
+            id: "sensor".to_string(),
+            address: "C8:47:8C:00:00:01".to_string(),
+            model: "HibouAir CO2".to_string(),
+            decoder: "HibouAir".to_string(),
+            at: Local.timestamp_opt(t, 0).unwrap(),
+            rssi: None,
+            values: vec![(Metric::Co2, 600.0)],
+            info: Vec::new(),
+        }
+    }
+
+    fn times(readings: &[Reading]) -> Vec<i64> {
+        readings.iter().map(|r| r.at.timestamp()).collect()
+    }
+
+    // Hands every write to the test.
+    struct Memory {
+        batch_size: usize,
+        flush_interval: Option<Duration>,
+        writes: mpsc::Sender<Vec<i64>>,
+    }
+
+    impl Sink for Memory {
+        fn name(&self) -> String {
+            "memory".to_string()
+        }
+
+        fn write(&mut self, readings: &[Reading]) -> Result<(), String> {
+            self.writes.send(times(readings)).map_err(|e| e.to_string())
+        }
+
+        fn batch_size(&self) -> usize {
+            self.batch_size
+        }
+
+        fn flush_interval(&self) -> Option<Duration> {
+            self.flush_interval
+        }
+    }
+
+    fn pipeline(batch_size: usize, flush_interval: Option<Duration>) -> (SinkPipeline, mpsc::Receiver<Vec<i64>>) {
+        let (writes, rx) = mpsc::channel();
+        let mut pipeline = SinkPipeline::default();
+        pipeline.add(Box::new(Memory { batch_size, flush_interval, writes }), 100, Overflow::DropOldest);
+        (pipeline, rx)
+    }
+
+    #[test]
+    fn drop_oldest_keeps_the_latest() {
+        let queue = Queue::new(3, Overflow::DropOldest);
+        (1..=5).for_each(|t| queue.push(reading(t)));
+        let (batch, dropped) = queue.take(10, Some(Duration::ZERO));
+        assert_eq!((times(&batch), dropped), (vec![3, 4, 5], 2));
+        assert_eq!(queue.take(10, Some(Duration::ZERO)).1, 0);
+    }
+
+    #[test]
+    fn drop_newest_keeps_the_first() {
+        let queue = Queue::new(3, Overflow::DropNewest);
+        (1..=5).for_each(|t| queue.push(reading(t)));
+        let (batch, dropped) = queue.take(10, Some(Duration::ZERO));
+        assert_eq!((times(&batch), dropped), (vec![1, 2, 3], 2));
+    }
+
+    #[test]
+    fn take_without_wait() {
+        let queue = Queue::new(10, Overflow::DropOldest);
+        (1..=3).for_each(|t| queue.push(reading(t)));
+        assert_eq!(times(&queue.take(2, None).0), [1, 2]);
+        assert_eq!(times(&queue.take(2, None).0), [3]);
+    }
+
+    #[test]
+    fn batches_up_to_the_batch_size() {
+        let (pipeline, writes) = pipeline(3, Some(Duration::from_secs(60)));
+        (1..=7).for_each(|t| pipeline.publish(&reading(t)));
+        let wait = Duration::from_secs(5);
+        assert_eq!(writes.recv_timeout(wait).unwrap(), [1, 2, 3]);
+        assert_eq!(writes.recv_timeout(wait).unwrap(), [4, 5, 6]);
+        // The last one waits for the batch to fill up.
+        assert!(writes.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+
+    #[test]
+    fn flush_interval_writes_what_there_is() {
+        let (pipeline, writes) = pipeline(10, Some(Duration::from_millis(20)));
+        pipeline.publish(&reading(1));
+        let wait = Duration::from_secs(5);
+        // The interval may run out before the reading is queued.
+        let first = std::iter::from_fn(|| writes.recv_timeout(wait).ok()).find(|w| !w.is_empty());
+        assert_eq!(first.unwrap(), [1]);
+        // Then the sink is called without readings, to retry what it has kept.
+        assert_eq!(writes.recv_timeout(wait).unwrap(), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn without_flush_interval_each_reading_is_written() {
+        let (pipeline, writes) = pipeline(10, None);
+        pipeline.publish(&reading(1));
+        pipeline.publish(&reading(2));
+        let wait = Duration::from_secs(5);
+        let mut written = writes.recv_timeout(wait).unwrap();
+        if written.len() < 2 {
+            written.extend(writes.recv_timeout(wait).unwrap());
+        }
+        assert_eq!(written, [1, 2]);
+        assert!(writes.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+}
@@ -0,0 +1,46 @@
+use std::io;
+use std::path::PathBuf;
+
+use serial2_tokio::SerialPort;
+use tokio::io::{AsyncBufRead, AsyncWrite, BufReader};
+
+pub type TransportReader = Box<dyn AsyncBufRead + Unpin + Send>;
+pub type TransportWriter = Box<dyn AsyncWrite + Unpin + Send>;
+
+// Where the BleuIO client gets its lines from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transport {
+    Serial { port_name: String },               // a real dongle
+    Replay { path: PathBuf, speed: f64 },       // a capture file, see capture.rs
+}
+
+impl Transport {
+    pub fn description(&self) -> String {
+        match self {
+            Transport::Serial { port_name } => port_name.clone(),
+            Transport::Replay { path, speed } => format!("{} ({}x)", path.display(), speed),
+        }
+    }
+
+    pub fn is_replay(&self) -> bool {
+        matches!(self, Transport::Replay { .. })
+    }
+
+    pub fn open(&self) -> io::Result<(TransportReader, TransportWriter)> {
+        match self {
+            Transport::Serial { port_name } => {
+                let port = SerialPort::open(port_name, 115200)?;
+                port.set_dtr(true).ok();
+                port.set_rts(true).ok();
+                // Dela upp porten i läsare och skrivare för att kunna använda båda i select!
+                let (reader, writer) = tokio::io::split(port);
+                Ok((Box::new(BufReader::new(reader)), Box::new(writer)))
+            }
+            Transport::Replay { path, speed } => {
+                let reader = crate::capture::replay(path, *speed)?;
+                // Commands go nowhere, the capture already contains the replies.
+                Ok((Box::new(reader), Box::new(tokio::io::sink())))
+            }
+        }
+    }
+}
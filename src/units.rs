@@ -0,0 +1,231 @@
+// Units for showing readings. Readings are decoded, stored in the history and
+// sent to sinks and the API in the units of `Metric::unit` (°C, hPa, lux);
+// they are only converted where they are shown or exported, to the units the
+// user picked. Like the language, the choice is read through a global signal
+// in components and from a lock elsewhere.
+
+use std::sync::RwLock;
+
+use dioxus::core::Runtime;
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::decoder::Metric;
+use crate::i18n;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemperatureUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PressureUnit {
+    #[default]
+    Hpa,
+    Inhg,
+    Mmhg,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LightUnit {
+    #[default]
+    Lux,
+    FootCandle,
+}
+
+impl TemperatureUnit {
+    pub const ALL: [TemperatureUnit; 2] = [TemperatureUnit::Celsius, TemperatureUnit::Fahrenheit];
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "°C",
+            TemperatureUnit::Fahrenheit => "°F",
+        }
+    }
+
+    // From °C.
+    pub fn convert(&self, celsius: f64) -> f64 {
+        match self {
+            TemperatureUnit::Celsius => celsius,
+            TemperatureUnit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+        }
+    }
+}
+
+impl PressureUnit {
+    pub const ALL: [PressureUnit; 3] = [PressureUnit::Hpa, PressureUnit::Inhg, PressureUnit::Mmhg];
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            PressureUnit::Hpa => "hPa",
+            PressureUnit::Inhg => "inHg",
+            PressureUnit::Mmhg => "mmHg",
+        }
+    }
+
+    // From hPa.
+    pub fn convert(&self, hpa: f64) -> f64 {
+        match self {
+            PressureUnit::Hpa => hpa,
+            PressureUnit::Inhg => hpa * 100.0 / 3386.389,
+            PressureUnit::Mmhg => hpa * 100.0 / 133.322387415,
+        }
+    }
+}
+
+impl LightUnit {
+    pub const ALL: [LightUnit; 2] = [LightUnit::Lux, LightUnit::FootCandle];
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            LightUnit::Lux => "lux",
+            LightUnit::FootCandle => "fc",
+        }
+    }
+
+    // From lux, one foot-candle is one lumen per square foot.
+    pub fn convert(&self, lux: f64) -> f64 {
+        match self {
+            LightUnit::Lux => lux,
+            LightUnit::FootCandle => lux * 0.09290304,
+        }
+    }
+}
+
+// The units picked by the user, kept in the settings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Units {
+    pub temperature: TemperatureUnit,
+    pub pressure: PressureUnit,
+    pub light: LightUnit,
+}
+
+impl Units {
+    const DEFAULT: Units = Units { temperature: TemperatureUnit::Celsius, pressure: PressureUnit::Hpa, light: LightUnit::Lux };
+
+    // A value of `metric` in these units.
+    pub fn convert(&self, metric: Metric, value: f64) -> f64 {
+        match metric {
+            Metric::Temperature => self.temperature.convert(value),
+            Metric::Pressure => self.pressure.convert(value),
+            Metric::Light => self.light.convert(value),
+            _ => value,
+        }
+    }
+
+    pub fn unit(&self, metric: Metric) -> &'static str {
+        match metric {
+            Metric::Temperature => self.temperature.symbol(),
+            Metric::Pressure => self.pressure.symbol(),
+            Metric::Light => self.light.symbol(),
+            _ => metric.unit(),
+        }
+    }
+
+    // Inches of mercury and foot-candles are larger units and need more decimals.
+    pub fn decimals(&self, metric: Metric) -> usize {
+        match (metric, self.pressure, self.light) {
+            (Metric::Pressure, PressureUnit::Inhg, _) => 2,
+            (Metric::Light, _, LightUnit::FootCandle) => 1,
+            _ => metric.decimals(),
+        }
+    }
+
+    // E.g. "71.6 °F", in the number format of the language in use.
+    pub fn format(&self, metric: Metric, value: f64) -> String {
+        format!("{} {}", i18n::number(self.convert(metric, value), self.decimals(metric)), self.unit(metric))
+    }
+}
+
+static CURRENT: RwLock<Units> = RwLock::new(Units::DEFAULT);
+static UNITS: GlobalSignal<Units> = Signal::global(stored_units);
+
+fn stored_units() -> Units {
+    *CURRENT.read().unwrap_or_else(|e| e.into_inner())
+}
+
+// The units in use. Read in a component, the component is rendered again when
+// they change.
+pub fn units() -> Units {
+    if Runtime::try_current().is_some() {
+        UNITS()
+    } else {
+        stored_units()
+    }
+}
+
+pub fn set_units(units: Units) {
+    *CURRENT.write().unwrap_or_else(|e| e.into_inner()) = units;
+    if Runtime::try_current().is_some() && *UNITS.peek() != units {
+        *UNITS.write() = units;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn temperature() {
+        let f = TemperatureUnit::Fahrenheit;
+        assert!(close(f.convert(0.0), 32.0));
+        assert!(close(f.convert(100.0), 212.0));
+        assert!(close(f.convert(-40.0), -40.0));
+        assert!(close(f.convert(21.5), 70.7));
+        assert!(close(TemperatureUnit::Celsius.convert(21.5), 21.5));
+    }
+
+    #[test]
+    fn pressure() {
+        // Standard atmosphere.
+        assert!((PressureUnit::Inhg.convert(1013.25) - 29.921).abs() < 0.001);
+        assert!((PressureUnit::Mmhg.convert(1013.25) - 760.0).abs() < 0.001);
+        assert!(close(PressureUnit::Hpa.convert(1013.25), 1013.25));
+    }
+
+    #[test]
+    fn light() {
+        assert!((LightUnit::FootCandle.convert(10.7639) - 1.0).abs() < 1e-4);
+        assert!((LightUnit::FootCandle.convert(500.0) - 46.45).abs() < 0.01);
+        assert!(close(LightUnit::Lux.convert(500.0), 500.0));
+    }
+
+    #[test]
+    fn other_metrics_are_not_converted() {
+        let units = Units { temperature: TemperatureUnit::Fahrenheit, pressure: PressureUnit::Mmhg, light: LightUnit::FootCandle };
+        for metric in [Metric::Co2, Metric::Humidity, Metric::Pm2_5, Metric::Voltage] {
+            assert!(close(units.convert(metric, 12.5), 12.5));
+            assert_eq!(units.unit(metric), metric.unit());
+            assert_eq!(units.decimals(metric), metric.decimals());
+        }
+    }
+
+    #[test]
+    fn format() {
+        let units = Units { temperature: TemperatureUnit::Fahrenheit, pressure: PressureUnit::Inhg, light: LightUnit::FootCandle };
+        assert_eq!(units.format(Metric::Temperature, 21.5), "70.7 °F");
+        assert_eq!(units.format(Metric::Pressure, 1013.25), "29.92 inHg");
+        assert_eq!(units.format(Metric::Light, 500.0), "46.5 fc");
+        assert_eq!(units.format(Metric::Co2, 612.0), "612 ppm");
+        let metric = Units { pressure: PressureUnit::Mmhg, ..Units::default() };
+        assert_eq!(metric.format(Metric::Pressure, 1013.25), "760 mmHg");
+        assert_eq!(metric.format(Metric::Temperature, 21.5), "21.5 °C");
+    }
+
+    #[test]
+    fn settings() {
+        let units: Units = serde_json::from_str(r#"{"temperature": "fahrenheit", "light": "footcandle"}"#).unwrap();
+        assert_eq!(units, Units { temperature: TemperatureUnit::Fahrenheit, pressure: PressureUnit::Hpa, light: LightUnit::FootCandle });
+        assert_eq!(serde_json::to_string(&Units::default()).unwrap(), r#"{"temperature":"celsius","pressure":"hpa","light":"lux"}"#);
+    }
+}
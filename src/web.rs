@@ -0,0 +1,212 @@
+// The dashboard as a web app, built with the `web` feature. It runs in the
+// browser and gets everything from the HTTP API of a collector (see api.rs),
+// which owns the dongle and serves this app as well.
+
+use std::collections::BTreeMap;
+
+use dioxus::prelude::*;
+use futures_util::StreamExt;
+use gloo_net::http::Request;
+use gloo_net::websocket::futures::WebSocket;
+use gloo_net::websocket::Message;
+use serde::de::DeserializeOwned;
+
+use crate::api_types::{ApiReading, ApiSample};
+use crate::dashboard::{HistoryChart, LanguageSelect, SensorPanel, UnitsSelect, HISTORY_RANGES};
+use crate::decoder::Reading;
+use crate::history::{self, Sample};
+use crate::i18n::{tr, Language};
+use crate::units::Units;
+
+const FAVICON: Asset = asset!("/assets/favicon.ico");
+const MAIN_CSS: Asset = asset!("/assets/main.css");
+const TAILWIND_CSS: Asset = asset!("/assets/tailwind.css");
+
+// Wait before connecting to the live feed again.
+const RECONNECT_MS: u32 = 5_000;
+// Where the chosen language is kept in the browser.
+const LANGUAGE_KEY: &str = "dxbleuio-language";
+// And the units, as JSON.
+const UNITS_KEY: &str = "dxbleuio-units";
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+// The language chosen earlier in this browser, or else the one of the browser.
+pub fn saved_language() -> Language {
+    local_storage()
+        .and_then(|s| s.get_item(LANGUAGE_KEY).ok()?)
+        .or_else(|| web_sys::window()?.navigator().language())
+        .and_then(|code| Language::from_code(&code))
+        .unwrap_or_default()
+}
+
+fn save_language(language: Language) {
+    if let Some(storage) = local_storage() {
+        storage.set_item(LANGUAGE_KEY, language.code()).ok();
+    }
+}
+
+// The units chosen earlier in this browser.
+pub fn saved_units() -> Units {
+    local_storage()
+        .and_then(|s| s.get_item(UNITS_KEY).ok()?)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_units(units: Units) {
+    if let (Some(storage), Ok(json)) = (local_storage(), serde_json::to_string(&units)) {
+        storage.set_item(UNITS_KEY, &json).ok();
+    }
+}
+
+async fn fetch_json<T: DeserializeOwned>(url: &str) -> Result<T, gloo_net::Error> {
+    Request::get(url).send().await?.json().await
+}
+
+// The WebSocket URL of the live feed on the server this page came from.
+fn live_url() -> String {
+    let location = web_sys::window().map(|w| w.location());
+    let host = location.as_ref().and_then(|l| l.host().ok()).unwrap_or_default();
+    let secure = location.and_then(|l| l.protocol().ok()).is_some_and(|p| p == "https:");
+    format!("{}://{}/api/live", if secure { "wss" } else { "ws" }, host)
+}
+
+// Sensor ids contain characters that are not allowed in a path segment.
+fn encode_path(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[component]
+pub fn WebApp() -> Element {
+    let mut readings = use_signal(BTreeMap::<String, Reading>::new);
+    let mut status = use_signal(String::new);
+
+    use_future(move || async move {
+        match fetch_json::<Vec<ApiReading>>("/api/sensors").await {
+            Ok(list) => readings.with_mut(|r| {
+                for reading in list {
+                    r.insert(reading.id.clone(), reading.into());
+                }
+            }),
+            Err(e) => status.set(tr!("Can not load the sensors: {}", e)),
+        }
+        // Follow the live feed, connecting again if the collector restarts.
+        loop {
+            match WebSocket::open(&live_url()) {
+                Ok(mut ws) => {
+                    status.set(String::new());
+                    while let Some(Ok(message)) = ws.next().await {
+                        if let Message::Text(text) = message {
+                            if let Ok(reading) = serde_json::from_str::<ApiReading>(&text) {
+                                readings.with_mut(|r| r.insert(reading.id.clone(), reading.into()));
+                            }
+                        }
+                    }
+                    status.set(tr!("Lost the connection to the collector, reconnecting...").to_string());
+                }
+                Err(e) => status.set(tr!("Can not connect to the live feed: {}", e)),
+            }
+            gloo_timers::future::TimeoutFuture::new(RECONNECT_MS).await;
+        }
+    });
+
+    rsx! {
+        document::Link { rel: "icon", href: FAVICON }
+        document::Link { rel: "stylesheet", href: MAIN_CSS }
+        document::Link { rel: "stylesheet", href: TAILWIND_CSS }
+        div {
+            class: "flex flex-col gap-8 p-4",
+            div {
+                class: "flex justify-end gap-2",
+                UnitsSelect { on_change: save_units }
+                LanguageSelect { on_change: save_language }
+            }
+            if !status().is_empty() {
+                div { class: "p-2 rounded-md bg-red-800 text-white", "{status}" }
+            }
+            if readings.read().is_empty() {
+                div { {tr!("No sensors seen yet.")} }
+            }
+            for reading in readings.read().values() {
+                div {
+                    key: "{reading.id}",
+                    SensorPanel { reading: reading.clone() }
+                    WebHistoryPanel { reading: reading.clone() }
+                }
+            }
+        }
+    }
+}
+
+// Like the desktop history panel, with the samples fetched from the collector.
+#[component]
+fn WebHistoryPanel(reading: Reading) -> Element {
+    let mut expanded = use_signal(|| false);
+    let mut metric = use_signal(|| reading.values.first().map(|(m, _)| *m));
+    let mut range = use_signal(|| HISTORY_RANGES[0].1);
+    let id = reading.id.clone();
+
+    let samples = use_resource(move || {
+        let id = id.clone();
+        async move {
+            if !expanded() {
+                return Ok(Vec::new());
+            }
+            let since = chrono::Local::now().timestamp() - range();
+            let url = format!("/api/sensors/{}/readings?from={}", encode_path(&id), since);
+            fetch_json::<Vec<ApiSample>>(&url).await.map(|s| s.into_iter().map(Sample::from).collect::<Vec<_>>())
+        }
+    });
+
+    let since = chrono::Local::now().timestamp() - range();
+    let metrics: Vec<_> = reading.values.iter().map(|(m, _)| *m).collect();
+    let chart = match (&*samples.read(), metric()) {
+        (Some(Ok(samples)), Some(m)) => Ok(Some((m, history::segments(samples.iter(), m, 3 * history::LIVE_INTERVAL)))),
+        (Some(Err(e)), _) => Err(e.to_string()),
+        _ => Ok(None),
+    };
+
+    rsx! {
+        div {
+            class: "px-4 py-2 bg-green-900 text-white text-sm",
+            button {
+                class: "underline",
+                onclick: move |_| expanded.toggle(),
+                {if expanded() { tr!("Hide history") } else { tr!("History") }}
+            }
+            if expanded() {
+                div {
+                    class: "flex gap-2 mt-2",
+                    for m in metrics {
+                        button {
+                            class: if metric() == Some(m) { "border px-1 rounded-md bg-green-700" } else { "border px-1 rounded-md" },
+                            onclick: move |_| metric.set(Some(m)),
+                            "{m.label()}"
+                        }
+                    }
+                    span { class: "flex-1" }
+                    for (label, seconds) in HISTORY_RANGES {
+                        button {
+                            class: if range() == seconds { "border px-1 rounded-md bg-green-700" } else { "border px-1 rounded-md" },
+                            onclick: move |_| range.set(seconds),
+                            {tr!(label)}
+                        }
+                    }
+                }
+                match chart {
+                    Ok(Some((metric, segments))) => rsx! { HistoryChart { metric, segments, since } },
+                    Ok(None) => rsx! { div { class: "mt-2", {tr!("Loading...")} } },
+                    Err(e) => rsx! { div { class: "mt-2", {tr!("Can not load the history: {}", e)} } },
+                }
+            }
+        }
+    }
+}